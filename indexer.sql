@@ -14,7 +14,8 @@ CREATE TYPE public.ticket_status AS ENUM (
     'WaitingForConfirmBySrc',
     'WaitingForConfirmByDest',
     'Finalized',
-    'Pending'
+    'Pending',
+    'Failed'
 );
 
 CREATE TYPE public.ticket_type AS ENUM (
@@ -34,6 +35,80 @@ SET default_tablespace = '';
 
 SET default_table_access_method = heap;
 
+CREATE TABLE public.amount_quarantine (
+    id bigint NOT NULL,
+    source_table character varying NOT NULL,
+    row_data json NOT NULL,
+    reason text NOT NULL,
+    quarantined_at timestamp with time zone DEFAULT now() NOT NULL
+);
+
+CREATE SEQUENCE public.amount_quarantine_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.amount_quarantine_id_seq OWNED BY public.amount_quarantine.id;
+
+CREATE TABLE public.api_key (
+    id bigint NOT NULL,
+    name character varying NOT NULL,
+    prefix character varying NOT NULL,
+    key_hash character varying NOT NULL,
+    rate_limit integer NOT NULL,
+    daily_quota bigint,
+    active boolean DEFAULT true NOT NULL,
+    created_at bigint NOT NULL
+);
+
+CREATE SEQUENCE public.api_key_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.api_key_id_seq OWNED BY public.api_key.id;
+
+CREATE TABLE public.api_key_usage (
+    api_key_id bigint NOT NULL,
+    date date NOT NULL,
+    request_count bigint DEFAULT 0 NOT NULL
+);
+
+CREATE TABLE public.bridge_fee_log (
+    chain_id character varying NOT NULL,
+    date character varying NOT NULL,
+    fee_token_id character varying NOT NULL,
+    amount numeric(78,0) NOT NULL,
+    total_ticket integer NOT NULL,
+    seqs character varying,
+    amount_human numeric
+);
+
+CREATE TABLE public.bridge_fee_log_detail (
+    chain_id character varying NOT NULL,
+    date character varying NOT NULL,
+    dst_chain character varying NOT NULL,
+    fee_token_id character varying NOT NULL,
+    amount numeric(78,0) NOT NULL,
+    amount_human numeric,
+    total_ticket integer NOT NULL
+);
+
+CREATE TABLE public.bridge_fee_log_dirty (
+    chain_id character varying NOT NULL,
+    date date NOT NULL
+);
+
+CREATE TABLE public.bridge_fee_log_ticket (
+    ticket_id character varying NOT NULL,
+    chain_id character varying NOT NULL,
+    date character varying NOT NULL
+);
+
 CREATE TABLE public.chain_meta (
     chain_id character varying NOT NULL,
     canister_id text NOT NULL,
@@ -44,6 +119,32 @@ CREATE TABLE public.chain_meta (
     fee_token character varying
 );
 
+CREATE TABLE public.route_volume (
+    src_chain character varying NOT NULL,
+    dst_chain character varying NOT NULL,
+    token character varying NOT NULL,
+    date date NOT NULL,
+    ticket_count bigint NOT NULL,
+    volume numeric(78,0) NOT NULL,
+    volume_human numeric,
+    fee_total numeric(78,0) DEFAULT 0 NOT NULL,
+    unique_senders bigint DEFAULT 0 NOT NULL,
+    unique_receivers bigint DEFAULT 0 NOT NULL
+);
+
+CREATE VIEW public.daily_stats AS
+ SELECT route_volume.date,
+    route_volume.src_chain,
+    route_volume.dst_chain,
+    route_volume.token,
+    route_volume.ticket_count,
+    route_volume.volume,
+    route_volume.volume_human,
+    route_volume.fee_total,
+    route_volume.unique_senders,
+    route_volume.unique_receivers
+   FROM public.route_volume;
+
 CREATE TABLE public.deleted_mint_ticket (
     ticket_id text NOT NULL,
     ticket_seq bigint,
@@ -59,9 +160,35 @@ CREATE TABLE public.deleted_mint_ticket (
     memo character varying,
     status public.ticket_status NOT NULL,
     tx_hash character varying,
-    date character varying NOT NULL
+    date character varying NOT NULL,
+    sub_status character varying,
+    sub_status_first_seen json
+);
+
+CREATE TABLE public.launchpad (
+    launchpad character varying NOT NULL,
+    cainister_id character varying NOT NULL
 );
 
+CREATE TABLE public.outbox (
+    id bigint NOT NULL,
+    topic character varying NOT NULL,
+    key text NOT NULL,
+    op character varying NOT NULL,
+    payload json NOT NULL,
+    created_at bigint NOT NULL,
+    published_at bigint
+);
+
+CREATE SEQUENCE public.outbox_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.outbox_id_seq OWNED BY public.outbox.id;
+
 CREATE TABLE public.pending_ticket (
     ticket_index integer NOT NULL
 );
@@ -73,24 +200,29 @@ CREATE SEQUENCE public.pending_ticket_ticket_index_seq
     NO MINVALUE
     NO MAXVALUE
     CACHE 1;
-    
+
 ALTER SEQUENCE public.pending_ticket_ticket_index_seq OWNED BY public.pending_ticket.ticket_index;
-ALTER TABLE ONLY public.pending_ticket ALTER COLUMN ticket_index SET DEFAULT nextval('public.pending_ticket_ticket_index_seq'::regclass);
-COPY public.pending_ticket (ticket_index) FROM stdin;
-\.
-SELECT pg_catalog.setval('public.pending_ticket_ticket_index_seq', 1, false);
+
+CREATE TABLE public.route_volume_dirty (
+    date date NOT NULL
+);
+
+CREATE TABLE public.rune_terms (
+    rune_id character varying NOT NULL,
+    terms_amount bigint NOT NULL
+);
 
 CREATE TABLE public.seaql_migrations (
     version character varying NOT NULL,
     applied_at bigint NOT NULL
 );
 
-COPY public.seaql_migrations (version, applied_at) FROM stdin;
-m20240507_055143_one	1728346779
-m20240701_000001_two	1728346779
-m20240802_000001_three	1728346779
-m20250111_000001_four	1736654929
-\.
+CREATE TABLE public.sender_resolution (
+    ticket_id character varying NOT NULL,
+    attempts integer DEFAULT 0 NOT NULL,
+    last_error text,
+    last_attempt_at bigint NOT NULL
+);
 
 CREATE TABLE public.ticket (
     ticket_id text NOT NULL,
@@ -101,17 +233,88 @@ CREATE TABLE public.ticket (
     dst_chain character varying NOT NULL,
     action public.tx_action NOT NULL,
     token character varying NOT NULL,
-    amount character varying NOT NULL,
+    amount numeric(78,0),
     sender character varying,
     receiver character varying NOT NULL,
     memo character varying,
     status public.ticket_status NOT NULL,
     tx_hash character varying,
     intermediate_tx_hash character varying,
-    bridge_fee character varying,
-    date character varying NOT NULL
+    bridge_fee numeric(78,0),
+    date character varying NOT NULL,
+    sub_status character varying,
+    sub_status_first_seen json,
+    confirmations integer,
+    block_height bigint,
+    memo_hex text,
+    memo_json json,
+    referral_code character varying,
+    fee_token character varying,
+    parse_error text,
+    amount_human numeric
+);
+
+CREATE VIEW public.ticket_hash AS
+ SELECT ticket.ticket_id AS hash,
+    ticket.ticket_id,
+    'ticket_id'::text AS kind,
+    false AS archived
+   FROM public.ticket
+UNION ALL
+ SELECT (ticket.tx_hash)::text AS hash,
+    ticket.ticket_id,
+    'tx_hash'::text AS kind,
+    false AS archived
+   FROM public.ticket
+  WHERE (ticket.tx_hash IS NOT NULL)
+UNION ALL
+ SELECT (ticket.intermediate_tx_hash)::text AS hash,
+    ticket.ticket_id,
+    'intermediate_tx_hash'::text AS kind,
+    false AS archived
+   FROM public.ticket
+  WHERE (ticket.intermediate_tx_hash IS NOT NULL)
+UNION ALL
+ SELECT deleted_mint_ticket.ticket_id AS hash,
+    deleted_mint_ticket.ticket_id,
+    'ticket_id'::text AS kind,
+    true AS archived
+   FROM public.deleted_mint_ticket
+UNION ALL
+ SELECT (deleted_mint_ticket.tx_hash)::text AS hash,
+    deleted_mint_ticket.ticket_id,
+    'tx_hash'::text AS kind,
+    true AS archived
+   FROM public.deleted_mint_ticket
+  WHERE (deleted_mint_ticket.tx_hash IS NOT NULL);
+
+CREATE TABLE public.ticket_link (
+    parent_id character varying NOT NULL,
+    child_id character varying NOT NULL,
+    kind character varying NOT NULL
+);
+
+CREATE TABLE public.ticket_transition (
+    id bigint NOT NULL,
+    ticket_id text NOT NULL,
+    kind character varying NOT NULL,
+    status character varying NOT NULL,
+    previous_status character varying,
+    tx_hash character varying,
+    intermediate_tx_hash character varying,
+    occurred_at bigint NOT NULL,
+    inferred boolean DEFAULT false NOT NULL
 );
 
+CREATE SEQUENCE public.ticket_transition_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.ticket_transition_id_seq OWNED BY public.ticket_transition.id;
+
 CREATE TABLE public.token_ledger_id_on_chain (
     chain_id character varying NOT NULL,
     token_id character varying NOT NULL,
@@ -126,51 +329,178 @@ CREATE TABLE public.token_meta (
     decimals smallint NOT NULL,
     icon text,
     metadata json NOT NULL,
-    dst_chains json NOT NULL
+    dst_chains json NOT NULL,
+    launchpad character varying
 );
 
 CREATE TABLE public.token_on_chain (
     chain_id character varying NOT NULL,
     token_id character varying NOT NULL,
-    amount character varying NOT NULL
+    amount numeric(78,0) NOT NULL,
+    amount_human numeric
 );
 
 CREATE TABLE public.token_volume (
     token_id character varying NOT NULL,
     ticket_count character varying NOT NULL,
-    historical_volume character varying NOT NULL
+    historical_volume numeric(78,0) NOT NULL,
+    historical_volume_human numeric
 );
 
-CREATE TABLE public.bridge_fee_log (
-    chain_id character varying NOT NULL,
-    date character varying NOT NULL,
-    fee_token_id character varying NOT NULL,
-    amount character varying NOT NULL,
-    total_ticket integer NOT NULL,
-    seqs character varying NOT NULL
+CREATE TABLE public.webhook_delivery (
+    id bigint NOT NULL,
+    endpoint_id bigint NOT NULL,
+    event character varying NOT NULL,
+    ticket_id text NOT NULL,
+    payload json NOT NULL,
+    status character varying DEFAULT 'pending'::character varying NOT NULL,
+    attempts integer DEFAULT 0 NOT NULL,
+    next_attempt_at bigint NOT NULL,
+    last_error text,
+    delivered_at bigint,
+    created_at bigint NOT NULL
 );
 
+CREATE SEQUENCE public.webhook_delivery_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.webhook_delivery_id_seq OWNED BY public.webhook_delivery.id;
+
+CREATE TABLE public.webhook_endpoint (
+    id bigint NOT NULL,
+    url text NOT NULL,
+    secret text NOT NULL,
+    chain character varying,
+    token character varying,
+    address character varying,
+    active boolean DEFAULT true NOT NULL,
+    created_at bigint NOT NULL
+);
+
+CREATE SEQUENCE public.webhook_endpoint_id_seq
+    START WITH 1
+    INCREMENT BY 1
+    NO MINVALUE
+    NO MAXVALUE
+    CACHE 1;
+
+ALTER SEQUENCE public.webhook_endpoint_id_seq OWNED BY public.webhook_endpoint.id;
+
+ALTER TABLE ONLY public.amount_quarantine ALTER COLUMN id SET DEFAULT nextval('public.amount_quarantine_id_seq'::regclass);
+
+ALTER TABLE ONLY public.api_key ALTER COLUMN id SET DEFAULT nextval('public.api_key_id_seq'::regclass);
+
+ALTER TABLE ONLY public.outbox ALTER COLUMN id SET DEFAULT nextval('public.outbox_id_seq'::regclass);
+
+ALTER TABLE ONLY public.pending_ticket ALTER COLUMN ticket_index SET DEFAULT nextval('public.pending_ticket_ticket_index_seq'::regclass);
+
+ALTER TABLE ONLY public.ticket_transition ALTER COLUMN id SET DEFAULT nextval('public.ticket_transition_id_seq'::regclass);
+
+ALTER TABLE ONLY public.webhook_delivery ALTER COLUMN id SET DEFAULT nextval('public.webhook_delivery_id_seq'::regclass);
+
+ALTER TABLE ONLY public.webhook_endpoint ALTER COLUMN id SET DEFAULT nextval('public.webhook_endpoint_id_seq'::regclass);
+
+COPY public.pending_ticket (ticket_index) FROM stdin;
+\.
+
+COPY public.seaql_migrations (version, applied_at) FROM stdin;
+m20240507_055143_one	1792377805
+m20240701_000001_two	1792377805
+m20240802_000001_three	1792377805
+m20250111_000001_four	1792377805
+m20261018_000001_five	1792377805
+m20261018_000002_six	1792377805
+m20261018_000003_seven	1792377805
+m20261018_000004_eight	1792377805
+m20261018_000005_nine	1792377805
+m20261018_000006_ten	1792377805
+m20261018_000007_eleven	1792377805
+m20261018_000008_twelve	1792377805
+m20261018_000009_thirteen	1792377805
+m20261018_000010_fourteen	1792377805
+m20261018_000011_fifteen	1792377805
+m20261019_000001_sixteen	1792377805
+m20261019_000002_seventeen	1792377805
+m20261019_000003_eighteen	1792377805
+m20261019_000004_nineteen	1792377805
+m20261019_000005_twenty	1792377805
+m20261019_000006_twentyone	1792377805
+\.
+
+SELECT pg_catalog.setval('public.pending_ticket_ticket_index_seq', 1, false);
+
+ALTER TABLE ONLY public.amount_quarantine
+    ADD CONSTRAINT amount_quarantine_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.api_key
+    ADD CONSTRAINT api_key_key_hash_key UNIQUE (key_hash);
+
+ALTER TABLE ONLY public.api_key
+    ADD CONSTRAINT api_key_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.bridge_fee_log_ticket
+    ADD CONSTRAINT bridge_fee_log_ticket_pkey PRIMARY KEY (ticket_id);
+
 ALTER TABLE ONLY public.chain_meta
     ADD CONSTRAINT chain_meta_pkey PRIMARY KEY (chain_id);
 
 ALTER TABLE ONLY public.deleted_mint_ticket
     ADD CONSTRAINT deleted_mint_ticket_pkey PRIMARY KEY (ticket_id);
 
+ALTER TABLE ONLY public.launchpad
+    ADD CONSTRAINT launchpad_pkey PRIMARY KEY (launchpad);
+
+ALTER TABLE ONLY public.outbox
+    ADD CONSTRAINT outbox_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.pending_ticket
+    ADD CONSTRAINT pending_ticket_pkey PRIMARY KEY (ticket_index);
+
+ALTER TABLE ONLY public.api_key_usage
+    ADD CONSTRAINT "pk-api_key_usage" PRIMARY KEY (api_key_id, date);
+
+ALTER TABLE ONLY public.bridge_fee_log
+    ADD CONSTRAINT pk_bridge_fee_log PRIMARY KEY (chain_id, date);
+
+ALTER TABLE ONLY public.bridge_fee_log_detail
+    ADD CONSTRAINT pk_bridge_fee_log_detail PRIMARY KEY (chain_id, date, dst_chain, fee_token_id);
+
+ALTER TABLE ONLY public.bridge_fee_log_dirty
+    ADD CONSTRAINT pk_bridge_fee_log_dirty PRIMARY KEY (chain_id, date);
+
 ALTER TABLE ONLY public.token_on_chain
     ADD CONSTRAINT pk_chain_token PRIMARY KEY (chain_id, token_id);
 
-
 ALTER TABLE ONLY public.token_ledger_id_on_chain
     ADD CONSTRAINT pk_chain_token_contract PRIMARY KEY (chain_id, token_id);
 
+ALTER TABLE ONLY public.route_volume
+    ADD CONSTRAINT pk_route_volume PRIMARY KEY (src_chain, dst_chain, token, date);
+
+ALTER TABLE ONLY public.ticket_link
+    ADD CONSTRAINT pk_ticket_link PRIMARY KEY (parent_id, child_id);
+
+ALTER TABLE ONLY public.route_volume_dirty
+    ADD CONSTRAINT route_volume_dirty_pkey PRIMARY KEY (date);
+
+ALTER TABLE ONLY public.rune_terms
+    ADD CONSTRAINT rune_terms_pkey PRIMARY KEY (rune_id);
 
 ALTER TABLE ONLY public.seaql_migrations
     ADD CONSTRAINT seaql_migrations_pkey PRIMARY KEY (version);
 
+ALTER TABLE ONLY public.sender_resolution
+    ADD CONSTRAINT sender_resolution_pkey PRIMARY KEY (ticket_id);
 
 ALTER TABLE ONLY public.ticket
     ADD CONSTRAINT ticket_pkey PRIMARY KEY (ticket_id);
 
+ALTER TABLE ONLY public.ticket_transition
+    ADD CONSTRAINT ticket_transition_pkey PRIMARY KEY (id);
 
 ALTER TABLE ONLY public.token_meta
     ADD CONSTRAINT token_meta_pkey PRIMARY KEY (token_id);
@@ -178,33 +508,67 @@ ALTER TABLE ONLY public.token_meta
 ALTER TABLE ONLY public.token_volume
     ADD CONSTRAINT token_volume_pkey PRIMARY KEY (token_id);
 
+ALTER TABLE ONLY public.webhook_delivery
+    ADD CONSTRAINT webhook_delivery_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.webhook_endpoint
+    ADD CONSTRAINT webhook_endpoint_pkey PRIMARY KEY (id);
+
+CREATE INDEX "idx-bridge_fee_log_ticket_chain_id_date" ON public.bridge_fee_log_ticket USING btree (chain_id, date);
+
+CREATE INDEX "idx-deleted_mint_ticket_tx_hash" ON public.deleted_mint_ticket USING btree (tx_hash);
+
 CREATE INDEX "idx-mint-ticket_seq" ON public.deleted_mint_ticket USING btree (ticket_seq);
 
+CREATE INDEX "idx-outbox_unpublished" ON public.outbox USING btree (id) WHERE (published_at IS NULL);
+
+CREATE INDEX "idx-route_volume_token_date" ON public.route_volume USING btree (token, date);
+
+CREATE INDEX "idx-ticket_intermediate_tx_hash" ON public.ticket USING btree (intermediate_tx_hash);
+
+CREATE INDEX "idx-ticket_link_child_id" ON public.ticket_link USING btree (child_id);
+
+CREATE INDEX "idx-ticket_lower_receiver_time_id" ON public.ticket USING btree (lower((receiver)::text), ticket_time, ticket_id);
+
+CREATE INDEX "idx-ticket_lower_sender_time_id" ON public.ticket USING btree (lower((sender)::text), ticket_time, ticket_id);
+
+CREATE INDEX "idx-ticket_receiver_time_id" ON public.ticket USING btree (receiver, ticket_time, ticket_id);
+
+CREATE INDEX "idx-ticket_sender_time_id" ON public.ticket USING btree (sender, ticket_time, ticket_id);
+
 CREATE INDEX "idx-ticket_seq" ON public.ticket USING btree (ticket_seq);
 
-ALTER TABLE ONLY public.token_on_chain
-    ADD CONSTRAINT fk_chain_id FOREIGN KEY (chain_id) REFERENCES public.chain_meta(chain_id);
+CREATE INDEX "idx-ticket_time_id" ON public.ticket USING btree (ticket_time, ticket_id);
+
+CREATE INDEX "idx-ticket_transition_ticket_id" ON public.ticket_transition USING btree (ticket_id, id);
+
+CREATE INDEX "idx-ticket_tx_hash" ON public.ticket USING btree (tx_hash);
+
+CREATE INDEX "idx-webhook_delivery_status_next_attempt_at" ON public.webhook_delivery USING btree (status, next_attempt_at);
 
+ALTER TABLE ONLY public.api_key_usage
+    ADD CONSTRAINT "fk-api_key_usage-api_key_id" FOREIGN KEY (api_key_id) REFERENCES public.api_key(id) ON DELETE CASCADE;
+
+ALTER TABLE ONLY public.webhook_delivery
+    ADD CONSTRAINT "fk-webhook_delivery-endpoint_id" FOREIGN KEY (endpoint_id) REFERENCES public.webhook_endpoint(id) ON DELETE CASCADE;
 
 ALTER TABLE ONLY public.token_ledger_id_on_chain
     ADD CONSTRAINT fk_chain_id FOREIGN KEY (chain_id) REFERENCES public.chain_meta(chain_id);
 
-
 ALTER TABLE ONLY public.token_on_chain
-    ADD CONSTRAINT fk_token_id FOREIGN KEY (token_id) REFERENCES public.token_meta(token_id);
+    ADD CONSTRAINT fk_chain_id FOREIGN KEY (chain_id) REFERENCES public.chain_meta(chain_id);
+
+ALTER TABLE ONLY public.token_meta
+    ADD CONSTRAINT fk_launch_pad FOREIGN KEY (launchpad) REFERENCES public.launchpad(launchpad);
 
+ALTER TABLE ONLY public.bridge_fee_log
+    ADD CONSTRAINT fk_log_chain_id FOREIGN KEY (chain_id) REFERENCES public.chain_meta(chain_id);
 
 ALTER TABLE ONLY public.token_ledger_id_on_chain
     ADD CONSTRAINT fk_token_id FOREIGN KEY (token_id) REFERENCES public.token_meta(token_id);
 
-ALTER TABLE ONLY public.pending_ticket
-    ADD CONSTRAINT pending_ticket_pkey PRIMARY KEY (ticket_index);
+ALTER TABLE ONLY public.token_on_chain
+    ADD CONSTRAINT fk_token_id FOREIGN KEY (token_id) REFERENCES public.token_meta(token_id);
 
 ALTER TABLE ONLY public.token_volume
     ADD CONSTRAINT fk_token_id_volume FOREIGN KEY (token_id) REFERENCES public.token_meta(token_id);
-
-ALTER TABLE ONLY public.bridge_fee_log
-    ADD CONSTRAINT pk_bridge_fee_log PRIMARY KEY (chain_id, date);
-
-ALTER TABLE ONLY public.bridge_fee_log
-    ADD CONSTRAINT fk_log_chain_id FOREIGN KEY (chain_id) REFERENCES public.chain_meta(chain_id);
\ No newline at end of file
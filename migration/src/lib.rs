@@ -3,6 +3,23 @@ mod m20240507_055143_one;
 mod m20240701_000001_two;
 mod m20240802_000001_three;
 mod m20250111_000001_four;
+mod m20261018_000001_five;
+mod m20261018_000002_six;
+mod m20261018_000003_seven;
+mod m20261018_000004_eight;
+mod m20261018_000005_nine;
+mod m20261018_000006_ten;
+mod m20261018_000007_eleven;
+mod m20261018_000008_twelve;
+mod m20261018_000009_thirteen;
+mod m20261018_000010_fourteen;
+mod m20261018_000011_fifteen;
+mod m20261019_000001_sixteen;
+mod m20261019_000002_seventeen;
+mod m20261019_000003_eighteen;
+mod m20261019_000004_nineteen;
+mod m20261019_000005_twenty;
+mod m20261019_000006_twentyone;
 
 pub struct Migrator;
 
@@ -14,6 +31,23 @@ impl MigratorTrait for Migrator {
 			Box::new(m20240701_000001_two::Migration),
 			Box::new(m20240802_000001_three::Migration),
 			Box::new(m20250111_000001_four::Migration),
+			Box::new(m20261018_000001_five::Migration),
+			Box::new(m20261018_000002_six::Migration),
+			Box::new(m20261018_000003_seven::Migration),
+			Box::new(m20261018_000004_eight::Migration),
+			Box::new(m20261018_000005_nine::Migration),
+			Box::new(m20261018_000006_ten::Migration),
+			Box::new(m20261018_000007_eleven::Migration),
+			Box::new(m20261018_000008_twelve::Migration),
+			Box::new(m20261018_000009_thirteen::Migration),
+			Box::new(m20261018_000010_fourteen::Migration),
+			Box::new(m20261018_000011_fifteen::Migration),
+			Box::new(m20261019_000001_sixteen::Migration),
+			Box::new(m20261019_000002_seventeen::Migration),
+			Box::new(m20261019_000003_eighteen::Migration),
+			Box::new(m20261019_000004_nineteen::Migration),
+			Box::new(m20261019_000005_twenty::Migration),
+			Box::new(m20261019_000006_twentyone::Migration),
 		]
 	}
 }
@@ -0,0 +1,41 @@
+use super::m20240507_055143_one::Ticket;
+use super::m20240802_000001_three::DeletedMintTicket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// in-flight progress reported by the custom canisters, e.g. Signing or Submitted. archived
+		// transfers still wait for their release, so they keep it too
+		for table in [
+			Ticket::Table.into_iden(),
+			DeletedMintTicket::Table.into_iden(),
+		] {
+			manager
+				.alter_table(
+					Table::alter()
+						.table(table)
+						.add_column_if_not_exists(
+							ColumnDef::new(TicketSubStatus::SubStatus).string().null(),
+						)
+						.add_column_if_not_exists(
+							ColumnDef::new(TicketSubStatus::SubStatusFirstSeen)
+								.json()
+								.null(),
+						)
+						.to_owned(),
+				)
+				.await?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum TicketSubStatus {
+	SubStatus,
+	SubStatusFirstSeen,
+}
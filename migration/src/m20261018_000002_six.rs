@@ -0,0 +1,35 @@
+use super::m20240507_055143_one::Ticket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// confirmation depth of the release tx on its destination chain
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Ticket::Table)
+					.add_column_if_not_exists(
+						ColumnDef::new(TicketConfirmation::Confirmations)
+							.integer()
+							.null(),
+					)
+					.add_column_if_not_exists(
+						ColumnDef::new(TicketConfirmation::BlockHeight)
+							.big_integer()
+							.null(),
+					)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum TicketConfirmation {
+	Confirmations,
+	BlockHeight,
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// failed sender lookups, tickets past the attempt limit are no longer retried
+		manager
+			.create_table(
+				Table::create()
+					.table(SenderResolution::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(SenderResolution::TicketId)
+							.string()
+							.not_null()
+							.primary_key(),
+					)
+					.col(
+						ColumnDef::new(SenderResolution::Attempts)
+							.integer()
+							.not_null()
+							.default(0),
+					)
+					.col(ColumnDef::new(SenderResolution::LastError).text().null())
+					.col(
+						ColumnDef::new(SenderResolution::LastAttemptAt)
+							.big_integer()
+							.not_null(),
+					)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum SenderResolution {
+	Table,
+	TicketId,
+	Attempts,
+	LastError,
+	LastAttemptAt,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the mint terms of a rune never change once it is etched
+		manager
+			.create_table(
+				Table::create()
+					.table(RuneTerms::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(RuneTerms::RuneId)
+							.string()
+							.not_null()
+							.primary_key(),
+					)
+					.col(
+						ColumnDef::new(RuneTerms::TermsAmount)
+							.big_integer()
+							.not_null(),
+					)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum RuneTerms {
+	Table,
+	RuneId,
+	TermsAmount,
+}
@@ -0,0 +1,103 @@
+use super::m20240507_055143_one::Ticket;
+use super::m20240802_000001_three::DeletedMintTicket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// parent/child edges between the tickets of a multi-hop transfer
+		manager
+			.create_table(
+				Table::create()
+					.table(TicketLink::Table)
+					.if_not_exists()
+					.col(ColumnDef::new(TicketLink::ParentId).string().not_null())
+					.col(ColumnDef::new(TicketLink::ChildId).string().not_null())
+					.col(ColumnDef::new(TicketLink::Kind).string().not_null())
+					.primary_key(
+						Index::create()
+							.name("pk_ticket_link")
+							.col(TicketLink::ParentId)
+							.col(TicketLink::ChildId)
+							.primary(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// the parents of a ticket are found by its id in their hashes
+		for index in [
+			Index::create()
+				.if_not_exists()
+				.name("idx-ticket_link_child_id")
+				.table(TicketLink::Table)
+				.col(TicketLink::ChildId)
+				.to_owned(),
+			Index::create()
+				.if_not_exists()
+				.name("idx-ticket_tx_hash")
+				.table(Ticket::Table)
+				.col(Ticket::TxHash)
+				.to_owned(),
+			Index::create()
+				.if_not_exists()
+				.name("idx-ticket_intermediate_tx_hash")
+				.table(Ticket::Table)
+				.col(Ticket::IntermediateTxHash)
+				.to_owned(),
+			Index::create()
+				.if_not_exists()
+				.name("idx-deleted_mint_ticket_tx_hash")
+				.table(DeletedMintTicket::Table)
+				.col(DeletedMintTicket::TxHash)
+				.to_owned(),
+		] {
+			manager.create_index(index).await?;
+		}
+
+		// the edges of the tickets synced so far, as `ticket::Model::child_link` makes them: a
+		// mint ticket links to the transfer in its hashes, any other ticket to the one its
+		// release tx created, once that ticket is known
+		manager
+			.get_connection()
+			.execute_unprepared(
+				r#"
+				INSERT INTO ticket_link (parent_id, child_id, kind)
+				SELECT parent.ticket_id, parent.child_id, parent.kind
+				FROM (
+					SELECT ticket_id,
+						CASE WHEN action = 'Mint' THEN coalesce(intermediate_tx_hash, tx_hash)
+							ELSE tx_hash END AS child_id,
+						CASE WHEN action = 'Mint' THEN 'Mint' ELSE 'Release' END AS kind
+					FROM ticket
+					UNION ALL
+					SELECT ticket_id, tx_hash,
+						CASE WHEN action = 'Mint' THEN 'Mint' ELSE 'Release' END
+					FROM deleted_mint_ticket
+				) parent
+				WHERE parent.child_id IS NOT NULL
+					AND parent.child_id <> parent.ticket_id
+					AND (
+						EXISTS (SELECT 1 FROM ticket WHERE ticket_id = parent.child_id)
+						OR EXISTS (
+							SELECT 1 FROM deleted_mint_ticket WHERE ticket_id = parent.child_id
+						)
+					)
+				ON CONFLICT DO NOTHING
+				"#,
+			)
+			.await?;
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum TicketLink {
+	Table,
+	ParentId,
+	ChildId,
+	Kind,
+}
@@ -0,0 +1,33 @@
+use super::m20240507_055143_one::Ticket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the raw memo and the fields decoded from it
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Ticket::Table)
+					.add_column_if_not_exists(ColumnDef::new(TicketMemo::MemoHex).text().null())
+					.add_column_if_not_exists(ColumnDef::new(TicketMemo::MemoJson).json().null())
+					.add_column_if_not_exists(
+						ColumnDef::new(TicketMemo::ReferralCode).string().null(),
+					)
+					.add_column_if_not_exists(ColumnDef::new(TicketMemo::FeeToken).string().null())
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum TicketMemo {
+	MemoHex,
+	MemoJson,
+	ReferralCode,
+	FeeToken,
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// raw amounts are integers in the smallest unit of the token, 78 digits fit a u256
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		let db = manager.get_connection();
+
+		// tickets keep the rows whose amount or fee does not parse, flagged with the raw value
+		db.execute_unprepared(
+			r#"
+			ALTER TABLE ticket ADD COLUMN IF NOT EXISTS parse_error text;
+			ALTER TABLE ticket ADD COLUMN IF NOT EXISTS amount_human numeric;
+			UPDATE ticket SET bridge_fee = btrim(bridge_fee, '"') WHERE bridge_fee LIKE '"%"';
+			UPDATE ticket SET parse_error = concat_ws('; ',
+				CASE WHEN btrim(amount) !~ '^[0-9]+$' THEN 'invalid amount: ' || amount END,
+				CASE WHEN btrim(bridge_fee) !~ '^[0-9]+$' THEN 'invalid bridge_fee: ' || bridge_fee END)
+			WHERE btrim(amount) !~ '^[0-9]+$' OR btrim(bridge_fee) !~ '^[0-9]+$';
+			ALTER TABLE ticket ALTER COLUMN amount DROP NOT NULL;
+			ALTER TABLE ticket ALTER COLUMN amount TYPE numeric(78, 0)
+				USING CASE WHEN btrim(amount) ~ '^[0-9]+$' THEN btrim(amount)::numeric END;
+			ALTER TABLE ticket ALTER COLUMN bridge_fee TYPE numeric(78, 0)
+				USING CASE WHEN btrim(bridge_fee) ~ '^[0-9]+$' THEN btrim(bridge_fee)::numeric END;
+			"#,
+		)
+		.await?;
+
+		// the aggregates are rebuilt by the sync tasks, rows that do not parse are moved to
+		// `amount_quarantine` as they were and written again on the next run
+		db.execute_unprepared(
+			r#"
+			CREATE TABLE IF NOT EXISTS amount_quarantine (
+				id bigserial PRIMARY KEY,
+				source_table character varying NOT NULL,
+				row_data json NOT NULL,
+				reason text NOT NULL,
+				quarantined_at timestamptz NOT NULL DEFAULT now()
+			);
+
+			INSERT INTO amount_quarantine (source_table, row_data, reason)
+			SELECT 'token_volume', row_to_json(t), 'invalid historical_volume: ' || historical_volume
+			FROM token_volume t WHERE btrim(historical_volume) !~ '^[0-9]+$';
+			DELETE FROM token_volume WHERE btrim(historical_volume) !~ '^[0-9]+$';
+			ALTER TABLE token_volume ALTER COLUMN historical_volume TYPE numeric(78, 0)
+				USING btrim(historical_volume)::numeric;
+			ALTER TABLE token_volume ADD COLUMN IF NOT EXISTS historical_volume_human numeric;
+
+			INSERT INTO amount_quarantine (source_table, row_data, reason)
+			SELECT 'token_on_chain', row_to_json(t), 'invalid amount: ' || amount
+			FROM token_on_chain t WHERE btrim(amount) !~ '^[0-9]+$';
+			DELETE FROM token_on_chain WHERE btrim(amount) !~ '^[0-9]+$';
+			ALTER TABLE token_on_chain ALTER COLUMN amount TYPE numeric(78, 0)
+				USING btrim(amount)::numeric;
+			ALTER TABLE token_on_chain ADD COLUMN IF NOT EXISTS amount_human numeric;
+
+			INSERT INTO amount_quarantine (source_table, row_data, reason)
+			SELECT 'bridge_fee_log', row_to_json(t), 'invalid amount: ' || amount
+			FROM bridge_fee_log t WHERE btrim(amount) !~ '^[0-9]+$';
+			DELETE FROM bridge_fee_log WHERE btrim(amount) !~ '^[0-9]+$';
+			ALTER TABLE bridge_fee_log ALTER COLUMN amount TYPE numeric(78, 0)
+				USING btrim(amount)::numeric;
+			ALTER TABLE bridge_fee_log ADD COLUMN IF NOT EXISTS amount_human numeric;
+			"#,
+		)
+		.await?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the volume of a token moved along one route on one utc day
+		manager
+			.create_table(
+				Table::create()
+					.table(RouteVolume::Table)
+					.if_not_exists()
+					.col(ColumnDef::new(RouteVolume::SrcChain).string().not_null())
+					.col(ColumnDef::new(RouteVolume::DstChain).string().not_null())
+					.col(ColumnDef::new(RouteVolume::Token).string().not_null())
+					.col(ColumnDef::new(RouteVolume::Date).date().not_null())
+					.col(
+						ColumnDef::new(RouteVolume::TicketCount)
+							.big_integer()
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(RouteVolume::Volume)
+							.decimal_len(78, 0)
+							.not_null(),
+					)
+					.col(ColumnDef::new(RouteVolume::VolumeHuman).decimal().null())
+					.primary_key(
+						Index::create()
+							.name("pk_route_volume")
+							.col(RouteVolume::SrcChain)
+							.col(RouteVolume::DstChain)
+							.col(RouteVolume::Token)
+							.col(RouteVolume::Date)
+							.primary(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("idx-route_volume_token_date")
+					.table(RouteVolume::Table)
+					.col(RouteVolume::Token)
+					.col(RouteVolume::Date)
+					.to_owned(),
+			)
+			.await?;
+
+		// the utc days whose route volumes are stale, written with the tickets and drained by
+		// the volume task
+		manager
+			.create_table(
+				Table::create()
+					.table(RouteVolumeDirty::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(RouteVolumeDirty::Date)
+							.date()
+							.not_null()
+							.primary_key(),
+					)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum RouteVolume {
+	Table,
+	SrcChain,
+	DstChain,
+	Token,
+	Date,
+	TicketCount,
+	Volume,
+	VolumeHuman,
+}
+
+#[derive(DeriveIden)]
+pub enum RouteVolumeDirty {
+	Table,
+	Date,
+}
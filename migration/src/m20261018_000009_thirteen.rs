@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the daily rollups share the key of the route volumes, so they are kept on the same rows
+		manager
+			.alter_table(
+				Table::alter()
+					.table(RouteVolume::Table)
+					.add_column_if_not_exists(
+						ColumnDef::new(RouteVolume::FeeTotal)
+							.decimal_len(78, 0)
+							.not_null()
+							.default(0),
+					)
+					.add_column_if_not_exists(
+						ColumnDef::new(RouteVolume::UniqueSenders)
+							.big_integer()
+							.not_null()
+							.default(0),
+					)
+					.add_column_if_not_exists(
+						ColumnDef::new(RouteVolume::UniqueReceivers)
+							.big_integer()
+							.not_null()
+							.default(0),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		let db = manager.get_connection();
+		db.execute_unprepared(
+			r#"
+			CREATE OR REPLACE VIEW daily_stats AS
+			SELECT date, src_chain, dst_chain, token, ticket_count, volume, volume_human,
+				fee_total, unique_senders, unique_receivers
+			FROM route_volume
+			"#,
+		)
+		.await?;
+
+		// the rows written before the columns existed are recomputed from the tickets
+		db.execute_unprepared(
+			r#"
+			INSERT INTO route_volume (src_chain, dst_chain, token, date, ticket_count, volume,
+				fee_total, unique_senders, unique_receivers)
+			SELECT src_chain, dst_chain, token,
+				(to_timestamp(ticket_time / 1e9) AT TIME ZONE 'UTC')::date AS day,
+				count(*), coalesce(sum(amount), 0), coalesce(sum(bridge_fee), 0),
+				count(DISTINCT sender), count(DISTINCT receiver)
+			FROM ticket
+			GROUP BY src_chain, dst_chain, token, day
+			ON CONFLICT (src_chain, dst_chain, token, date) DO UPDATE
+			SET ticket_count = excluded.ticket_count, volume = excluded.volume,
+				fee_total = excluded.fee_total, unique_senders = excluded.unique_senders,
+				unique_receivers = excluded.unique_receivers
+			"#,
+		)
+		.await?;
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum RouteVolume {
+	Table,
+	FeeTotal,
+	UniqueSenders,
+	UniqueReceivers,
+}
@@ -0,0 +1,171 @@
+use super::m20250111_000001_four::BridgeFeeLog;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the (src chain, utc day) pairs whose fee log is stale, written with the tickets and
+		// drained by the fee log task
+		manager
+			.create_table(
+				Table::create()
+					.table(BridgeFeeLogDirty::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(BridgeFeeLogDirty::ChainId)
+							.string()
+							.not_null(),
+					)
+					.col(ColumnDef::new(BridgeFeeLogDirty::Date).date().not_null())
+					.primary_key(
+						Index::create()
+							.name("pk_bridge_fee_log_dirty")
+							.col(BridgeFeeLogDirty::ChainId)
+							.col(BridgeFeeLogDirty::Date)
+							.primary(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// the fees of a chain and day broken down by destination chain and fee token
+		manager
+			.create_table(
+				Table::create()
+					.table(BridgeFeeLogDetail::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::ChainId)
+							.string()
+							.not_null(),
+					)
+					.col(ColumnDef::new(BridgeFeeLogDetail::Date).string().not_null())
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::DstChain)
+							.string()
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::FeeTokenId)
+							.string()
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::Amount)
+							.decimal_len(78, 0)
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::AmountHuman)
+							.decimal()
+							.null(),
+					)
+					.col(
+						ColumnDef::new(BridgeFeeLogDetail::TotalTicket)
+							.integer()
+							.not_null(),
+					)
+					.primary_key(
+						Index::create()
+							.name("pk_bridge_fee_log_detail")
+							.col(BridgeFeeLogDetail::ChainId)
+							.col(BridgeFeeLogDetail::Date)
+							.col(BridgeFeeLogDetail::DstChain)
+							.col(BridgeFeeLogDetail::FeeTokenId)
+							.primary(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// the tickets counted in a fee log row, replaces the `seqs` string
+		manager
+			.create_table(
+				Table::create()
+					.table(BridgeFeeLogTicket::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(BridgeFeeLogTicket::TicketId)
+							.string()
+							.not_null()
+							.primary_key(),
+					)
+					.col(
+						ColumnDef::new(BridgeFeeLogTicket::ChainId)
+							.string()
+							.not_null(),
+					)
+					.col(ColumnDef::new(BridgeFeeLogTicket::Date).string().not_null())
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("idx-bridge_fee_log_ticket_chain_id_date")
+					.table(BridgeFeeLogTicket::Table)
+					.col(BridgeFeeLogTicket::ChainId)
+					.col(BridgeFeeLogTicket::Date)
+					.to_owned(),
+			)
+			.await?;
+
+		// the fee log is rebuilt for every day that had fee paying tickets
+		manager
+			.get_connection()
+			.execute_unprepared(
+				r#"
+				INSERT INTO bridge_fee_log_dirty (chain_id, date)
+				SELECT DISTINCT src_chain, (to_timestamp(ticket_time / 1e9) AT TIME ZONE 'UTC')::date
+				FROM ticket
+				WHERE bridge_fee IS NOT NULL
+				ON CONFLICT DO NOTHING
+				"#,
+			)
+			.await?;
+
+		// the rebuilt rows leave `seqs` empty, the column stays for the instances that still
+		// write it and is dropped once none run
+		manager
+			.alter_table(
+				Table::alter()
+					.table(BridgeFeeLog::Table)
+					.modify_column(ColumnDef::new(BridgeFeeLog::Seqs).string().null())
+					.to_owned(),
+			)
+			.await?;
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum BridgeFeeLogDirty {
+	Table,
+	ChainId,
+	Date,
+}
+
+#[derive(DeriveIden)]
+pub enum BridgeFeeLogDetail {
+	Table,
+	ChainId,
+	Date,
+	DstChain,
+	FeeTokenId,
+	Amount,
+	AmountHuman,
+	TotalTicket,
+}
+
+#[derive(DeriveIden)]
+pub enum BridgeFeeLogTicket {
+	Table,
+	TicketId,
+	ChainId,
+	Date,
+}
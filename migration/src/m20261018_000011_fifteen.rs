@@ -0,0 +1,23 @@
+use super::m20240507_055143_one::Ticket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the api lists tickets newest first and pages on (ticket_time, ticket_id)
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("idx-ticket_time_id")
+					.table(Ticket::Table)
+					.col(Ticket::TicketTime)
+					.col(Ticket::TicketId)
+					.to_owned(),
+			)
+			.await
+	}
+}
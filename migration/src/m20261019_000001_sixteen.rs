@@ -0,0 +1,144 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the partner urls notified of ticket lifecycle events, unset filters match every ticket
+		manager
+			.create_table(
+				Table::create()
+					.table(WebhookEndpoint::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(WebhookEndpoint::Id)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(WebhookEndpoint::Url).text().not_null())
+					.col(ColumnDef::new(WebhookEndpoint::Secret).text().not_null())
+					.col(ColumnDef::new(WebhookEndpoint::Chain).string().null())
+					.col(ColumnDef::new(WebhookEndpoint::Token).string().null())
+					.col(ColumnDef::new(WebhookEndpoint::Address).string().null())
+					.col(
+						ColumnDef::new(WebhookEndpoint::Active)
+							.boolean()
+							.not_null()
+							.default(true),
+					)
+					.col(
+						ColumnDef::new(WebhookEndpoint::CreatedAt)
+							.big_integer()
+							.not_null(),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// one row per event and endpoint, kept once delivered or dead
+		manager
+			.create_table(
+				Table::create()
+					.table(WebhookDelivery::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(WebhookDelivery::Id)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(
+						ColumnDef::new(WebhookDelivery::EndpointId)
+							.big_integer()
+							.not_null(),
+					)
+					.col(ColumnDef::new(WebhookDelivery::Event).string().not_null())
+					.col(ColumnDef::new(WebhookDelivery::TicketId).text().not_null())
+					.col(ColumnDef::new(WebhookDelivery::Payload).json().not_null())
+					.col(
+						ColumnDef::new(WebhookDelivery::Status)
+							.string()
+							.not_null()
+							.default("pending"),
+					)
+					.col(
+						ColumnDef::new(WebhookDelivery::Attempts)
+							.integer()
+							.not_null()
+							.default(0),
+					)
+					.col(
+						ColumnDef::new(WebhookDelivery::NextAttemptAt)
+							.big_integer()
+							.not_null(),
+					)
+					.col(ColumnDef::new(WebhookDelivery::LastError).text().null())
+					.col(
+						ColumnDef::new(WebhookDelivery::DeliveredAt)
+							.big_integer()
+							.null(),
+					)
+					.col(
+						ColumnDef::new(WebhookDelivery::CreatedAt)
+							.big_integer()
+							.not_null(),
+					)
+					.foreign_key(
+						ForeignKey::create()
+							.name("fk-webhook_delivery-endpoint_id")
+							.from(WebhookDelivery::Table, WebhookDelivery::EndpointId)
+							.to(WebhookEndpoint::Table, WebhookEndpoint::Id)
+							.on_delete(ForeignKeyAction::Cascade),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// the delivery task polls the due pending rows
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("idx-webhook_delivery_status_next_attempt_at")
+					.table(WebhookDelivery::Table)
+					.col(WebhookDelivery::Status)
+					.col(WebhookDelivery::NextAttemptAt)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookEndpoint {
+	Table,
+	Id,
+	Url,
+	Secret,
+	Chain,
+	Token,
+	Address,
+	Active,
+	CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum WebhookDelivery {
+	Table,
+	Id,
+	EndpointId,
+	Event,
+	TicketId,
+	Payload,
+	Status,
+	Attempts,
+	NextAttemptAt,
+	LastError,
+	DeliveredAt,
+	CreatedAt,
+}
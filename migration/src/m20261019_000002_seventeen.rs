@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// one row per change written by the sync, in the transaction of the change
+		manager
+			.create_table(
+				Table::create()
+					.table(Outbox::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(Outbox::Id)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(Outbox::Topic).string().not_null())
+					.col(ColumnDef::new(Outbox::Key).text().not_null())
+					.col(ColumnDef::new(Outbox::Op).string().not_null())
+					.col(ColumnDef::new(Outbox::Payload).json().not_null())
+					.col(ColumnDef::new(Outbox::CreatedAt).big_integer().not_null())
+					.col(ColumnDef::new(Outbox::PublishedAt).big_integer().null())
+					.to_owned(),
+			)
+			.await?;
+
+		// the publisher only reads the rows it has not published yet
+		manager
+			.get_connection()
+			.execute_unprepared(
+				r#"CREATE INDEX IF NOT EXISTS "idx-outbox_unpublished" ON outbox (id)
+				WHERE published_at IS NULL"#,
+			)
+			.await?;
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum Outbox {
+	Table,
+	Id,
+	Topic,
+	Key,
+	Op,
+	Payload,
+	CreatedAt,
+	PublishedAt,
+}
@@ -0,0 +1,43 @@
+use super::m20240507_055143_one::Ticket;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the history of an address merges both sides of its tickets, newest first
+		for (name, column) in [
+			("idx-ticket_sender_time_id", Ticket::Sender),
+			("idx-ticket_receiver_time_id", Ticket::Receiver),
+		] {
+			manager
+				.create_index(
+					Index::create()
+						.if_not_exists()
+						.name(name)
+						.table(Ticket::Table)
+						.col(column)
+						.col(Ticket::TicketTime)
+						.col(Ticket::TicketId)
+						.to_owned(),
+				)
+				.await?;
+		}
+
+		// hex addresses are looked up in lowercase, whatever their checksum casing
+		for (name, column) in [
+			("idx-ticket_lower_sender_time_id", "sender"),
+			("idx-ticket_lower_receiver_time_id", "receiver"),
+		] {
+			manager
+				.get_connection()
+				.execute_unprepared(&format!(
+					r#"CREATE INDEX IF NOT EXISTS "{name}" ON ticket (lower({column}), ticket_time, ticket_id)"#
+				))
+				.await?;
+		}
+		Ok(())
+	}
+}
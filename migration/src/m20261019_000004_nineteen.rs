@@ -0,0 +1,35 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// one row per hash and ticket, a lookup on `hash` is pushed down to the primary keys and
+		// the hash indexes of the ticket links migration as long as every branch yields the same
+		// column types
+		manager
+			.get_connection()
+			.execute_unprepared(
+				r#"
+				CREATE OR REPLACE VIEW ticket_hash AS
+				SELECT ticket_id AS hash, ticket_id, 'ticket_id' AS kind, false AS archived
+				FROM ticket
+				UNION ALL
+				SELECT tx_hash::text, ticket_id, 'tx_hash', false
+				FROM ticket WHERE tx_hash IS NOT NULL
+				UNION ALL
+				SELECT intermediate_tx_hash::text, ticket_id, 'intermediate_tx_hash', false
+				FROM ticket WHERE intermediate_tx_hash IS NOT NULL
+				UNION ALL
+				SELECT ticket_id, ticket_id, 'ticket_id', true FROM deleted_mint_ticket
+				UNION ALL
+				SELECT tx_hash::text, ticket_id, 'tx_hash', true
+				FROM deleted_mint_ticket WHERE tx_hash IS NOT NULL
+				"#,
+			)
+			.await?;
+		Ok(())
+	}
+}
@@ -0,0 +1,100 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// the keys of the api clients, only the sha-256 of a key is stored, `prefix` tells them
+		// apart. `rate_limit` is in requests per minute, no `daily_quota` means unlimited
+		manager
+			.create_table(
+				Table::create()
+					.table(ApiKey::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(ApiKey::Id)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(ApiKey::Name).string().not_null())
+					.col(ColumnDef::new(ApiKey::Prefix).string().not_null())
+					.col(
+						ColumnDef::new(ApiKey::KeyHash)
+							.string()
+							.not_null()
+							.unique_key(),
+					)
+					.col(ColumnDef::new(ApiKey::RateLimit).integer().not_null())
+					.col(ColumnDef::new(ApiKey::DailyQuota).big_integer().null())
+					.col(
+						ColumnDef::new(ApiKey::Active)
+							.boolean()
+							.not_null()
+							.default(true),
+					)
+					.col(ColumnDef::new(ApiKey::CreatedAt).big_integer().not_null())
+					.to_owned(),
+			)
+			.await?;
+
+		// the requests made with a key per utc day
+		manager
+			.create_table(
+				Table::create()
+					.table(ApiKeyUsage::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(ApiKeyUsage::ApiKeyId)
+							.big_integer()
+							.not_null(),
+					)
+					.col(ColumnDef::new(ApiKeyUsage::Date).date().not_null())
+					.col(
+						ColumnDef::new(ApiKeyUsage::RequestCount)
+							.big_integer()
+							.not_null()
+							.default(0),
+					)
+					.primary_key(
+						Index::create()
+							.name("pk-api_key_usage")
+							.col(ApiKeyUsage::ApiKeyId)
+							.col(ApiKeyUsage::Date),
+					)
+					.foreign_key(
+						ForeignKey::create()
+							.name("fk-api_key_usage-api_key_id")
+							.from(ApiKeyUsage::Table, ApiKeyUsage::ApiKeyId)
+							.to(ApiKey::Table, ApiKey::Id)
+							.on_delete(ForeignKeyAction::Cascade),
+					)
+					.to_owned(),
+			)
+			.await
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum ApiKey {
+	Table,
+	Id,
+	Name,
+	Prefix,
+	KeyHash,
+	RateLimit,
+	DailyQuota,
+	Active,
+	CreatedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum ApiKeyUsage {
+	Table,
+	ApiKeyId,
+	Date,
+	RequestCount,
+}
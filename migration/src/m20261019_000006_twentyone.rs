@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// every ticket event as it was published, the lifecycle timeline of a ticket is read back
+		// from it. `occurred_at` is in unix nanoseconds, `inferred` marks the rows not logged
+		// as they happened
+		manager
+			.create_table(
+				Table::create()
+					.table(TicketTransition::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(TicketTransition::Id)
+							.big_integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(TicketTransition::TicketId).text().not_null())
+					.col(ColumnDef::new(TicketTransition::Kind).string().not_null())
+					.col(ColumnDef::new(TicketTransition::Status).string().not_null())
+					.col(
+						ColumnDef::new(TicketTransition::PreviousStatus)
+							.string()
+							.null(),
+					)
+					.col(ColumnDef::new(TicketTransition::TxHash).string().null())
+					.col(
+						ColumnDef::new(TicketTransition::IntermediateTxHash)
+							.string()
+							.null(),
+					)
+					.col(
+						ColumnDef::new(TicketTransition::OccurredAt)
+							.big_integer()
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(TicketTransition::Inferred)
+							.boolean()
+							.not_null()
+							.default(false),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_index(
+				Index::create()
+					.if_not_exists()
+					.name("idx-ticket_transition_ticket_id")
+					.table(TicketTransition::Table)
+					.col(TicketTransition::TicketId)
+					.col(TicketTransition::Id)
+					.to_owned(),
+			)
+			.await?;
+
+		// the tickets indexed before the log get a `created` row at their ticket time, with the
+		// status they have now
+		manager
+			.get_connection()
+			.execute_unprepared(
+				r#"
+				INSERT INTO ticket_transition
+					(ticket_id, kind, status, tx_hash, intermediate_tx_hash, occurred_at, inferred)
+				SELECT ticket_id, 'created', status::text, tx_hash, intermediate_tx_hash,
+					ticket_time, true
+				FROM ticket
+				WHERE NOT EXISTS (
+					SELECT 1 FROM ticket_transition
+					WHERE ticket_transition.ticket_id = ticket.ticket_id
+						AND ticket_transition.kind = 'created'
+				)
+				ORDER BY ticket_time, ticket_id
+				"#,
+			)
+			.await?;
+		Ok(())
+	}
+}
+
+#[derive(DeriveIden)]
+pub enum TicketTransition {
+	Table,
+	Id,
+	TicketId,
+	Kind,
+	Status,
+	PreviousStatus,
+	TxHash,
+	IntermediateTxHash,
+	OccurredAt,
+	Inferred,
+}
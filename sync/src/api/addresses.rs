@@ -0,0 +1,50 @@
+use super::{
+	dto::{AddressDto, TicketDto},
+	error::{ApiError, ErrorDto},
+	pagination::Page,
+	tickets::{list_tickets, TicketParams},
+	ApiState,
+};
+use crate::service::Query as Dao;
+use axum::{
+	extract::{Path, Query, State},
+	Json,
+};
+
+// the tickets the address sent or received, with the filters of the ticket list
+#[utoipa::path(
+	get,
+	path = "/api/v1/addresses/{address}/tickets",
+	tag = "addresses",
+	params(("address" = String, Path), TicketParams),
+	responses(
+		(status = 200, description = "Tickets, newest first", body = Page<TicketDto>),
+		(status = 400, description = "Invalid parameters", body = ErrorDto),
+	)
+)]
+pub async fn list_address_tickets(
+	state: State<ApiState>,
+	Path(address): Path<String>,
+	Query(params): Query<TicketParams>,
+) -> Result<Json<Page<TicketDto>>, ApiError> {
+	let params = TicketParams {
+		address: Some(address),
+		..params
+	};
+	list_tickets(state, Query(params)).await
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/addresses/{address}",
+	tag = "addresses",
+	params(("address" = String, Path)),
+	responses((status = 200, body = AddressDto))
+)]
+pub async fn get_address(
+	State(state): State<ApiState>,
+	Path(address): Path<String>,
+) -> Result<Json<AddressDto>, ApiError> {
+	let summary = Dao::get_address_summary(&state.db, address.clone()).await?;
+	Ok(Json(AddressDto::new(address, summary)))
+}
@@ -0,0 +1,213 @@
+//! Api keys and rate limits. A request may carry a key in `X-API-Key`, keyed requests are counted
+//! per key and day and limited by the rate limit and daily quota of their key, the others share
+//! the stricter anonymous limit of their client address.
+
+use super::{error::ApiError, ApiState};
+use crate::service::{Mutation, Query};
+use anyhow::{anyhow, Result};
+use axum::{
+	extract::{ConnectInfo, Request, State},
+	http::HeaderValue,
+	middleware::Next,
+	response::Response,
+};
+use chrono::{Duration as ChronoDuration, Utc};
+use ring::digest;
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv4Addr, SocketAddr},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+pub const RATE_LIMIT_HEADER: &str = "X-RateLimit-Limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+pub const API_KEY_PREFIX: &str = "omn_";
+// requests per minute
+pub const DEFAULT_KEY_RATE_LIMIT: i32 = 600;
+pub const DEFAULT_ANONYMOUS_RATE_LIMIT: u32 = 60;
+// a bucket idle this long is full again and can be dropped
+const BUCKET_IDLE: Duration = Duration::from_secs(60);
+const BUCKET_PRUNE_THRESHOLD: usize = 10_000;
+
+pub fn new_api_key() -> String {
+	format!(
+		"{}{}",
+		API_KEY_PREFIX,
+		hex::encode(rand::random::<[u8; 24]>())
+	)
+}
+
+// only the hash of a key is stored
+pub fn hash_api_key(key: &str) -> String {
+	hex::encode(digest::digest(&digest::SHA256, key.as_bytes()))
+}
+
+// enough of a key to tell it apart in listings
+pub fn api_key_prefix(key: &str) -> String {
+	key.chars().take(API_KEY_PREFIX.len() + 8).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Client {
+	Key(i64),
+	Anonymous(IpAddr),
+}
+
+struct Bucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+// token buckets refilled at the limit per minute, held by the api process so the limits apply
+// per replica
+#[derive(Clone)]
+pub struct RateLimiter {
+	anonymous_limit: u32,
+	buckets: Arc<Mutex<HashMap<Client, Bucket>>>,
+}
+
+impl RateLimiter {
+	pub fn new(anonymous_limit: u32) -> Self {
+		RateLimiter {
+			anonymous_limit,
+			buckets: Arc::new(Mutex::new(HashMap::new())),
+		}
+	}
+
+	// the anonymous limit from API_ANONYMOUS_RATE_LIMIT, or the default one
+	pub fn from_env() -> Result<Self> {
+		let anonymous_limit = match std::env::var("API_ANONYMOUS_RATE_LIMIT") {
+			Ok(limit) => limit
+				.parse()
+				.map_err(|_| anyhow!("invalid API_ANONYMOUS_RATE_LIMIT {}", limit))?,
+			Err(_) => DEFAULT_ANONYMOUS_RATE_LIMIT,
+		};
+		Ok(Self::new(anonymous_limit))
+	}
+
+	// takes a request from the bucket of the client, Ok holds the requests left and Err the
+	// seconds until the next one is allowed
+	fn acquire(&self, client: Client, limit: u32, now: Instant) -> Result<u32, u64> {
+		if limit == 0 {
+			return Err(BUCKET_IDLE.as_secs());
+		}
+		let per_second = limit as f64 / 60.0;
+		let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+		if buckets.len() > BUCKET_PRUNE_THRESHOLD {
+			buckets.retain(|_, bucket| now.duration_since(bucket.updated) < BUCKET_IDLE);
+		}
+		let bucket = buckets.entry(client).or_insert(Bucket {
+			tokens: limit as f64,
+			updated: now,
+		});
+		let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+		bucket.tokens = (bucket.tokens + elapsed * per_second).min(limit as f64);
+		bucket.updated = now;
+		if bucket.tokens < 1.0 {
+			return Err(((1.0 - bucket.tokens) / per_second).ceil() as u64);
+		}
+		bucket.tokens -= 1.0;
+		Ok(bucket.tokens as u32)
+	}
+}
+
+fn seconds_to_next_utc_day() -> u64 {
+	let now = Utc::now();
+	let tomorrow = (now.date_naive() + ChronoDuration::days(1))
+		.and_hms_opt(0, 0, 0)
+		.unwrap_or_default()
+		.and_utc();
+	(tomorrow - now).num_seconds().max(1) as u64
+}
+
+// authenticates the keyed requests, applies the rate limits and counts the keyed requests let through
+pub async fn limit_requests(
+	State(state): State<ApiState>,
+	request: Request,
+	next: Next,
+) -> Result<Response, ApiError> {
+	let (client, limit, api_key) = match request.headers().get(API_KEY_HEADER) {
+		Some(key) => {
+			let invalid = || ApiError::Unauthorized("invalid api key".to_owned());
+			let key = key.to_str().map_err(|_| invalid())?;
+			let api_key = Query::get_active_api_key(&state.db, &hash_api_key(key))
+				.await?
+				.ok_or_else(invalid)?;
+			(
+				Client::Key(api_key.id),
+				api_key.rate_limit.max(0) as u32,
+				Some(api_key),
+			)
+		}
+		None => {
+			// the peer is unknown when the router is not served with its connect info
+			let ip = request
+				.extensions()
+				.get::<ConnectInfo<SocketAddr>>()
+				.map(|ConnectInfo(peer)| peer.ip())
+				.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+			(Client::Anonymous(ip), state.limiter.anonymous_limit, None)
+		}
+	};
+	let remaining = state
+		.limiter
+		.acquire(client, limit, Instant::now())
+		.map_err(|retry_after| ApiError::TooManyRequests {
+			reason: "rate limit exceeded".to_owned(),
+			retry_after,
+		})?;
+	// only the requests let through count against the quota
+	if let Some(api_key) = api_key {
+		let today = Utc::now().date_naive();
+		Mutation::count_api_key_request(&state.db, api_key.id, api_key.daily_quota, today)
+			.await?
+			.ok_or_else(|| ApiError::TooManyRequests {
+				reason: "daily quota exceeded".to_owned(),
+				retry_after: seconds_to_next_utc_day(),
+			})?;
+	}
+
+	let mut response = next.run(request).await;
+	let headers = response.headers_mut();
+	headers.insert(RATE_LIMIT_HEADER, HeaderValue::from(limit));
+	headers.insert(RATE_LIMIT_REMAINING_HEADER, HeaderValue::from(remaining));
+	Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn buckets_refill_at_the_limit_per_minute() {
+		let limiter = RateLimiter::new(2);
+		let client = Client::Anonymous(IpAddr::V4(Ipv4Addr::LOCALHOST));
+		let start = Instant::now();
+
+		assert_eq!(limiter.acquire(client.clone(), 2, start), Ok(1));
+		assert_eq!(limiter.acquire(client.clone(), 2, start), Ok(0));
+		assert_eq!(limiter.acquire(client.clone(), 2, start), Err(30));
+		// other clients have their own bucket
+		assert_eq!(limiter.acquire(Client::Key(1), 2, start), Ok(1));
+
+		let later = start + Duration::from_secs(30);
+		assert_eq!(limiter.acquire(client.clone(), 2, later), Ok(0));
+		assert!(limiter.acquire(client.clone(), 2, later).is_err());
+		assert!(limiter.acquire(Client::Key(2), 0, later).is_err());
+	}
+
+	#[test]
+	fn keys_are_stored_as_hashes() {
+		let key = new_api_key();
+		assert!(key.starts_with(API_KEY_PREFIX));
+		assert_eq!(key.len(), API_KEY_PREFIX.len() + 48);
+		assert_ne!(key, new_api_key());
+
+		let hash = hash_api_key(&key);
+		assert_eq!(hash.len(), 64);
+		assert_eq!(hash, hash_api_key(&key));
+		assert!(key.starts_with(&api_key_prefix(&key)));
+	}
+}
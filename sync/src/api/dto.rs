@@ -0,0 +1,441 @@
+//! The json shapes of the api, raw amounts are decimal strings as they may not fit a json number.
+
+use crate::entity::{
+	bridge_fee_log, chain_meta, ticket, token_ledger_id_on_chain, token_meta, token_on_chain,
+	token_volume,
+};
+use crate::service::{AddressSummary, AddressTokenFlow, TicketHashMatch};
+use async_graphql::SimpleObject;
+use bigdecimal::BigDecimal;
+use sea_orm::{ActiveEnum, JsonValue};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+fn amount(amount: Option<BigDecimal>) -> Option<String> {
+	amount.map(|amount| amount.normalized().to_plain_string())
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "Ticket", complex)]
+#[schema(as = Ticket)]
+pub struct TicketDto {
+	pub ticket_id: String,
+	pub ticket_seq: Option<i64>,
+	pub ticket_type: String,
+	pub ticket_time: i64,
+	pub src_chain: String,
+	pub dst_chain: String,
+	pub action: String,
+	pub token: String,
+	pub amount: Option<String>,
+	pub amount_human: Option<String>,
+	pub sender: Option<String>,
+	pub receiver: String,
+	pub memo: Option<String>,
+	#[schema(value_type = Option<Value>)]
+	pub memo_json: Option<JsonValue>,
+	pub referral_code: Option<String>,
+	pub fee_token: Option<String>,
+	pub bridge_fee: Option<String>,
+	pub status: String,
+	pub sub_status: Option<String>,
+	pub tx_hash: Option<String>,
+	pub intermediate_tx_hash: Option<String>,
+	pub confirmations: Option<i32>,
+	pub block_height: Option<i64>,
+	pub date: String,
+}
+
+impl From<ticket::Model> for TicketDto {
+	fn from(ticket: ticket::Model) -> Self {
+		TicketDto {
+			ticket_id: ticket.ticket_id,
+			ticket_seq: ticket.ticket_seq,
+			ticket_type: ticket.ticket_type.to_value(),
+			ticket_time: ticket.ticket_time,
+			src_chain: ticket.src_chain,
+			dst_chain: ticket.dst_chain,
+			action: ticket.action.to_value(),
+			token: ticket.token,
+			amount: amount(ticket.amount),
+			amount_human: amount(ticket.amount_human),
+			sender: ticket.sender,
+			receiver: ticket.receiver,
+			memo: ticket.memo,
+			memo_json: ticket.memo_json,
+			referral_code: ticket.referral_code,
+			fee_token: ticket.fee_token,
+			bridge_fee: amount(ticket.bridge_fee),
+			status: ticket.status.to_value(),
+			sub_status: ticket.sub_status,
+			tx_hash: ticket.tx_hash,
+			intermediate_tx_hash: ticket.intermediate_tx_hash,
+			confirmations: ticket.confirmations,
+			block_height: ticket.block_height,
+			date: ticket.date,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "Chain", complex)]
+#[schema(as = Chain)]
+pub struct ChainDto {
+	pub chain_id: String,
+	pub canister_id: String,
+	pub chain_type: String,
+	pub chain_state: String,
+	pub contract_address: Option<String>,
+	#[schema(value_type = Option<Vec<String>>)]
+	pub counterparties: Option<JsonValue>,
+	pub fee_token: Option<String>,
+}
+
+impl From<chain_meta::Model> for ChainDto {
+	fn from(chain: chain_meta::Model) -> Self {
+		ChainDto {
+			chain_id: chain.chain_id,
+			canister_id: chain.canister_id,
+			chain_type: chain.chain_type.to_value(),
+			chain_state: chain.chain_state.to_value(),
+			contract_address: chain.contract_address,
+			counterparties: chain.counterparties,
+			fee_token: chain.fee_token,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "Token", complex)]
+#[schema(as = Token)]
+pub struct TokenDto {
+	pub token_id: String,
+	pub name: String,
+	pub symbol: String,
+	pub issue_chain: String,
+	pub decimals: i16,
+	pub icon: Option<String>,
+	#[schema(value_type = HashMap<String, String>)]
+	pub metadata: JsonValue,
+	#[schema(value_type = Vec<String>)]
+	pub dst_chains: JsonValue,
+	pub launchpad: Option<String>,
+}
+
+impl From<token_meta::Model> for TokenDto {
+	fn from(token: token_meta::Model) -> Self {
+		TokenDto {
+			token_id: token.token_id,
+			name: token.name,
+			symbol: token.symbol,
+			issue_chain: token.issue_chain,
+			decimals: token.decimals,
+			icon: token.icon,
+			metadata: token.metadata,
+			dst_chains: token.dst_chains,
+			launchpad: token.launchpad,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TokenOnChain", complex)]
+#[schema(as = TokenOnChain)]
+pub struct TokenOnChainDto {
+	pub chain_id: String,
+	pub token_id: String,
+	pub amount: String,
+	pub amount_human: Option<String>,
+}
+
+impl From<token_on_chain::Model> for TokenOnChainDto {
+	fn from(token_on_chain: token_on_chain::Model) -> Self {
+		TokenOnChainDto {
+			chain_id: token_on_chain.chain_id,
+			token_id: token_on_chain.token_id,
+			amount: amount(Some(token_on_chain.amount)).unwrap_or_default(),
+			amount_human: amount(token_on_chain.amount_human),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TokenLedgerId", complex)]
+#[schema(as = TokenLedgerId)]
+pub struct TokenLedgerIdDto {
+	pub chain_id: String,
+	pub token_id: String,
+	pub contract_id: String,
+}
+
+impl From<token_ledger_id_on_chain::Model> for TokenLedgerIdDto {
+	fn from(ledger_id: token_ledger_id_on_chain::Model) -> Self {
+		TokenLedgerIdDto {
+			chain_id: ledger_id.chain_id,
+			token_id: ledger_id.token_id,
+			contract_id: ledger_id.contract_id,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TokenVolume")]
+#[schema(as = TokenVolume)]
+pub struct TokenVolumeDto {
+	pub token_id: String,
+	pub ticket_count: String,
+	pub historical_volume: String,
+	pub historical_volume_human: Option<String>,
+}
+
+impl From<token_volume::Model> for TokenVolumeDto {
+	fn from(volume: token_volume::Model) -> Self {
+		TokenVolumeDto {
+			token_id: volume.token_id,
+			ticket_count: volume.ticket_count,
+			historical_volume: amount(Some(volume.historical_volume)).unwrap_or_default(),
+			historical_volume_human: amount(volume.historical_volume_human),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "BridgeFeeLog", complex)]
+#[schema(as = BridgeFeeLog)]
+pub struct BridgeFeeLogDto {
+	pub chain_id: String,
+	pub date: String,
+	pub fee_token_id: String,
+	pub amount: String,
+	pub amount_human: Option<String>,
+	pub total_ticket: i32,
+}
+
+impl From<bridge_fee_log::Model> for BridgeFeeLogDto {
+	fn from(log: bridge_fee_log::Model) -> Self {
+		BridgeFeeLogDto {
+			chain_id: log.chain_id,
+			date: log.date,
+			fee_token_id: log.fee_token_id,
+			amount: amount(Some(log.amount)).unwrap_or_default(),
+			amount_human: amount(log.amount_human),
+			total_ticket: log.total_ticket,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "StatusCount")]
+#[schema(as = StatusCount)]
+pub struct StatusCountDto {
+	pub status: String,
+	pub count: i64,
+}
+
+// `net` is the inflow less the outflow and may be negative
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "AddressTokenFlow")]
+#[schema(as = AddressTokenFlow)]
+pub struct AddressTokenFlowDto {
+	pub token: String,
+	pub inflow: String,
+	pub outflow: String,
+	pub net: String,
+	pub inflow_human: Option<String>,
+	pub outflow_human: Option<String>,
+	pub net_human: Option<String>,
+	pub incoming_count: i64,
+	pub outgoing_count: i64,
+	pub first_seen: i64,
+	pub last_seen: i64,
+}
+
+impl From<AddressTokenFlow> for AddressTokenFlowDto {
+	fn from(flow: AddressTokenFlow) -> Self {
+		let net = &flow.inflow - &flow.outflow;
+		let net_human = match (&flow.inflow_human, &flow.outflow_human) {
+			(None, None) => None,
+			(inflow, outflow) => {
+				Some(inflow.clone().unwrap_or_default() - outflow.clone().unwrap_or_default())
+			}
+		};
+		AddressTokenFlowDto {
+			token: flow.token,
+			inflow: amount(Some(flow.inflow)).unwrap_or_default(),
+			outflow: amount(Some(flow.outflow)).unwrap_or_default(),
+			net: amount(Some(net)).unwrap_or_default(),
+			inflow_human: amount(flow.inflow_human),
+			outflow_human: amount(flow.outflow_human),
+			net_human: amount(net_human),
+			incoming_count: flow.incoming_count,
+			outgoing_count: flow.outgoing_count,
+			first_seen: flow.first_seen,
+			last_seen: flow.last_seen,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "Address")]
+#[schema(as = Address)]
+pub struct AddressDto {
+	pub address: String,
+	pub ticket_count: i64,
+	pub first_seen: Option<i64>,
+	pub last_seen: Option<i64>,
+	pub status_counts: Vec<StatusCountDto>,
+	pub tokens: Vec<AddressTokenFlowDto>,
+}
+
+impl AddressDto {
+	pub fn new(address: String, summary: AddressSummary) -> Self {
+		AddressDto {
+			address,
+			ticket_count: summary.ticket_count,
+			first_seen: summary.first_seen,
+			last_seen: summary.last_seen,
+			status_counts: summary
+				.status_counts
+				.into_iter()
+				.map(|(status, count)| StatusCountDto { status, count })
+				.collect(),
+			tokens: summary.tokens.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+// `linked` holds the other tickets of the transfer, e.g. the mint a release tx created
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TicketHashMatch")]
+#[schema(as = TicketHashMatch)]
+pub struct TicketHashMatchDto {
+	pub matched_by: String,
+	pub archived: bool,
+	pub ticket: TicketDto,
+	pub linked: Vec<TicketDto>,
+}
+
+impl TicketHashMatchDto {
+	pub fn new(found: TicketHashMatch, journey: Vec<ticket::Model>) -> Self {
+		let ticket_id = found.ticket.ticket_id.clone();
+		TicketHashMatchDto {
+			matched_by: found.matched_by,
+			archived: found.archived,
+			ticket: found.ticket.into(),
+			linked: journey
+				.into_iter()
+				.filter(|ticket| ticket.ticket_id != ticket_id)
+				.map(Into::into)
+				.collect(),
+		}
+	}
+}
+
+// a step of the lifecycle of a ticket, at unix nanoseconds `at`. `kind` is `ticket_time` for
+// the hub ticket time, `sub_status` for an in-flight sub status, or the kind of a ticket event.
+// `inferred` events were not logged as they happened, such as the `created` one of a ticket
+// indexed before the log, dated at its ticket time
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TimelineStep")]
+#[schema(as = TimelineStep)]
+pub struct TimelineStepDto {
+	pub kind: String,
+	pub at: i64,
+	pub status: Option<String>,
+	pub previous_status: Option<String>,
+	pub sub_status: Option<String>,
+	pub tx_hash: Option<String>,
+	pub intermediate_tx_hash: Option<String>,
+	pub since_previous_ms: i64,
+	pub since_ticket_time_ms: i64,
+	pub inferred: bool,
+}
+
+// the time a ticket spent in a status, the current one has no end yet
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "StatusStage")]
+#[schema(as = StatusStage)]
+pub struct StatusStageDto {
+	pub status: String,
+	pub entered_at: i64,
+	pub left_at: Option<i64>,
+	pub duration_ms: Option<i64>,
+}
+
+// `hub_to_indexed_ms` is the time until the sync saw the ticket, `indexed_to_finalized_ms` the
+// time until the destination finalized it once seen, and `journey_ms` the time from the first
+// hop's ticket time to the finalization of the last hop
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TicketLatency")]
+#[schema(as = TicketLatency)]
+pub struct LatencyDto {
+	pub hub_to_indexed_ms: Option<i64>,
+	pub indexed_to_finalized_ms: Option<i64>,
+	pub total_ms: Option<i64>,
+	pub journey_ms: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TicketHop")]
+#[schema(as = TicketHop)]
+pub struct TicketHopDto {
+	pub ticket_id: String,
+	pub src_chain: String,
+	pub dst_chain: String,
+	pub status: String,
+	pub ticket_time: i64,
+	pub indexed_at: Option<i64>,
+	pub finalized_at: Option<i64>,
+	pub tx_hash: Option<String>,
+	pub intermediate_tx_hash: Option<String>,
+}
+
+// the times are unknown for the events of tickets indexed before they were logged
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+#[graphql(name = "TicketTimeline")]
+#[schema(as = TicketTimeline)]
+pub struct TicketTimelineDto {
+	pub ticket: TicketDto,
+	pub indexed_at: Option<i64>,
+	pub finalized_at: Option<i64>,
+	pub latency: LatencyDto,
+	pub steps: Vec<TimelineStepDto>,
+	pub stages: Vec<StatusStageDto>,
+	pub hops: Vec<TicketHopDto>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	#[test]
+	fn amounts_are_plain_decimal_strings() {
+		let raw = BigDecimal::from_str("1e30").unwrap();
+		assert_eq!(
+			amount(Some(raw)).as_deref(),
+			Some("1000000000000000000000000000000")
+		);
+		let human = BigDecimal::from_str("0.12300000").unwrap();
+		assert_eq!(amount(Some(human)).as_deref(), Some("0.123"));
+		assert_eq!(amount(None), None);
+	}
+
+	#[test]
+	fn net_flows_may_be_negative() {
+		let flow = AddressTokenFlow {
+			token: "BTC".to_owned(),
+			inflow: BigDecimal::from(100),
+			outflow: BigDecimal::from(250),
+			inflow_human: None,
+			outflow_human: Some(BigDecimal::from_str("0.0000025").unwrap()),
+			incoming_count: 1,
+			outgoing_count: 2,
+			first_seen: 1,
+			last_seen: 2,
+		};
+		let dto = AddressTokenFlowDto::from(flow);
+		assert_eq!(dto.net, "-150");
+		assert_eq!(dto.net_human.as_deref(), Some("-0.0000025"));
+	}
+}
@@ -0,0 +1,57 @@
+use axum::{
+	http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+	response::{IntoResponse, Response},
+	Json,
+};
+use log::error;
+use sea_orm::DbErr;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+// the body of every error answer
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(as = Error)]
+pub struct ErrorDto {
+	pub error: String,
+}
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+	#[error("{0} not found")]
+	NotFound(String),
+	#[error("{0}")]
+	BadRequest(String),
+	#[error("{0}")]
+	Unauthorized(String),
+	// `retry_after` is in seconds
+	#[error("{reason}")]
+	TooManyRequests { reason: String, retry_after: u64 },
+	#[error(transparent)]
+	Db(#[from] DbErr),
+}
+
+impl IntoResponse for ApiError {
+	fn into_response(self) -> Response {
+		let (status, error) = match &self {
+			ApiError::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
+			ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+			ApiError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+			ApiError::TooManyRequests { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+			ApiError::Db(e) => {
+				error!("api query failed: {:?}", e);
+				(
+					StatusCode::INTERNAL_SERVER_ERROR,
+					"internal error".to_owned(),
+				)
+			}
+		};
+		let mut response = (status, Json(ErrorDto { error })).into_response();
+		if let ApiError::TooManyRequests { retry_after, .. } = self {
+			response
+				.headers_mut()
+				.insert(RETRY_AFTER, HeaderValue::from(retry_after));
+		}
+		response
+	}
+}
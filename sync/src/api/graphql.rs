@@ -0,0 +1,564 @@
+//! The GraphQL schema over the indexer entities, served next to the REST api.
+
+use super::{
+	dto::{
+		AddressDto, BridgeFeeLogDto, ChainDto, TicketDto, TicketHashMatchDto, TicketTimelineDto,
+		TokenDto, TokenLedgerIdDto, TokenOnChainDto, TokenVolumeDto,
+	},
+	pagination::{
+		decode_cursor, decode_pair_cursor, decode_ticket_cursor, encode_cursor, page_limit,
+		ticket_key,
+	},
+	search::resolve_hash,
+	tickets::parse_status,
+	timeline::resolve_timeline,
+};
+use crate::entity::{
+	chain_meta, token_ledger_id_on_chain, token_meta, token_on_chain, token_volume,
+};
+use crate::service::{Query, TicketFilter};
+use async_graphql::{
+	connection::{Connection, Edge},
+	dataloader::{DataLoader, Loader},
+	ComplexObject, Context, EmptyMutation, EmptySubscription, InputObject, Object, OutputType,
+	Result, Schema,
+};
+use chrono::NaiveDate;
+use sea_orm::DbConn;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub type IndexerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+// relationships nest, the limits keep a single query from walking the whole index. a paged
+// field costs its page size times its selection, a full page of tickets with their chains and
+// token fits
+const MAX_QUERY_DEPTH: usize = 8;
+const MAX_QUERY_COMPLEXITY: usize = 10_000;
+
+pub fn schema(db: Arc<DbConn>) -> IndexerSchema {
+	Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+		.data(DataLoader::new(IndexerLoader(db.clone()), tokio::spawn))
+		.data(db)
+		.limit_depth(MAX_QUERY_DEPTH)
+		.limit_complexity(MAX_QUERY_COMPLEXITY)
+		.finish()
+}
+
+fn db<'a>(ctx: &Context<'a>) -> Result<&'a DbConn> {
+	Ok(ctx.data::<Arc<DbConn>>()?.as_ref())
+}
+
+fn loader<'a>(ctx: &Context<'a>) -> Result<&'a DataLoader<IndexerLoader>> {
+	ctx.data::<DataLoader<IndexerLoader>>()
+}
+
+// the relationships of the items of a list are loaded with one query per key type instead of
+// one query per item
+pub struct IndexerLoader(Arc<DbConn>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChainKey(String);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenKey(String);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenVolumeKey(String);
+
+// the rows of one chain or of one token
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OnChainKey {
+	Chain(String),
+	Token(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LedgerIdKey {
+	Chain(String),
+	Token(String),
+}
+
+impl Loader<ChainKey> for IndexerLoader {
+	type Value = chain_meta::Model;
+	type Error = async_graphql::Error;
+
+	async fn load(&self, keys: &[ChainKey]) -> Result<HashMap<ChainKey, Self::Value>> {
+		let chain_ids = keys.iter().map(|key| key.0.clone()).collect();
+		let chains = Query::get_chains_by_ids(&self.0, chain_ids).await?;
+		Ok(chains
+			.into_iter()
+			.map(|chain| (ChainKey(chain.chain_id.clone()), chain))
+			.collect())
+	}
+}
+
+impl Loader<TokenKey> for IndexerLoader {
+	type Value = token_meta::Model;
+	type Error = async_graphql::Error;
+
+	async fn load(&self, keys: &[TokenKey]) -> Result<HashMap<TokenKey, Self::Value>> {
+		let token_ids = keys.iter().map(|key| key.0.clone()).collect();
+		let tokens = Query::get_tokens_by_ids(&self.0, token_ids).await?;
+		Ok(tokens
+			.into_iter()
+			.map(|token| (TokenKey(token.token_id.clone()), token))
+			.collect())
+	}
+}
+
+impl Loader<TokenVolumeKey> for IndexerLoader {
+	type Value = token_volume::Model;
+	type Error = async_graphql::Error;
+
+	async fn load(&self, keys: &[TokenVolumeKey]) -> Result<HashMap<TokenVolumeKey, Self::Value>> {
+		let token_ids = keys.iter().map(|key| key.0.clone()).collect();
+		let volumes = Query::get_token_volumes_by_ids(&self.0, token_ids).await?;
+		Ok(volumes
+			.into_iter()
+			.map(|volume| (TokenVolumeKey(volume.token_id.clone()), volume))
+			.collect())
+	}
+}
+
+impl Loader<OnChainKey> for IndexerLoader {
+	type Value = Vec<token_on_chain::Model>;
+	type Error = async_graphql::Error;
+
+	async fn load(&self, keys: &[OnChainKey]) -> Result<HashMap<OnChainKey, Self::Value>> {
+		let (mut chain_ids, mut token_ids) = (vec![], vec![]);
+		for key in keys {
+			match key {
+				OnChainKey::Chain(chain_id) => chain_ids.push(chain_id.clone()),
+				OnChainKey::Token(token_id) => token_ids.push(token_id.clone()),
+			}
+		}
+		let rows = Query::get_token_on_chains_of(&self.0, chain_ids, token_ids).await?;
+		// a row is listed under its chain and under its token, keys without rows stay empty
+		let mut loaded: HashMap<_, Vec<_>> = keys.iter().map(|key| (key.clone(), vec![])).collect();
+		for row in rows {
+			for key in [
+				OnChainKey::Chain(row.chain_id.clone()),
+				OnChainKey::Token(row.token_id.clone()),
+			] {
+				if let Some(rows) = loaded.get_mut(&key) {
+					rows.push(row.clone());
+				}
+			}
+		}
+		Ok(loaded)
+	}
+}
+
+impl Loader<LedgerIdKey> for IndexerLoader {
+	type Value = Vec<token_ledger_id_on_chain::Model>;
+	type Error = async_graphql::Error;
+
+	async fn load(&self, keys: &[LedgerIdKey]) -> Result<HashMap<LedgerIdKey, Self::Value>> {
+		let (mut chain_ids, mut token_ids) = (vec![], vec![]);
+		for key in keys {
+			match key {
+				LedgerIdKey::Chain(chain_id) => chain_ids.push(chain_id.clone()),
+				LedgerIdKey::Token(token_id) => token_ids.push(token_id.clone()),
+			}
+		}
+		let rows = Query::get_token_ledger_ids_of(&self.0, chain_ids, token_ids).await?;
+		let mut loaded: HashMap<_, Vec<_>> = keys.iter().map(|key| (key.clone(), vec![])).collect();
+		for row in rows {
+			for key in [
+				LedgerIdKey::Chain(row.chain_id.clone()),
+				LedgerIdKey::Token(row.token_id.clone()),
+			] {
+				if let Some(rows) = loaded.get_mut(&key) {
+					rows.push(row.clone());
+				}
+			}
+		}
+		Ok(loaded)
+	}
+}
+
+// `rows` are fetched with one row over the limit to tell whether there is a next page
+fn connection<R, T: OutputType + From<R>>(
+	mut rows: Vec<R>,
+	limit: u64,
+	has_previous_page: bool,
+	key: impl Fn(&R) -> String,
+) -> Connection<String, T> {
+	let has_next_page = rows.len() as u64 > limit;
+	rows.truncate(limit as usize);
+	let mut connection = Connection::new(has_previous_page, has_next_page);
+	connection.edges.extend(
+		rows.into_iter()
+			.map(|row| Edge::new(encode_cursor(&key(&row)), T::from(row))),
+	);
+	connection
+}
+
+fn first_limit(first: Option<i32>) -> u64 {
+	page_limit(first.map(|first| first.max(0) as u64))
+}
+
+#[derive(Debug, Default, InputObject)]
+pub struct TicketFilterInput {
+	// matches either end of the ticket
+	pub chain: Option<String>,
+	// matches the sender or the receiver
+	pub address: Option<String>,
+	pub src_chain: Option<String>,
+	pub dst_chain: Option<String>,
+	pub token: Option<String>,
+	pub status: Option<String>,
+	// the ticket time range in nanoseconds, `to_time` is exclusive
+	pub from_time: Option<i64>,
+	pub to_time: Option<i64>,
+}
+
+impl TryFrom<TicketFilterInput> for TicketFilter {
+	type Error = async_graphql::Error;
+
+	fn try_from(filter: TicketFilterInput) -> Result<Self> {
+		Ok(TicketFilter {
+			chain: filter.chain,
+			address: filter.address,
+			src_chain: filter.src_chain,
+			dst_chain: filter.dst_chain,
+			token: filter.token,
+			status: filter.status.as_deref().map(parse_status).transpose()?,
+			from_time: filter.from_time,
+			to_time: filter.to_time,
+		})
+	}
+}
+
+async fn bridge_fee_logs(
+	db: &DbConn,
+	chain_id: Option<String>,
+	from: Option<NaiveDate>,
+	to: Option<NaiveDate>,
+	first: Option<i32>,
+	after: Option<String>,
+) -> Result<Connection<String, BridgeFeeLogDto>> {
+	let after = after.as_deref().map(decode_pair_cursor).transpose()?;
+	let has_previous_page = after.is_some();
+	let limit = first_limit(first);
+	let logs = Query::get_bridge_fee_logs(db, chain_id, from, to, after, limit + 1).await?;
+	Ok(connection(logs, limit, has_previous_page, |log| {
+		format!("{}:{}", log.date, log.chain_id)
+	}))
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+	async fn ticket(&self, ctx: &Context<'_>, ticket_id: String) -> Result<Option<TicketDto>> {
+		Ok(Query::get_ticket_by_id(db(ctx)?, ticket_id)
+			.await?
+			.map(Into::into))
+	}
+
+	async fn ticket_timeline(
+		&self,
+		ctx: &Context<'_>,
+		ticket_id: String,
+	) -> Result<Option<TicketTimelineDto>> {
+		Ok(resolve_timeline(db(ctx)?, ticket_id).await?)
+	}
+
+	// the tickets whose id, tx hash or intermediate tx hash is the hash
+	async fn tickets_by_hash(
+		&self,
+		ctx: &Context<'_>,
+		hash: String,
+	) -> Result<Vec<TicketHashMatchDto>> {
+		Ok(resolve_hash(db(ctx)?, &hash).await?)
+	}
+
+	// newest first
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn tickets(
+		&self,
+		ctx: &Context<'_>,
+		filter: Option<TicketFilterInput>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, TicketDto>> {
+		let filter = filter.unwrap_or_default().try_into()?;
+		let after = after.as_deref().map(decode_ticket_cursor).transpose()?;
+		let has_previous_page = after.is_some();
+		let limit = first_limit(first);
+		let tickets = Query::get_tickets(db(ctx)?, filter, after, limit + 1).await?;
+		Ok(connection(tickets, limit, has_previous_page, |ticket| {
+			ticket_key(ticket.ticket_time, &ticket.ticket_id)
+		}))
+	}
+
+	// the totals of the tickets the address sent or received, its tickets are listed by
+	// `tickets(filter: { address })`
+	async fn address(&self, ctx: &Context<'_>, address: String) -> Result<AddressDto> {
+		let summary = Query::get_address_summary(db(ctx)?, address.clone()).await?;
+		Ok(AddressDto::new(address, summary))
+	}
+
+	async fn chain(&self, ctx: &Context<'_>, chain_id: String) -> Result<Option<ChainDto>> {
+		Ok(Query::get_chain_by_id(db(ctx)?, chain_id)
+			.await?
+			.map(Into::into))
+	}
+
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn chains(
+		&self,
+		ctx: &Context<'_>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, ChainDto>> {
+		let after = after.as_deref().map(decode_cursor).transpose()?;
+		let has_previous_page = after.is_some();
+		let limit = first_limit(first);
+		let chains = Query::get_chains(db(ctx)?, after, limit + 1).await?;
+		Ok(connection(chains, limit, has_previous_page, |chain| {
+			chain.chain_id.clone()
+		}))
+	}
+
+	async fn token(&self, ctx: &Context<'_>, token_id: String) -> Result<Option<TokenDto>> {
+		Ok(Query::get_token_by_id(db(ctx)?, token_id)
+			.await?
+			.map(Into::into))
+	}
+
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn tokens(
+		&self,
+		ctx: &Context<'_>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, TokenDto>> {
+		let after = after.as_deref().map(decode_cursor).transpose()?;
+		let has_previous_page = after.is_some();
+		let limit = first_limit(first);
+		let tokens = Query::get_tokens(db(ctx)?, after, limit + 1).await?;
+		Ok(connection(tokens, limit, has_previous_page, |token| {
+			token.token_id.clone()
+		}))
+	}
+
+	async fn token_on_chains(
+		&self,
+		ctx: &Context<'_>,
+		chain_id: Option<String>,
+		token_id: Option<String>,
+	) -> Result<Vec<TokenOnChainDto>> {
+		let token_on_chains = Query::get_token_on_chains(db(ctx)?, chain_id, token_id).await?;
+		Ok(token_on_chains.into_iter().map(Into::into).collect())
+	}
+
+	async fn token_ledger_ids(
+		&self,
+		ctx: &Context<'_>,
+		chain_id: Option<String>,
+		token_id: Option<String>,
+	) -> Result<Vec<TokenLedgerIdDto>> {
+		let ledger_ids = Query::get_token_ledger_ids(db(ctx)?, chain_id, token_id).await?;
+		Ok(ledger_ids.into_iter().map(Into::into).collect())
+	}
+
+	async fn token_volume(
+		&self,
+		ctx: &Context<'_>,
+		token_id: String,
+	) -> Result<Option<TokenVolumeDto>> {
+		Ok(Query::get_token_volume(db(ctx)?, token_id)
+			.await?
+			.map(Into::into))
+	}
+
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn token_volumes(
+		&self,
+		ctx: &Context<'_>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, TokenVolumeDto>> {
+		let after = after.as_deref().map(decode_cursor).transpose()?;
+		let has_previous_page = after.is_some();
+		let limit = first_limit(first);
+		let volumes = Query::get_token_volumes(db(ctx)?, after, limit + 1).await?;
+		Ok(connection(volumes, limit, has_previous_page, |volume| {
+			volume.token_id.clone()
+		}))
+	}
+
+	// newest day first, `from` and `to` are inclusive
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn bridge_fee_logs(
+		&self,
+		ctx: &Context<'_>,
+		chain_id: Option<String>,
+		from: Option<NaiveDate>,
+		to: Option<NaiveDate>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, BridgeFeeLogDto>> {
+		bridge_fee_logs(db(ctx)?, chain_id, from, to, first, after).await
+	}
+}
+
+#[ComplexObject]
+impl TicketDto {
+	async fn src_chain_meta(&self, ctx: &Context<'_>) -> Result<Option<ChainDto>> {
+		Ok(loader(ctx)?
+			.load_one(ChainKey(self.src_chain.clone()))
+			.await?
+			.map(Into::into))
+	}
+
+	async fn dst_chain_meta(&self, ctx: &Context<'_>) -> Result<Option<ChainDto>> {
+		Ok(loader(ctx)?
+			.load_one(ChainKey(self.dst_chain.clone()))
+			.await?
+			.map(Into::into))
+	}
+
+	async fn token_meta(&self, ctx: &Context<'_>) -> Result<Option<TokenDto>> {
+		Ok(loader(ctx)?
+			.load_one(TokenKey(self.token.clone()))
+			.await?
+			.map(Into::into))
+	}
+}
+
+#[ComplexObject]
+impl ChainDto {
+	#[graphql(complexity = "first_limit(first) as usize * child_complexity")]
+	async fn fee_logs(
+		&self,
+		ctx: &Context<'_>,
+		from: Option<NaiveDate>,
+		to: Option<NaiveDate>,
+		first: Option<i32>,
+		after: Option<String>,
+	) -> Result<Connection<String, BridgeFeeLogDto>> {
+		bridge_fee_logs(
+			db(ctx)?,
+			Some(self.chain_id.clone()),
+			from,
+			to,
+			first,
+			after,
+		)
+		.await
+	}
+
+	async fn tokens(&self, ctx: &Context<'_>) -> Result<Vec<TokenOnChainDto>> {
+		let token_on_chains = loader(ctx)?
+			.load_one(OnChainKey::Chain(self.chain_id.clone()))
+			.await?
+			.unwrap_or_default();
+		Ok(token_on_chains.into_iter().map(Into::into).collect())
+	}
+
+	async fn ledger_ids(&self, ctx: &Context<'_>) -> Result<Vec<TokenLedgerIdDto>> {
+		let ledger_ids = loader(ctx)?
+			.load_one(LedgerIdKey::Chain(self.chain_id.clone()))
+			.await?
+			.unwrap_or_default();
+		Ok(ledger_ids.into_iter().map(Into::into).collect())
+	}
+}
+
+#[ComplexObject]
+impl TokenDto {
+	async fn ledger_ids(&self, ctx: &Context<'_>) -> Result<Vec<TokenLedgerIdDto>> {
+		let ledger_ids = loader(ctx)?
+			.load_one(LedgerIdKey::Token(self.token_id.clone()))
+			.await?
+			.unwrap_or_default();
+		Ok(ledger_ids.into_iter().map(Into::into).collect())
+	}
+
+	async fn on_chains(&self, ctx: &Context<'_>) -> Result<Vec<TokenOnChainDto>> {
+		let token_on_chains = loader(ctx)?
+			.load_one(OnChainKey::Token(self.token_id.clone()))
+			.await?
+			.unwrap_or_default();
+		Ok(token_on_chains.into_iter().map(Into::into).collect())
+	}
+
+	async fn volume(&self, ctx: &Context<'_>) -> Result<Option<TokenVolumeDto>> {
+		Ok(loader(ctx)?
+			.load_one(TokenVolumeKey(self.token_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+}
+
+#[ComplexObject]
+impl TokenOnChainDto {
+	async fn chain(&self, ctx: &Context<'_>) -> Result<Option<ChainDto>> {
+		Ok(loader(ctx)?
+			.load_one(ChainKey(self.chain_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+
+	async fn token(&self, ctx: &Context<'_>) -> Result<Option<TokenDto>> {
+		Ok(loader(ctx)?
+			.load_one(TokenKey(self.token_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+}
+
+#[ComplexObject]
+impl TokenLedgerIdDto {
+	async fn chain(&self, ctx: &Context<'_>) -> Result<Option<ChainDto>> {
+		Ok(loader(ctx)?
+			.load_one(ChainKey(self.chain_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+
+	async fn token(&self, ctx: &Context<'_>) -> Result<Option<TokenDto>> {
+		Ok(loader(ctx)?
+			.load_one(TokenKey(self.token_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+}
+
+#[ComplexObject]
+impl BridgeFeeLogDto {
+	async fn chain(&self, ctx: &Context<'_>) -> Result<Option<ChainDto>> {
+		Ok(loader(ctx)?
+			.load_one(ChainKey(self.chain_id.clone()))
+			.await?
+			.map(Into::into))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn nested_fee_logs(first: i32) -> String {
+		format!(
+			"{{ chains(first: {first}) {{ nodes {{ chainId feeLogs(first: {first}) {{ nodes {{ date amount }} }} }} }} }}"
+		)
+	}
+
+	// the complexity is checked before any resolver runs, the queries only validate
+	#[tokio::test]
+	async fn paged_fields_cost_their_page_size() {
+		let schema = schema(Arc::new(DbConn::default()));
+		let errors = |first| {
+			let request = async_graphql::Request::new(nested_fee_logs(first)).only_introspection();
+			let schema = schema.clone();
+			async move { schema.execute(request).await.errors }
+		};
+		assert_eq!(errors(200).await[0].message, "Query is too complex.");
+		assert!(errors(10).await.is_empty());
+	}
+}
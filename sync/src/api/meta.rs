@@ -0,0 +1,95 @@
+use super::{
+	dto::{ChainDto, TokenDto},
+	error::{ApiError, ErrorDto},
+	pagination::{decode_cursor, page_limit, Page, PageParams},
+	ApiState,
+};
+use crate::service::Query as Dao;
+use axum::{
+	extract::{Path, Query, State},
+	Json,
+};
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/chains",
+	tag = "chains",
+	params(PageParams),
+	responses(
+		(status = 200, body = Page<ChainDto>),
+		(status = 400, description = "Invalid parameters", body = ErrorDto),
+	)
+)]
+pub async fn list_chains(
+	State(state): State<ApiState>,
+	Query(params): Query<PageParams>,
+) -> Result<Json<Page<ChainDto>>, ApiError> {
+	let after = params.cursor.as_deref().map(decode_cursor).transpose()?;
+	let limit = page_limit(params.limit);
+	let chains = Dao::get_chains(&state.db, after, limit + 1).await?;
+	Ok(Json(Page::new(chains, limit, |chain| {
+		chain.chain_id.clone()
+	})))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/chains/{chain_id}",
+	tag = "chains",
+	params(("chain_id" = String, Path)),
+	responses(
+		(status = 200, body = ChainDto),
+		(status = 404, description = "Not found", body = ErrorDto),
+	)
+)]
+pub async fn get_chain(
+	State(state): State<ApiState>,
+	Path(chain_id): Path<String>,
+) -> Result<Json<ChainDto>, ApiError> {
+	Dao::get_chain_by_id(&state.db, chain_id.clone())
+		.await?
+		.map(|chain| Json(chain.into()))
+		.ok_or_else(|| ApiError::NotFound(format!("chain {}", chain_id)))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/tokens",
+	tag = "tokens",
+	params(PageParams),
+	responses(
+		(status = 200, body = Page<TokenDto>),
+		(status = 400, description = "Invalid parameters", body = ErrorDto),
+	)
+)]
+pub async fn list_tokens(
+	State(state): State<ApiState>,
+	Query(params): Query<PageParams>,
+) -> Result<Json<Page<TokenDto>>, ApiError> {
+	let after = params.cursor.as_deref().map(decode_cursor).transpose()?;
+	let limit = page_limit(params.limit);
+	let tokens = Dao::get_tokens(&state.db, after, limit + 1).await?;
+	Ok(Json(Page::new(tokens, limit, |token| {
+		token.token_id.clone()
+	})))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/tokens/{token_id}",
+	tag = "tokens",
+	params(("token_id" = String, Path)),
+	responses(
+		(status = 200, body = TokenDto),
+		(status = 404, description = "Not found", body = ErrorDto),
+	)
+)]
+pub async fn get_token(
+	State(state): State<ApiState>,
+	Path(token_id): Path<String>,
+) -> Result<Json<TokenDto>, ApiError> {
+	Dao::get_token_by_id(&state.db, token_id.clone())
+		.await?
+		.map(|token| Json(token.into()))
+		.ok_or_else(|| ApiError::NotFound(format!("token {}", token_id)))
+}
@@ -0,0 +1,96 @@
+//! The REST and GraphQL apis over the indexed tickets, chains and tokens.
+
+pub mod addresses;
+pub mod auth;
+pub mod dto;
+pub mod error;
+pub mod graphql;
+pub mod meta;
+pub mod openapi;
+pub mod pagination;
+pub mod search;
+pub mod subscriptions;
+pub mod tickets;
+pub mod timeline;
+
+use crate::events::{self, EventBus};
+use async_graphql::http::GraphiQLSource;
+use async_graphql_axum::GraphQL;
+use axum::{middleware, response::Html, routing::get, Router};
+use log::info;
+use sea_orm::DbConn;
+use std::{error::Error, net::SocketAddr, sync::Arc};
+
+pub const DEFAULT_API_LISTEN_ADDR: &str = "0.0.0.0:8000";
+
+#[derive(Clone)]
+pub struct ApiState {
+	pub db: Arc<DbConn>,
+	pub events: EventBus,
+	pub limiter: auth::RateLimiter,
+}
+
+pub fn router(state: ApiState) -> Router {
+	let schema = graphql::schema(state.db.clone());
+	Router::new()
+		.route("/graphql", get(graphiql).post_service(GraphQL::new(schema)))
+		.route("/openapi.json", get(openapi::openapi_json))
+		.route("/api/v1/tickets", get(tickets::list_tickets))
+		.route("/api/v1/tickets/{ticket_id}", get(tickets::get_ticket))
+		.route(
+			"/api/v1/tickets/{ticket_id}/timeline",
+			get(timeline::get_ticket_timeline),
+		)
+		.route("/api/v1/chains", get(meta::list_chains))
+		.route("/api/v1/chains/{chain_id}", get(meta::get_chain))
+		.route("/api/v1/tokens", get(meta::list_tokens))
+		.route("/api/v1/tokens/{token_id}", get(meta::get_token))
+		.route("/api/v1/search/{hash}", get(search::search_hash))
+		.route("/api/v1/addresses/{address}", get(addresses::get_address))
+		.route(
+			"/api/v1/addresses/{address}/tickets",
+			get(addresses::list_address_tickets),
+		)
+		.route(
+			"/api/v1/events/tickets",
+			get(subscriptions::ticket_events_sse),
+		)
+		.route(
+			"/api/v1/events/tickets/ws",
+			get(subscriptions::ticket_events_ws),
+		)
+		.layer(middleware::from_fn_with_state(
+			state.clone(),
+			auth::limit_requests,
+		))
+		.with_state(state)
+}
+
+async fn graphiql() -> Html<String> {
+	Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+// the address from API_LISTEN_ADDR, or the default one
+pub fn api_listen_addr() -> String {
+	std::env::var("API_LISTEN_ADDR").unwrap_or_else(|_| DEFAULT_API_LISTEN_ADDR.to_owned())
+}
+
+pub async fn serve(db: Arc<DbConn>, addr: &str) -> Result<(), Box<dyn Error>> {
+	let listener = tokio::net::TcpListener::bind(addr).await?;
+	info!("api listening on {}", listener.local_addr()?);
+	let limiter = auth::RateLimiter::from_env()?;
+	let events = EventBus::new();
+	tokio::spawn(events::listen(db.clone(), events.clone()));
+	let router = router(ApiState {
+		db,
+		events,
+		limiter,
+	});
+	// the client address keys the anonymous rate limit
+	axum::serve(
+		listener,
+		router.into_make_service_with_connect_info::<SocketAddr>(),
+	)
+	.await?;
+	Ok(())
+}
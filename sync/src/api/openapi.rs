@@ -0,0 +1,121 @@
+//! The OpenAPI document of the REST api, served at `/openapi.json`. `openapi.json` at the crate
+//! root is the checked in copy clients are generated from, the test below keeps it current.
+
+use super::{addresses, auth, meta, search, subscriptions, tickets, timeline};
+use axum::Json;
+use utoipa::{
+	openapi::{
+		self,
+		security::{ApiKey, ApiKeyValue, SecurityRequirement, SecurityScheme},
+		Content, Ref, ResponseBuilder,
+	},
+	Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+	info(
+		title = "Omnity indexer api",
+		version = "1",
+		description = "Tickets, chains and tokens indexed from the omnity canisters. Raw amounts \
+			are decimal strings, times are unix nanoseconds."
+	),
+	paths(
+		tickets::list_tickets,
+		tickets::get_ticket,
+		timeline::get_ticket_timeline,
+		search::search_hash,
+		meta::list_chains,
+		meta::get_chain,
+		meta::list_tokens,
+		meta::get_token,
+		addresses::get_address,
+		addresses::list_address_tickets,
+		subscriptions::ticket_events_sse,
+		subscriptions::ticket_events_ws,
+	),
+	modifiers(&ManifestInfo, &ApiKeyAuth)
+)]
+pub struct ApiDoc;
+
+// utoipa fills the contact and license in from the crate manifest, they are not the api's
+struct ManifestInfo;
+
+impl Modify for ManifestInfo {
+	fn modify(&self, openapi: &mut openapi::OpenApi) {
+		openapi.info.contact = None;
+		openapi.info.license = None;
+	}
+}
+
+// every route goes through `auth::limit_requests`, the key is optional
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+	fn modify(&self, openapi: &mut openapi::OpenApi) {
+		let components = openapi.components.get_or_insert_with(Default::default);
+		components.add_security_scheme(
+			"api_key",
+			SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+				auth::API_KEY_HEADER,
+				"Raises the stricter anonymous rate limit to the limits of the key",
+			))),
+		);
+		openapi.security = Some(vec![
+			SecurityRequirement::default(),
+			SecurityRequirement::new("api_key", Vec::<String>::new()),
+		]);
+
+		let error = |description: &str| {
+			ResponseBuilder::new()
+				.description(description)
+				.content(
+					"application/json",
+					Content::new(Some(Ref::from_schema_name("Error"))),
+				)
+				.build()
+		};
+		for item in openapi.paths.paths.values_mut() {
+			let operations = [
+				&mut item.get,
+				&mut item.put,
+				&mut item.post,
+				&mut item.delete,
+				&mut item.patch,
+			];
+			for operation in operations.into_iter().flatten() {
+				let responses = &mut operation.responses.responses;
+				responses.insert("401".to_owned(), error("Unknown or revoked api key").into());
+				responses.insert(
+					"429".to_owned(),
+					error("Rate limit or daily quota exceeded, see Retry-After").into(),
+				);
+			}
+		}
+	}
+}
+
+pub async fn openapi_json() -> Json<openapi::OpenApi> {
+	Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// regenerate with `UPDATE_OPENAPI=1 cargo test -p omnity-indexer-sync openapi`
+	#[test]
+	fn checked_in_openapi_document_is_current() {
+		let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+		let document = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+		if std::env::var_os("UPDATE_OPENAPI").is_some() {
+			std::fs::write(path, &document).unwrap();
+		}
+		let checked_in = std::fs::read_to_string(path).unwrap_or_default();
+		assert!(
+			checked_in == document,
+			"{} is stale, regenerate it with UPDATE_OPENAPI=1",
+			path
+		);
+	}
+}
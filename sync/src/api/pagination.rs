@@ -0,0 +1,108 @@
+//! Cursor pagination, a cursor is the hex encoded key of the last item of the previous page.
+
+use super::error::ApiError;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_PAGE_LIMIT: u64 = 50;
+pub const MAX_PAGE_LIMIT: u64 = 200;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+	/// The `next_cursor` of the previous page
+	pub cursor: Option<String>,
+	/// 50 by default, at most 200
+	pub limit: Option<u64>,
+}
+
+pub fn page_limit(limit: Option<u64>) -> u64 {
+	limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+	pub items: Vec<T>,
+	pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+	// `rows` are fetched with one row over the limit to tell whether there is a next page
+	pub fn new<R>(mut rows: Vec<R>, limit: u64, key: impl Fn(&R) -> String) -> Self
+	where
+		T: From<R>,
+	{
+		let next_cursor = if rows.len() as u64 > limit {
+			rows.truncate(limit as usize);
+			rows.last().map(|row| encode_cursor(&key(row)))
+		} else {
+			None
+		};
+		Page {
+			items: rows.into_iter().map(T::from).collect(),
+			next_cursor,
+		}
+	}
+}
+
+pub fn encode_cursor(key: &str) -> String {
+	hex::encode(key)
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<String, ApiError> {
+	hex::decode(cursor)
+		.ok()
+		.and_then(|key| String::from_utf8(key).ok())
+		.ok_or_else(|| ApiError::BadRequest(format!("invalid cursor: {}", cursor)))
+}
+
+pub fn ticket_key(ticket_time: i64, ticket_id: &str) -> String {
+	format!("{}:{}", ticket_time, ticket_id)
+}
+
+// a key of two parts, the first one never holds a colon
+pub fn decode_pair_cursor(cursor: &str) -> Result<(String, String), ApiError> {
+	decode_cursor(cursor)?
+		.split_once(':')
+		.map(|(first, second)| (first.to_owned(), second.to_owned()))
+		.ok_or_else(|| ApiError::BadRequest(format!("invalid cursor: {}", cursor)))
+}
+
+// ticket ids may hold colons, the time never does
+pub fn decode_ticket_cursor(cursor: &str) -> Result<(i64, String), ApiError> {
+	let (time, id) = decode_pair_cursor(cursor)?;
+	let time = time
+		.parse()
+		.map_err(|_| ApiError::BadRequest(format!("invalid cursor: {}", cursor)))?;
+	Ok((time, id))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ticket_cursors_round_trip() {
+		let cursor = encode_cursor(&ticket_key(1_700_000_000_000_000_000, "Bitcoin:tx:0"));
+		assert_eq!(
+			decode_ticket_cursor(&cursor).unwrap(),
+			(1_700_000_000_000_000_000, "Bitcoin:tx:0".to_owned())
+		);
+		assert!(decode_ticket_cursor("zz").is_err());
+		assert!(decode_ticket_cursor(&encode_cursor("no-time")).is_err());
+	}
+
+	#[test]
+	fn pages_stop_at_the_limit() {
+		let page = Page::<String>::new(vec!["a", "b", "c"], 2, |row| row.to_string());
+		assert_eq!(page.items, vec!["a", "b"]);
+		assert_eq!(page.next_cursor, Some(encode_cursor("b")));
+
+		let page = Page::<String>::new(vec!["a", "b"], 2, |row| row.to_string());
+		assert_eq!(page.next_cursor, None);
+
+		assert_eq!(page_limit(None), DEFAULT_PAGE_LIMIT);
+		assert_eq!(page_limit(Some(0)), 1);
+		assert_eq!(page_limit(Some(1000)), MAX_PAGE_LIMIT);
+	}
+}
@@ -0,0 +1,52 @@
+use super::{
+	dto::TicketHashMatchDto,
+	error::{ApiError, ErrorDto},
+	ApiState,
+};
+use crate::service::Query as Dao;
+use axum::{
+	extract::{Path, State},
+	Json,
+};
+use sea_orm::{DbConn, DbErr};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HashSearch {
+	pub hash: String,
+	pub matches: Vec<TicketHashMatchDto>,
+}
+
+// the tickets known by the hash with the tickets linked to them
+pub async fn resolve_hash(db: &DbConn, hash: &str) -> Result<Vec<TicketHashMatchDto>, DbErr> {
+	let mut matches = Vec::new();
+	for found in Dao::search_ticket_hash(db, hash).await? {
+		let journey = Dao::get_ticket_journey(db, found.ticket.ticket_id.clone()).await?;
+		matches.push(TicketHashMatchDto::new(found, journey));
+	}
+	Ok(matches)
+}
+
+// resolves a ticket id, a tx hash, an intermediate tx hash or an icp `<ledger>_<block>` to
+// its tickets, archived mint tickets included
+#[utoipa::path(
+	get,
+	path = "/api/v1/search/{hash}",
+	tag = "tickets",
+	params(("hash" = String, Path)),
+	responses(
+		(status = 200, body = HashSearch),
+		(status = 404, description = "Not found", body = ErrorDto),
+	)
+)]
+pub async fn search_hash(
+	State(state): State<ApiState>,
+	Path(hash): Path<String>,
+) -> Result<Json<HashSearch>, ApiError> {
+	let matches = resolve_hash(&state.db, &hash).await?;
+	if matches.is_empty() {
+		return Err(ApiError::NotFound(format!("hash {}", hash)));
+	}
+	Ok(Json(HashSearch { hash, matches }))
+}
@@ -0,0 +1,104 @@
+//! Pushes the ticket events matching a subscriber's filter, over server-sent events or a
+//! websocket.
+
+use super::ApiState;
+use crate::events::{TicketEvent, TicketEventFilter};
+use axum::{
+	extract::{
+		ws::{Message, WebSocket, WebSocketUpgrade},
+		Query, State,
+	},
+	response::{
+		sse::{Event, KeepAlive, Sse},
+		Response,
+	},
+};
+use futures::{stream, SinkExt, Stream, StreamExt};
+use log::warn;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+// the next event matching `filter`, None once the bus is gone
+async fn next_event(
+	events: &mut Receiver<TicketEvent>,
+	filter: &TicketEventFilter,
+) -> Option<TicketEvent> {
+	loop {
+		match events.recv().await {
+			Ok(event) if filter.matches(&event) => return Some(event),
+			Ok(_) => continue,
+			Err(RecvError::Lagged(skipped)) => {
+				warn!("ticket event subscriber lagged, {} events skipped", skipped);
+			}
+			Err(RecvError::Closed) => return None,
+		}
+	}
+}
+
+// each event is named by its kind and carries the json event as data
+#[utoipa::path(
+	get,
+	path = "/api/v1/events/tickets",
+	tag = "events",
+	params(TicketEventFilter),
+	responses((status = 200, content_type = "text/event-stream", body = TicketEvent))
+)]
+pub async fn ticket_events_sse(
+	State(state): State<ApiState>,
+	Query(filter): Query<TicketEventFilter>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+	let events = state.events.subscribe();
+	let stream = stream::unfold((events, filter), |(mut events, filter)| async move {
+		let event = next_event(&mut events, &filter).await?;
+		let sse = Event::default()
+			.event(event.kind.as_str())
+			.json_data(&event)
+			.unwrap_or_default();
+		Some((Ok(sse), (events, filter)))
+	});
+	Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/events/tickets/ws",
+	tag = "events",
+	params(TicketEventFilter),
+	responses((
+		status = 101,
+		description = "A websocket of json ticket events as text messages",
+		body = TicketEvent
+	))
+)]
+pub async fn ticket_events_ws(
+	State(state): State<ApiState>,
+	Query(filter): Query<TicketEventFilter>,
+	ws: WebSocketUpgrade,
+) -> Response {
+	let events = state.events.subscribe();
+	ws.on_upgrade(move |socket| push_events(socket, events, filter))
+}
+
+async fn push_events(
+	socket: WebSocket,
+	mut events: Receiver<TicketEvent>,
+	filter: TicketEventFilter,
+) {
+	let (mut sink, mut incoming) = socket.split();
+	loop {
+		tokio::select! {
+			event = next_event(&mut events, &filter) => {
+				let Some(event) = event else { break };
+				let Ok(text) = serde_json::to_string(&event) else { continue };
+				if sink.send(Message::Text(text.into())).await.is_err() {
+					break;
+				}
+			}
+			// the client only ever closes the socket
+			message = incoming.next() => match message {
+				Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+				Some(Ok(_)) => continue,
+			},
+		}
+	}
+}
@@ -0,0 +1,107 @@
+use super::{
+	dto::TicketDto,
+	error::{ApiError, ErrorDto},
+	pagination::{decode_ticket_cursor, page_limit, ticket_key, Page},
+	ApiState,
+};
+use crate::{
+	entity::sea_orm_active_enums::TicketStatus,
+	service::{Query as Dao, TicketFilter},
+};
+use axum::{
+	extract::{Path, Query, State},
+	Json,
+};
+use sea_orm::ActiveEnum;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+// the doc comments below are the parameter descriptions of the openapi document
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TicketParams {
+	/// Either the source or the destination chain
+	pub chain: Option<String>,
+	/// Either the sender or the receiver
+	pub address: Option<String>,
+	pub src_chain: Option<String>,
+	pub dst_chain: Option<String>,
+	pub token: Option<String>,
+	pub status: Option<String>,
+	/// Lower bound of the ticket time in nanoseconds
+	pub from: Option<i64>,
+	/// Exclusive upper bound of the ticket time in nanoseconds
+	pub to: Option<i64>,
+	/// The `next_cursor` of the previous page
+	pub cursor: Option<String>,
+	/// 50 by default, at most 200
+	pub limit: Option<u64>,
+}
+
+impl TicketParams {
+	fn filter(&self) -> Result<TicketFilter, ApiError> {
+		let status = self.status.as_deref().map(parse_status).transpose()?;
+		Ok(TicketFilter {
+			chain: self.chain.clone(),
+			address: self.address.clone(),
+			src_chain: self.src_chain.clone(),
+			dst_chain: self.dst_chain.clone(),
+			token: self.token.clone(),
+			status,
+			from_time: self.from,
+			to_time: self.to,
+		})
+	}
+}
+
+pub fn parse_status(status: &str) -> Result<TicketStatus, ApiError> {
+	TicketStatus::try_from_value(&status.to_owned())
+		.map_err(|_| ApiError::BadRequest(format!("invalid status: {}", status)))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/tickets",
+	tag = "tickets",
+	params(TicketParams),
+	responses(
+		(status = 200, description = "Tickets, newest first", body = Page<TicketDto>),
+		(status = 400, description = "Invalid parameters", body = ErrorDto),
+	)
+)]
+pub async fn list_tickets(
+	State(state): State<ApiState>,
+	Query(params): Query<TicketParams>,
+) -> Result<Json<Page<TicketDto>>, ApiError> {
+	let filter = params.filter()?;
+	let after = params
+		.cursor
+		.as_deref()
+		.map(decode_ticket_cursor)
+		.transpose()?;
+	let limit = page_limit(params.limit);
+	let tickets = Dao::get_tickets(&state.db, filter, after, limit + 1).await?;
+	Ok(Json(Page::new(tickets, limit, |ticket| {
+		ticket_key(ticket.ticket_time, &ticket.ticket_id)
+	})))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/tickets/{ticket_id}",
+	tag = "tickets",
+	params(("ticket_id" = String, Path)),
+	responses(
+		(status = 200, body = TicketDto),
+		(status = 404, description = "Not found", body = ErrorDto),
+	)
+)]
+pub async fn get_ticket(
+	State(state): State<ApiState>,
+	Path(ticket_id): Path<String>,
+) -> Result<Json<TicketDto>, ApiError> {
+	Dao::get_ticket_by_id(state.db.as_ref(), ticket_id.clone())
+		.await?
+		.map(|ticket| Json(ticket.into()))
+		.ok_or_else(|| ApiError::NotFound(format!("ticket {}", ticket_id)))
+}
@@ -0,0 +1,353 @@
+//! The lifecycle of a ticket, from its hub ticket time through the events the sync logged to its
+//! finalization, with the time spent in between.
+
+use super::{
+	dto::{LatencyDto, StatusStageDto, TicketHopDto, TicketTimelineDto, TimelineStepDto},
+	error::{ApiError, ErrorDto},
+	ApiState,
+};
+use crate::entity::{sea_orm_active_enums::TicketStatus, ticket, ticket_transition};
+use crate::events::TicketEventKind;
+use crate::service::Query as Dao;
+use axum::{
+	extract::{Path, State},
+	Json,
+};
+use sea_orm::{ActiveEnum, DbConn, DbErr};
+use std::collections::{HashMap, HashSet};
+
+pub const STEP_TICKET_TIME: &str = "ticket_time";
+pub const STEP_SUB_STATUS: &str = "sub_status";
+
+fn millis(nanos: i64) -> i64 {
+	nanos / 1_000_000
+}
+
+// the times the sync first saw the ticket and saw it finalized, the inferred rows of the tickets
+// indexed before the log say neither
+fn indexed_and_finalized_at(
+	transitions: &[&ticket_transition::Model],
+) -> (Option<i64>, Option<i64>) {
+	let finalized = TicketStatus::Finalized.to_value();
+	let mut transitions = transitions.iter().filter(|transition| !transition.inferred);
+	let indexed_at = transitions
+		.clone()
+		.find(|transition| transition.kind == TicketEventKind::Created.as_str())
+		.map(|transition| transition.occurred_at);
+	let finalized_at = transitions
+		.find(|transition| transition.status == finalized)
+		.map(|transition| transition.occurred_at);
+	(indexed_at, finalized_at)
+}
+
+fn step(kind: &str, at: i64) -> TimelineStepDto {
+	TimelineStepDto {
+		kind: kind.to_owned(),
+		at,
+		status: None,
+		previous_status: None,
+		sub_status: None,
+		tx_hash: None,
+		intermediate_tx_hash: None,
+		since_previous_ms: 0,
+		since_ticket_time_ms: 0,
+		inferred: false,
+	}
+}
+
+// `transitions` are those of the tickets of `journey` in the order they were logged, `journey`
+// holds every hop of the transfer, the ticket included
+pub fn build_timeline(
+	ticket: ticket::Model,
+	transitions: Vec<ticket_transition::Model>,
+	journey: Vec<ticket::Model>,
+) -> TicketTimelineDto {
+	let mut by_ticket: HashMap<&str, Vec<&ticket_transition::Model>> = HashMap::new();
+	for transition in &transitions {
+		by_ticket
+			.entry(transition.ticket_id.as_str())
+			.or_default()
+			.push(transition);
+	}
+	let own = by_ticket
+		.get(ticket.ticket_id.as_str())
+		.cloned()
+		.unwrap_or_default();
+	let (indexed_at, finalized_at) = indexed_and_finalized_at(&own);
+
+	let mut steps = Vec::new();
+	for transition in &own {
+		steps.push(TimelineStepDto {
+			status: Some(transition.status.clone()),
+			previous_status: transition.previous_status.clone(),
+			tx_hash: transition.tx_hash.clone(),
+			intermediate_tx_hash: transition.intermediate_tx_hash.clone(),
+			inferred: transition.inferred,
+			..step(&transition.kind, transition.occurred_at)
+		});
+	}
+	if let Some(serde_json::Value::Object(first_seen)) = &ticket.sub_status_first_seen {
+		for (sub_status, at) in first_seen {
+			if let Some(at) = at.as_i64() {
+				steps.push(TimelineStepDto {
+					sub_status: Some(sub_status.clone()),
+					..step(STEP_SUB_STATUS, at)
+				});
+			}
+		}
+	}
+	// stable, the events logged at the same time keep their order
+	steps.sort_by_key(|step| step.at);
+	steps.insert(0, step(STEP_TICKET_TIME, ticket.ticket_time));
+	let mut previous = ticket.ticket_time;
+	for step in &mut steps {
+		step.since_previous_ms = millis(step.at - previous);
+		step.since_ticket_time_ms = millis(step.at - ticket.ticket_time);
+		previous = step.at;
+	}
+
+	let mut stages: Vec<StatusStageDto> = Vec::new();
+	for transition in &own {
+		if transition.inferred
+			|| transition.kind == TicketEventKind::TxHashChanged.as_str()
+			|| stages
+				.last()
+				.is_some_and(|stage| stage.status == transition.status)
+		{
+			continue;
+		}
+		if let Some(stage) = stages.last_mut() {
+			stage.left_at = Some(transition.occurred_at);
+			stage.duration_ms = Some(millis(transition.occurred_at - stage.entered_at));
+		}
+		stages.push(StatusStageDto {
+			status: transition.status.clone(),
+			entered_at: transition.occurred_at,
+			left_at: None,
+			duration_ms: None,
+		});
+	}
+
+	let hops: Vec<TicketHopDto> = journey
+		.into_iter()
+		.map(|hop| {
+			let (indexed_at, finalized_at) = indexed_and_finalized_at(
+				by_ticket
+					.get(hop.ticket_id.as_str())
+					.map(Vec::as_slice)
+					.unwrap_or_default(),
+			);
+			TicketHopDto {
+				ticket_id: hop.ticket_id,
+				src_chain: hop.src_chain,
+				dst_chain: hop.dst_chain,
+				status: hop.status.to_value(),
+				ticket_time: hop.ticket_time,
+				indexed_at,
+				finalized_at,
+				tx_hash: hop.tx_hash,
+				intermediate_tx_hash: hop.intermediate_tx_hash,
+			}
+		})
+		.collect();
+	// only once every hop is finalized
+	let journey_ms = hops
+		.iter()
+		.map(|hop| hop.finalized_at)
+		.collect::<Option<Vec<_>>>()
+		.and_then(|finalized| finalized.into_iter().max())
+		.zip(hops.iter().map(|hop| hop.ticket_time).min())
+		.map(|(finalized_at, ticket_time)| millis(finalized_at - ticket_time));
+
+	TicketTimelineDto {
+		latency: LatencyDto {
+			hub_to_indexed_ms: indexed_at.map(|at| millis(at - ticket.ticket_time)),
+			indexed_to_finalized_ms: indexed_at
+				.zip(finalized_at)
+				.map(|(indexed_at, finalized_at)| millis(finalized_at - indexed_at)),
+			total_ms: finalized_at.map(|at| millis(at - ticket.ticket_time)),
+			journey_ms,
+		},
+		ticket: ticket.into(),
+		indexed_at,
+		finalized_at,
+		steps,
+		stages,
+		hops,
+	}
+}
+
+pub async fn resolve_timeline(
+	db: &DbConn,
+	ticket_id: String,
+) -> Result<Option<TicketTimelineDto>, DbErr> {
+	let Some(ticket) = Dao::get_ticket_by_id(db, ticket_id.clone()).await? else {
+		return Ok(None);
+	};
+	let journey = Dao::get_ticket_journey(db, ticket_id.clone()).await?;
+	let ticket_ids = journey
+		.iter()
+		.map(|hop| hop.ticket_id.clone())
+		.chain([ticket_id])
+		.collect::<HashSet<_>>();
+	let transitions = Dao::get_ticket_transitions(db, ticket_ids.into_iter().collect()).await?;
+	Ok(Some(build_timeline(ticket, transitions, journey)))
+}
+
+#[utoipa::path(
+	get,
+	path = "/api/v1/tickets/{ticket_id}/timeline",
+	tag = "tickets",
+	params(("ticket_id" = String, Path)),
+	responses(
+		(status = 200, body = TicketTimelineDto),
+		(status = 404, description = "Not found", body = ErrorDto),
+	)
+)]
+pub async fn get_ticket_timeline(
+	State(state): State<ApiState>,
+	Path(ticket_id): Path<String>,
+) -> Result<Json<TicketTimelineDto>, ApiError> {
+	resolve_timeline(&state.db, ticket_id.clone())
+		.await?
+		.map(Json)
+		.ok_or_else(|| ApiError::NotFound(format!("ticket {}", ticket_id)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+	use serde_json::json;
+
+	const SECOND: i64 = 1_000_000_000;
+
+	fn transition(
+		id: i64,
+		ticket_id: &str,
+		kind: TicketEventKind,
+		status: &str,
+		at: i64,
+	) -> ticket_transition::Model {
+		ticket_transition::Model {
+			id,
+			ticket_id: ticket_id.to_owned(),
+			kind: kind.as_str().to_owned(),
+			status: status.to_owned(),
+			previous_status: None,
+			tx_hash: None,
+			intermediate_tx_hash: None,
+			occurred_at: at,
+			inferred: false,
+		}
+	}
+
+	#[test]
+	fn timelines_break_the_latency_down() {
+		let mut ticket = fixtures::ticket("t1", "Bitcoin", "eICP");
+		ticket.ticket_time = 100 * SECOND;
+		ticket.status = TicketStatus::Finalized;
+		ticket.sub_status_first_seen = Some(json!({ "Signing": 130 * SECOND }));
+		let mut release = fixtures::ticket("t2", "eICP", "Bitcoin");
+		release.ticket_time = 200 * SECOND;
+		let transitions = vec![
+			transition(
+				1,
+				"t1",
+				TicketEventKind::Created,
+				"WaitingForConfirmByDest",
+				110 * SECOND,
+			),
+			transition(
+				2,
+				"t1",
+				TicketEventKind::TxHashChanged,
+				"WaitingForConfirmByDest",
+				140 * SECOND,
+			),
+			transition(
+				3,
+				"t1",
+				TicketEventKind::StatusChanged,
+				"Finalized",
+				160 * SECOND,
+			),
+			transition(4, "t2", TicketEventKind::Created, "Finalized", 250 * SECOND),
+		];
+
+		let timeline = build_timeline(
+			ticket.clone(),
+			transitions.clone(),
+			vec![ticket.clone(), release.clone()],
+		);
+		assert_eq!(timeline.indexed_at, Some(110 * SECOND));
+		assert_eq!(timeline.finalized_at, Some(160 * SECOND));
+		assert_eq!(
+			timeline.latency,
+			LatencyDto {
+				hub_to_indexed_ms: Some(10_000),
+				indexed_to_finalized_ms: Some(50_000),
+				total_ms: Some(60_000),
+				journey_ms: Some(150_000),
+			}
+		);
+
+		let steps: Vec<_> = timeline
+			.steps
+			.iter()
+			.map(|step| (step.kind.as_str(), step.since_previous_ms))
+			.collect();
+		assert_eq!(
+			steps,
+			vec![
+				(STEP_TICKET_TIME, 0),
+				("created", 10_000),
+				(STEP_SUB_STATUS, 20_000),
+				("tx_hash_changed", 10_000),
+				("status_changed", 20_000),
+			]
+		);
+		assert_eq!(timeline.steps[4].since_ticket_time_ms, 60_000);
+
+		let stages: Vec<_> = timeline
+			.stages
+			.iter()
+			.map(|stage| (stage.status.as_str(), stage.duration_ms))
+			.collect();
+		assert_eq!(
+			stages,
+			vec![
+				("WaitingForConfirmByDest", Some(50_000)),
+				("Finalized", None)
+			]
+		);
+		assert_eq!(timeline.hops[1].finalized_at, Some(250 * SECOND));
+
+		// a hop not yet finalized leaves the journey open
+		let timeline = build_timeline(
+			ticket.clone(),
+			transitions[..3].to_vec(),
+			vec![ticket, release],
+		);
+		assert_eq!(timeline.latency.journey_ms, None);
+		assert_eq!(timeline.latency.total_ms, Some(60_000));
+	}
+
+	#[test]
+	fn inferred_events_are_steps_only() {
+		let mut ticket = fixtures::ticket("t1", "Bitcoin", "eICP");
+		ticket.ticket_time = 100 * SECOND;
+		ticket.status = TicketStatus::Finalized;
+		let mut created = transition(1, "t1", TicketEventKind::Created, "Finalized", 100 * SECOND);
+		created.inferred = true;
+
+		let timeline = build_timeline(ticket.clone(), vec![created], vec![ticket]);
+		assert_eq!(timeline.steps.len(), 2);
+		assert!(timeline.steps[1].inferred);
+		assert_eq!(timeline.indexed_at, None);
+		assert_eq!(timeline.finalized_at, None);
+		assert_eq!(timeline.latency, LatencyDto::default());
+		assert!(timeline.stages.is_empty());
+		assert_eq!(timeline.hops[0].finalized_at, None);
+	}
+}
@@ -0,0 +1,405 @@
+//! Streams the changes of the index database to other services.
+//!
+//! Every insert, update or delete of the `Mutation` and `Delete` layers writes an `outbox` row in
+//! the transaction of the change, the publisher task then sends the unpublished rows in id order
+//! to the sink set by `OUTBOX_SINK`. A row is only marked published once the sink accepted it and the publisher
+//! stops at the first failure, so delivery is at-least-once and the changes of a ticket keep
+//! their order: a write waits on the row lock of the ticket until the previous one commits.
+
+use crate::entity::outbox;
+use crate::service::{Delete, Mutation, Query};
+use anyhow::{anyhow, Result};
+use async_nats::jetstream;
+use chrono::Utc;
+use log::info;
+use sea_orm::{ConnectionTrait, DbBackend, DbConn, Statement};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+
+pub const OUTBOX_PUBLISH_INTERVAL: u64 = 2;
+pub const OUTBOX_BATCH_SIZE: u64 = 500;
+// published rows are pruned after a week
+const OUTBOX_RETENTION: i64 = 7 * 24 * 3600 * 1_000_000_000;
+
+pub const OP_INSERT: &str = "insert";
+pub const OP_UPDATE: &str = "update";
+// written by an upsert, the row may or may not be new
+pub const OP_UPSERT: &str = "upsert";
+// the payload is the row as it was before the delete
+pub const OP_DELETE: &str = "delete";
+// an aggregate recomputed from the tickets, the payload holds the recomputed range
+pub const OP_REFRESH: &str = "refresh";
+
+pub const OUTBOX_CHANNEL: &str = "outbox";
+// pg_notify refuses payloads from 8000 bytes on
+const NOTIFY_PAYLOAD_LIMIT: usize = 7999;
+pub const NATS_STREAM: &str = "OMNITY_OUTBOX";
+pub const NATS_SUBJECT_PREFIX: &str = "omnity.outbox";
+
+// a table of the change stream and the columns of its key, the topic is the table name
+pub struct Topic {
+	pub table: &'static str,
+	pub keys: &'static [&'static str],
+}
+
+pub const CHAIN_META: Topic = Topic {
+	table: "chain_meta",
+	keys: &["chain_id"],
+};
+pub const TOKEN_META: Topic = Topic {
+	table: "token_meta",
+	keys: &["token_id"],
+};
+pub const TOKEN_ON_CHAIN: Topic = Topic {
+	table: "token_on_chain",
+	keys: &["chain_id", "token_id"],
+};
+pub const TOKEN_LEDGER_ID_ON_CHAIN: Topic = Topic {
+	table: "token_ledger_id_on_chain",
+	keys: &["chain_id", "token_id"],
+};
+pub const TICKET: Topic = Topic {
+	table: "ticket",
+	keys: &["ticket_id"],
+};
+pub const TICKET_LINK: Topic = Topic {
+	table: "ticket_link",
+	keys: &["parent_id", "child_id"],
+};
+pub const DELETED_MINT_TICKET: Topic = Topic {
+	table: "deleted_mint_ticket",
+	keys: &["ticket_id"],
+};
+pub const PENDING_TICKET: Topic = Topic {
+	table: "pending_ticket",
+	keys: &["ticket_index"],
+};
+pub const TOKEN_VOLUME: Topic = Topic {
+	table: "token_volume",
+	keys: &["token_id"],
+};
+pub const RUNE_TERMS: Topic = Topic {
+	table: "rune_terms",
+	keys: &["rune_id"],
+};
+pub const LAUNCHPAD: Topic = Topic {
+	table: "launchpad",
+	keys: &["launchpad"],
+};
+
+// what the sinks receive, `id` is increasing and lets the consumers drop the redeliveries
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+	pub id: i64,
+	pub topic: String,
+	// the key columns of the row joined by `:`
+	pub key: String,
+	pub op: String,
+	// the row after the change, the row before it for a delete
+	pub payload: Value,
+	pub created_at: i64,
+}
+
+impl From<outbox::Model> for OutboxMessage {
+	fn from(row: outbox::Model) -> Self {
+		OutboxMessage {
+			id: row.id,
+			topic: row.topic,
+			key: row.key,
+			op: row.op,
+			payload: row.payload,
+			created_at: row.created_at,
+		}
+	}
+}
+
+pub enum OutboxSink {
+	// one json line per message
+	Stdout,
+	File(PathBuf),
+	// pg_notify on `OUTBOX_CHANNEL`
+	Postgres,
+	// a JetStream publish acked by the server, subjects are `omnity.outbox.<topic>`
+	Nats {
+		url: String,
+		// boxed, the client is much larger than the other variants
+		context: Box<OnceCell<jetstream::Context>>,
+	},
+}
+
+impl OutboxSink {
+	// `stdout`, `file:<path>`, `postgres` or a `nats://` url
+	pub fn parse(sink: &str) -> Result<Self> {
+		match sink {
+			"stdout" => Ok(OutboxSink::Stdout),
+			"postgres" => Ok(OutboxSink::Postgres),
+			_ => {
+				if let Some(path) = sink.strip_prefix("file:") {
+					Ok(OutboxSink::File(PathBuf::from(path)))
+				} else if sink.starts_with("nats://") {
+					Ok(OutboxSink::Nats {
+						url: sink.to_owned(),
+						context: Box::default(),
+					})
+				} else {
+					Err(anyhow!("unknown outbox sink {}", sink))
+				}
+			}
+		}
+	}
+
+	// postgres when `OUTBOX_SINK` is unset
+	pub fn from_env() -> Result<Self> {
+		match std::env::var("OUTBOX_SINK") {
+			Ok(sink) if !sink.is_empty() => Self::parse(&sink),
+			_ => Ok(OutboxSink::Postgres),
+		}
+	}
+
+	pub async fn send(&self, db: &DbConn, messages: &[OutboxMessage]) -> Result<()> {
+		match self {
+			OutboxSink::Stdout => {
+				let mut stdout = std::io::stdout().lock();
+				write_lines(&mut stdout, messages)?;
+				stdout.flush()?;
+			}
+			OutboxSink::File(path) => {
+				let mut file = std::fs::OpenOptions::new()
+					.create(true)
+					.append(true)
+					.open(path)?;
+				write_lines(&mut file, messages)?;
+				file.sync_data()?;
+			}
+			OutboxSink::Postgres => {
+				for message in messages {
+					db.execute(Statement::from_sql_and_values(
+						DbBackend::Postgres,
+						"SELECT pg_notify($1, $2)",
+						[OUTBOX_CHANNEL.into(), notify_payload(message)?.into()],
+					))
+					.await?;
+				}
+			}
+			OutboxSink::Nats { url, context } => {
+				let context = context
+					.get_or_try_init(|| async {
+						let context = jetstream::new(async_nats::connect(url.as_str()).await?);
+						context
+							.get_or_create_stream(jetstream::stream::Config {
+								name: NATS_STREAM.to_owned(),
+								subjects: vec![format!("{}.>", NATS_SUBJECT_PREFIX)],
+								..Default::default()
+							})
+							.await?;
+						info!("publishing the outbox to {}", url);
+						Ok::<_, anyhow::Error>(context)
+					})
+					.await?;
+				for message in messages {
+					let mut headers = async_nats::HeaderMap::new();
+					// the stream drops the ids it has already seen within its duplicate window
+					headers.insert("Nats-Msg-Id", message.id.to_string().as_str());
+					context
+						.publish_with_headers(
+							format!("{}.{}", NATS_SUBJECT_PREFIX, message.topic),
+							headers,
+							serde_json::to_vec(message)?.into(),
+						)
+						.await?
+						.await?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+fn write_lines(out: &mut impl Write, messages: &[OutboxMessage]) -> Result<()> {
+	for message in messages {
+		serde_json::to_writer(&mut *out, message)?;
+		out.write_all(b"\n")?;
+	}
+	Ok(())
+}
+
+// a message too large for a notification goes without its payload, the listeners read the
+// row from its topic and key
+fn notify_payload(message: &OutboxMessage) -> Result<String> {
+	let payload = serde_json::to_string(message)?;
+	if payload.len() <= NOTIFY_PAYLOAD_LIMIT {
+		return Ok(payload);
+	}
+	Ok(serde_json::to_string(&OutboxMessage {
+		payload: Value::Null,
+		..message.clone()
+	})?)
+}
+
+// sends the unpublished rows in id order until none is left, a failed batch is marked up to
+// the message the sink accepted and sent again from there on the next run
+pub async fn publish_outbox(db: &DbConn, sink: &OutboxSink) -> Result<(), Box<dyn Error>> {
+	loop {
+		let rows = Query::get_unpublished_outbox(db, OUTBOX_BATCH_SIZE).await?;
+		let count = rows.len() as u64;
+		let messages: Vec<OutboxMessage> = rows.into_iter().map(OutboxMessage::from).collect();
+		let (sent, result) = send_in_order(db, sink, &messages).await;
+		let ids: Vec<i64> = messages[..sent].iter().map(|message| message.id).collect();
+		let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+		Mutation::mark_outbox_published(db, ids, now).await?;
+		result?;
+		if count < OUTBOX_BATCH_SIZE {
+			break;
+		}
+	}
+	let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+	Delete::remove_published_outbox(db, now - OUTBOX_RETENTION).await?;
+	Ok(())
+}
+
+// the number of messages the sink accepted before the first failure
+async fn send_in_order(
+	db: &DbConn,
+	sink: &OutboxSink,
+	messages: &[OutboxMessage],
+) -> (usize, Result<()>) {
+	match sink {
+		// a stream sink takes the batch at once, a failed write is sent again as a whole
+		OutboxSink::Stdout | OutboxSink::File(_) => match sink.send(db, messages).await {
+			Ok(()) => (messages.len(), Ok(())),
+			Err(e) => (0, Err(e)),
+		},
+		_ => {
+			for (sent, message) in messages.iter().enumerate() {
+				if let Err(e) = sink.send(db, std::slice::from_ref(message)).await {
+					return (sent, Err(e));
+				}
+			}
+			(messages.len(), Ok(()))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::StreamExt;
+	use serde_json::json;
+
+	fn message(id: i64, key: &str) -> OutboxMessage {
+		OutboxMessage {
+			id,
+			topic: TICKET.table.to_owned(),
+			key: key.to_owned(),
+			op: OP_UPDATE.to_owned(),
+			payload: json!({ "ticket_id": key, "status": "Finalized" }),
+			created_at: id,
+		}
+	}
+
+	#[test]
+	fn sinks_are_parsed_from_their_names() {
+		assert!(matches!(
+			OutboxSink::parse("stdout"),
+			Ok(OutboxSink::Stdout)
+		));
+		assert!(matches!(
+			OutboxSink::parse("postgres"),
+			Ok(OutboxSink::Postgres)
+		));
+		assert!(matches!(
+			OutboxSink::parse("file:/tmp/outbox.jsonl"),
+			Ok(OutboxSink::File(path)) if path.to_str() == Some("/tmp/outbox.jsonl")
+		));
+		assert!(matches!(
+			OutboxSink::parse("nats://localhost:4222"),
+			Ok(OutboxSink::Nats { .. })
+		));
+		assert!(OutboxSink::parse("kafka://localhost:9092").is_err());
+	}
+
+	#[test]
+	fn large_notifications_drop_the_payload() {
+		let small = message(1, "t1");
+		assert_eq!(
+			serde_json::from_str::<OutboxMessage>(&notify_payload(&small).unwrap()).unwrap(),
+			small
+		);
+
+		let mut large = message(2, "t2");
+		large.payload = json!({ "memo": "x".repeat(NOTIFY_PAYLOAD_LIMIT) });
+		let notified: OutboxMessage =
+			serde_json::from_str(&notify_payload(&large).unwrap()).unwrap();
+		assert_eq!(notified.payload, Value::Null);
+		assert_eq!(notified.key, "t2");
+	}
+
+	#[tokio::test]
+	async fn files_get_one_line_per_message() {
+		let path = std::env::temp_dir().join(format!("outbox-{}.jsonl", std::process::id()));
+		let sink = OutboxSink::File(path.clone());
+		let db = DbConn::Disconnected;
+		sink.send(&db, &[message(1, "t1"), message(2, "t2")])
+			.await
+			.unwrap();
+		sink.send(&db, &[message(3, "t1")]).await.unwrap();
+
+		let lines: Vec<OutboxMessage> = std::fs::read_to_string(&path)
+			.unwrap()
+			.lines()
+			.map(|line| serde_json::from_str(line).unwrap())
+			.collect();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(
+			lines,
+			vec![message(1, "t1"), message(2, "t2"), message(3, "t1")]
+		);
+	}
+
+	// needs docker: cargo test -p omnity-indexer-sync -- --ignored nats
+	#[tokio::test]
+	#[ignore]
+	async fn nats_gets_the_messages_once_and_in_order() {
+		use testcontainers::core::{IntoContainerPort, WaitFor};
+		use testcontainers::runners::AsyncRunner;
+		use testcontainers::{GenericImage, ImageExt};
+
+		let container = GenericImage::new("nats", "2.10-alpine")
+			.with_exposed_port(4222.tcp())
+			.with_wait_for(WaitFor::message_on_stderr("Server is ready"))
+			.with_cmd(["-js"])
+			.start()
+			.await
+			.unwrap();
+		let port = container.get_host_port_ipv4(4222).await.unwrap();
+		let url = format!("nats://127.0.0.1:{}", port);
+		let sink = OutboxSink::parse(&url).unwrap();
+		let db = DbConn::Disconnected;
+
+		let messages = [message(1, "t1"), message(2, "t2"), message(3, "t1")];
+		assert_eq!(send_in_order(&db, &sink, &messages).await.0, 3);
+		// a redelivery after a crash before the rows were marked
+		assert_eq!(send_in_order(&db, &sink, &messages[1..]).await.0, 2);
+
+		let context = jetstream::new(async_nats::connect(url).await.unwrap());
+		let consumer = context
+			.get_stream(NATS_STREAM)
+			.await
+			.unwrap()
+			.create_consumer(jetstream::consumer::pull::Config::default())
+			.await
+			.unwrap();
+		let mut batch = consumer.fetch().max_messages(10).messages().await.unwrap();
+		let mut received = Vec::new();
+		while let Some(delivered) = batch.next().await {
+			let delivered = delivered.unwrap();
+			assert_eq!(delivered.subject.as_str(), "omnity.outbox.ticket");
+			received.push(serde_json::from_slice::<OutboxMessage>(&delivered.payload).unwrap());
+		}
+		assert_eq!(received, messages.to_vec());
+	}
+}
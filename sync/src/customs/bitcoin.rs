@@ -1,18 +1,64 @@
-use crate::entity::ticket;
-use crate::graphql::terms_amount::query_terms_amount;
-use crate::service::{Delete, Mutation, Query};
-use crate::{types::TicketId, with_omnity_canister, Arg, ChainId};
+use crate::customs::esplora::{
+	confirmation_count, esplora_url, min_confirmations, not_found_timeout, Esplora, TX_NOT_FOUND,
+};
+use crate::customs::mint_reconciliation::reconcile_mint_ticket;
+use crate::entity::{rune_terms, ticket};
+use crate::graphql::terms_amount::{query_terms_amount, runes_graphql_url};
+use crate::service::{Mutation, Query};
+use crate::{
+	is_canister_configured, token_ledger_id_on_chain, types::TicketId, with_omnity_canister, Arg,
+	ChainId,
+};
 // use log::info;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use log::warn;
 use sea_orm::DbConn;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 
+pub const BITCOIN_CHAIN_ID: &str = "Bitcoin";
+pub const BRC20_CHAIN_ID: &str = "Bitcoinbrc20";
+pub const BRC20_CANISTER: &str = "OMNITY_CUSTOMS_BITCOIN_BRC20_CANISTER_ID";
+pub const BRC20_TOKEN_PREFIX: &str = "Bitcoinbrc20-brc20-";
+
 #[derive(candid::CandidType, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 struct BtcCustom {
 	pub canister: &'static str,
 	pub chain: ChainId,
 }
 
+pub struct BtcCustoms {
+	customs: Vec<BtcCustom>,
+}
+
+impl BtcCustoms {
+	pub fn new() -> Self {
+		let mut customs = vec![BtcCustom {
+			canister: "OMNITY_CUSTOMS_BITCOIN_CANISTER_ID",
+			chain: BITCOIN_CHAIN_ID.to_owned(),
+		}];
+		// the brc20 custom is optional and only tracked once its canister id is configured
+		if is_canister_configured(BRC20_CANISTER) {
+			customs.push(BtcCustom {
+				canister: BRC20_CANISTER,
+				chain: BRC20_CHAIN_ID.to_owned(),
+			});
+		}
+		Self { customs }
+	}
+}
+
+// brc20 source transactions may be referenced by inscription id (`<txid>i<index>`)
+// or outpoint (`<txid>:<vout>`), the sender lookup needs the bare txid
+pub fn brc20_source_txid(ticket_id: &str) -> &str {
+	let txid = ticket_id.split(':').next().unwrap_or(ticket_id);
+	match txid.char_indices().nth(64) {
+		Some((index, 'i')) => &txid[..index],
+		_ => txid,
+	}
+}
+
 #[derive(candid::CandidType, Clone, Debug, PartialEq, Eq, Deserialize)]
 pub enum ReleaseTokenStatus {
 	Unknown,
@@ -24,19 +70,27 @@ pub enum ReleaseTokenStatus {
 	Failed(Option<TicketId>),
 }
 
+impl ReleaseTokenStatus {
+	// the stage name kept in `ticket.sub_status`
+	pub fn sub_status(&self) -> Option<&'static str> {
+		match self {
+			Self::Unknown => None,
+			Self::Pending => Some("Pending"),
+			Self::Signing => Some("Signing"),
+			Self::Sending(_) => Some("Sending"),
+			Self::Submitted(_) => Some("Submitted"),
+			Self::Confirmed(_) => Some("Confirmed"),
+			Self::Failed(_) => Some("Failed"),
+		}
+	}
+}
+
 pub async fn sync_all_ticket_status_from_bitcoin(db: &DbConn) -> Result<(), Box<dyn Error>> {
-	let btc_customs: Vec<BtcCustom> = vec![
-		BtcCustom {
-			canister: "OMNITY_CUSTOMS_BITCOIN_CANISTER_ID",
-			chain: "Bitcoin".to_owned(),
-		},
-		// BtcCustom {
-		// 	canister: "OMNITY_CUSTOMS_BITCOIN_BRC20_CANISTER_ID",
-		// 	chain: "Bitcoinbrc20".to_owned(),
-		// },
-	];
+	let btc_customs = BtcCustoms::new();
+	// without the tracker a submitted release is final as soon as the custom broadcast it
+	let tracking_confirmations = esplora_url().is_some();
 
-	for btc_custom in btc_customs.iter() {
+	for btc_custom in btc_customs.customs.iter() {
 		with_omnity_canister(btc_custom.canister, |agent, canister_id| async move {
 			// info!("BTC状态更新在工作 ... ");
 			let unconfirmed_tickets =
@@ -55,31 +109,44 @@ pub async fn sync_all_ticket_status_from_bitcoin(db: &DbConn) -> Result<(), Box<
 					.await?
 					.convert_to_release_token_status();
 
+				let mut ticket = unconfirmed_ticket.clone();
+				let mut tx_changed = false;
 				if let ReleaseTokenStatus::Submitted(tx_hash)
-				| ReleaseTokenStatus::Confirmed(tx_hash) = mint_token_status
+				| ReleaseTokenStatus::Confirmed(tx_hash) = mint_token_status.clone()
 				{
-					let _ticket_model = Mutation::update_ticket(
-						db,
-						unconfirmed_ticket.clone(),
-						Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
-						Some(Some(tx_hash)),
-						None,
-						None,
-						None,
-						None,
-					)
-					.await?;
+					if !tracking_confirmations {
+						ticket = Mutation::update_ticket(
+							db,
+							unconfirmed_ticket.clone(),
+							Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
+							Some(Some(tx_hash)),
+							None,
+							None,
+							None,
+							None,
+						)
+						.await?;
+					} else if unconfirmed_ticket.tx_hash.as_ref() != Some(&tx_hash) {
+						// broadcast, `track_bitcoin_confirmations` finalizes it once deep enough
+						tx_changed = true;
+						ticket = Mutation::update_ticket_tx_hash(
+							db,
+							unconfirmed_ticket.clone(),
+							Some(tx_hash),
+						)
+						.await?;
+					}
 
 					// info!(
-					// 	"btc ticket id({:?}) finally status:{:?} and its hash is {:?} ",
-					// 	ticket_model.ticket_id, ticket_model.status, ticket_model.tx_hash
+					// 	"btc ticket id({:?}) status:{:?} and its hash is {:?} ",
+					// 	ticket.ticket_id, ticket.status, ticket.tx_hash
 					// );
-				} else if let ReleaseTokenStatus::Failed(tx_id) = mint_token_status {
+				} else if let ReleaseTokenStatus::Failed(tx_id) = mint_token_status.clone() {
 					let update_hash = match tx_id {
 						None => "None".to_string(),
 						Some(tx) => tx,
 					};
-					let _ = Mutation::update_ticket(
+					ticket = Mutation::update_ticket(
 						db,
 						unconfirmed_ticket.clone(),
 						Some(crate::entity::sea_orm_active_enums::TicketStatus::Failed),
@@ -96,6 +163,12 @@ pub async fn sync_all_ticket_status_from_bitcoin(db: &DbConn) -> Result<(), Box<
 					// 	unconfirmed_ticket.ticket_id, mint_token_status
 					// );
 				}
+
+				// the custom still reports the tx the tracker found unknown to the network
+				let tracked = ticket.sub_status.as_deref() == Some(TX_NOT_FOUND) && !tx_changed;
+				if let Some(sub_status) = mint_token_status.sub_status().filter(|_| !tracked) {
+					Mutation::update_ticket_sub_status(db, ticket, sub_status).await?;
+				}
 			}
 			Ok(())
 		})
@@ -104,170 +177,84 @@ pub async fn sync_all_ticket_status_from_bitcoin(db: &DbConn) -> Result<(), Box<
 	Ok(())
 }
 
-// update mint tickets meta
-pub async fn update_mint_tickets(db: &DbConn) -> Result<(), Box<dyn Error>> {
-	// Find all the mint tickets
-	let non_updated_mint_tickets = Query::get_non_updated_mint_tickets(db).await?;
+// tracks the confirmations of submitted release txs and finalizes them after enough blocks,
+// when BITCOIN_ESPLORA_URL is set
+pub async fn track_bitcoin_confirmations(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	let esplora = match Esplora::from_env()? {
+		Some(esplora) => esplora,
+		None => return Ok(()),
+	};
+	let min_confirmations = min_confirmations();
+	let not_found_timeout = not_found_timeout();
+	let btc_customs = BtcCustoms::new();
 
-	for ticket in non_updated_mint_tickets {
-		if let Some(token_id) = ticket.token.as_str().strip_prefix("Bitcoin-runes-") {
-			// Fetch the amount from the runescan graphql api
-			let amount = query_terms_amount(token_id).await.unwrap();
-			// Insert the amount into the ticket meta
-			let _updated_ticket = Mutation::update_ticket(
-				db,
-				ticket,
-				None,
-				None,
-				Some(amount.to_string()),
-				None,
-				None,
-				None,
-			)
-			.await?;
-			// info!(
-			// 	"Ticket id({:?}) has changed its amount to {:?}",
-			// 	updated_ticket.ticket_id, updated_ticket.amount
-			// );
+	for btc_custom in btc_customs.customs.iter() {
+		let submitted_tickets = Query::get_submitted_tickets(db, btc_custom.chain.clone()).await?;
+		if submitted_tickets.is_empty() {
+			continue;
 		}
-	}
-	Ok(())
-}
-
-async fn process_deleted_mint_tickets(
-	db: &DbConn,
-	tx_hash: String,
-	mint_ticket: ticket::Model,
-) -> Result<(), Box<dyn Error>> {
-	let existing_ticket = Query::get_ticket_by_id(db, tx_hash.clone()).await?;
-	let removed_ticket = Query::get_deleted_ticket_by_id(db, tx_hash.clone()).await?;
+		let tip_height = esplora.tip_height().await?;
 
-	if let (Some(ticket_should_be_removed), None) = (&existing_ticket, &removed_ticket) {
-		if let Ok(_) =
-			Mutation::save_deleted_mint_ticket(db, ticket_should_be_removed.clone().into()).await
-		{
-			if let Ok(_row) =
-				Delete::remove_ticket_by_id(db, ticket_should_be_removed.clone().ticket_id).await
-			{
-				match ticket_should_be_removed.clone().tx_hash {
-					Some(tx_hash) => {
-						// fetch the tx_hash from the mint ticket and put it in
-						// intermediate_tx_hash
-						let intermediate_tx_hash = match (
-							mint_ticket.clone().tx_hash,
-							mint_ticket.clone().intermediate_tx_hash,
-						) {
-							(Some(hash), None) => Some(hash),
-							(None, Some(hash)) => Some(hash),
-							_ => None,
-						};
-						if let Ok(_) = Mutation::update_ticket(
-							db,
-							mint_ticket.clone(),
-							Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
-							Some(Some(tx_hash.clone())),
-							None,
-							None,
-							Some(intermediate_tx_hash),
-							None,
-						)
-						.await
-						{
-							// info!(
-							// 	"Ticket id({:?}) has been removed and {:?} row has been deleted",
-							// 	ticket_should_be_removed.clone().ticket_id,
-							// 	row
-							// );
-						}
-					}
-					None => {
-						let intermediate_tx_hash = match (
-							mint_ticket.clone().tx_hash,
-							mint_ticket.clone().intermediate_tx_hash,
-						) {
-							(Some(hash), None) => Some(hash),
-							(None, Some(hash)) => Some(hash),
-							_ => None,
-						};
+		for ticket in submitted_tickets {
+			let txid = ticket.tx_hash.clone().unwrap_or_default();
+			let block_height = match esplora.tx_status(&txid).await {
+				Ok(Some(status)) => status.block_height,
+				Ok(None) => {
+					let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+					let ticket =
+						Mutation::update_ticket_sub_status(db, ticket, TX_NOT_FOUND).await?;
+					// dropped or never broadcast, the tx is given up on past the timeout
+					if now - ticket.ticket_time > not_found_timeout * 1_000_000_000 {
 						Mutation::update_ticket(
 							db,
-							mint_ticket.clone(),
-							Some(crate::entity::sea_orm_active_enums::TicketStatus::Unknown),
+							ticket,
+							Some(crate::entity::sea_orm_active_enums::TicketStatus::Failed),
+							None,
 							None,
 							None,
 							None,
-							Some(intermediate_tx_hash),
 							None,
 						)
 						.await?;
-						Mutation::update_ticket_tx_hash(db, mint_ticket.clone(), None).await?;
-						// info!(
-						// 	"Ticket id({:?}) is waiting to be finalized",
-						// 	mint_ticket.clone().tx_hash
-						// );
 					}
+					continue;
 				}
-			}
-		}
-	} else if let (None, None) = (&existing_ticket, &removed_ticket) {
-		//update mint tickets status if there is no corresponding transfer tickets.
-		let intermediate_tx_hash = match (
-			mint_ticket.clone().tx_hash,
-			mint_ticket.clone().intermediate_tx_hash,
-		) {
-			(Some(hash), None) => Some(hash),
-			(None, Some(hash)) => Some(hash),
-			_ => None,
-		};
-		Mutation::update_ticket(
-			db,
-			mint_ticket.clone(),
-			Some(crate::entity::sea_orm_active_enums::TicketStatus::Unknown),
-			None,
-			None,
-			None,
-			Some(intermediate_tx_hash),
-			None,
-		)
-		.await?;
-		Mutation::update_ticket_tx_hash(db, mint_ticket.clone(), None).await?;
-	} else if let (None, Some(_removed_ticket)) = (&existing_ticket, &removed_ticket) {
-		match &_removed_ticket.tx_hash {
-			Some(tx_hash) => {
-				Mutation::update_ticket(
+				Err(e) => {
+					warn!(
+						"esplora tx status error: ticket_id={}, txid={}, error={}",
+						ticket.ticket_id, txid, e
+					);
+					continue;
+				}
+			};
+			let confirmations = confirmation_count(tip_height, block_height);
+			// the tx showed up after all, the stage of the custom applies again
+			let ticket = if ticket.sub_status.as_deref() == Some(TX_NOT_FOUND) {
+				Mutation::update_ticket_sub_status(db, ticket, "Submitted").await?
+			} else {
+				ticket
+			};
+
+			let ticket = if ticket.confirmations != Some(confirmations as i32)
+				|| ticket.block_height != block_height.map(|height| height as i64)
+			{
+				Mutation::update_ticket_confirmations(
 					db,
-					mint_ticket.clone(),
-					Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
-					Some(Some(tx_hash.to_owned())),
-					None,
-					None,
-					None,
-					None,
+					ticket,
+					confirmations as i32,
+					block_height.map(|height| height as i64),
 				)
-				.await?;
-			}
-			None => {
-				// info!(
-				// 	"Ticket id({:?}) is waiting to be finalized",
-				// 	mint_ticket.clone().tx_hash
-				// );
-			}
-		}
-	} else if let (Some(ticket_should_be_removed), Some(_removed_ticket)) =
-		(&existing_ticket, &removed_ticket)
-	{
-		if let Ok(_row) =
-			Delete::remove_ticket_by_id(db, ticket_should_be_removed.clone().ticket_id).await
-		{
-			// info!("{:?} row has been deleted", row);
-		}
-		match &_removed_ticket.tx_hash {
-			Some(tx_hash) => {
+				.await?
+			} else {
+				ticket
+			};
+
+			if confirmations >= min_confirmations {
 				Mutation::update_ticket(
 					db,
-					mint_ticket.clone(),
+					ticket,
 					Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
-					Some(Some(tx_hash.to_owned())),
+					None,
 					None,
 					None,
 					None,
@@ -275,12 +262,88 @@ async fn process_deleted_mint_tickets(
 				)
 				.await?;
 			}
-			None => {
-				// info!(
-				// 	"Ticket id({:?}) is waiting to be finalized",
-				// 	mint_ticket.clone().tx_hash
-				// );
-			}
+		}
+	}
+	Ok(())
+}
+
+// brc20 tokens are keyed by their ticker on the brc20 custom
+pub async fn sync_all_brc20_token_ledger_id_on_chain(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	if !is_canister_configured(BRC20_CANISTER) {
+		return Ok(());
+	}
+
+	for token in Query::get_all_tokens(db).await? {
+		if let Some(ticker) = token.token_id.strip_prefix(BRC20_TOKEN_PREFIX) {
+			let token_ledger_id_on_chain_model = token_ledger_id_on_chain::Model::new(
+				BRC20_CHAIN_ID.to_owned(),
+				token.token_id.clone(),
+				ticker.to_owned(),
+			);
+			Mutation::save_all_token_ledger_id_on_chain(db, token_ledger_id_on_chain_model).await?;
+		}
+	}
+	Ok(())
+}
+
+// update mint tickets meta
+pub async fn update_mint_tickets(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	// Find all the mint tickets
+	let non_updated_mint_tickets = Query::get_non_updated_mint_tickets(db).await?;
+	if non_updated_mint_tickets.is_empty() {
+		return Ok(());
+	}
+
+	let client = reqwest::Client::builder()
+		.timeout(std::time::Duration::new(30, 0))
+		.build()?;
+	let url = runes_graphql_url();
+	for ticket in non_updated_mint_tickets {
+		if let Some(rune_id) = ticket.token.as_str().strip_prefix("Bitcoin-runes-") {
+			let terms_amount = match Query::get_rune_terms(db, rune_id.to_owned()).await? {
+				Some(rune_terms) => rune_terms.terms_amount,
+				// Fetch the amount from the runes graphql api
+				None => match query_terms_amount(&client, &url, rune_id).await {
+					Ok(Some(terms_amount)) => {
+						Mutation::save_rune_terms(
+							db,
+							rune_terms::Model::new(rune_id.to_owned(), terms_amount),
+						)
+						.await?;
+						terms_amount
+					}
+					Ok(None) => {
+						warn!(
+							"rune has no mint terms: ticket_id={}, rune_id={}",
+							ticket.ticket_id, rune_id
+						);
+						continue;
+					}
+					Err(e) => {
+						warn!(
+							"runes terms query error: ticket_id={}, rune_id={}, error={}",
+							ticket.ticket_id, rune_id, e
+						);
+						continue;
+					}
+				},
+			};
+			// Insert the amount into the ticket meta
+			let _updated_ticket = Mutation::update_ticket(
+				db,
+				ticket,
+				None,
+				None,
+				Some(BigDecimal::from(terms_amount)),
+				None,
+				None,
+				None,
+			)
+			.await?;
+			// info!(
+			// 	"Ticket id({:?}) has changed its amount to {:?}",
+			// 	updated_ticket.ticket_id, updated_ticket.amount
+			// );
 		}
 	}
 	Ok(())
@@ -291,17 +354,21 @@ pub async fn update_deleted_mint_tickets(db: &DbConn) -> Result<(), Box<dyn Erro
 	let updated_mint_tickets = Query::get_updated_mint_tickets(db).await?;
 
 	for mint_ticket in updated_mint_tickets {
-		if let (Some(tx_hash), None) = (
-			mint_ticket.clone().tx_hash,
-			mint_ticket.clone().intermediate_tx_hash,
-		) {
-			process_deleted_mint_tickets(db, tx_hash, mint_ticket.clone()).await?;
-		} else if let (None, Some(tx_hash)) = (
-			mint_ticket.clone().tx_hash,
-			mint_ticket.clone().intermediate_tx_hash,
-		) {
-			process_deleted_mint_tickets(db, tx_hash, mint_ticket.clone()).await?;
-		}
+		reconcile_mint_ticket(db, mint_ticket).await?;
 	}
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn brc20_source_txid_strips_inscription_and_outpoint_suffixes() {
+		let txid = "6fb976ab49dcec017f1e201e84395983204ae1a7c2abf7ced0a85d692e442799";
+
+		assert_eq!(brc20_source_txid(txid), txid);
+		assert_eq!(brc20_source_txid(&format!("{}i0", txid)), txid);
+		assert_eq!(brc20_source_txid(&format!("{}:1", txid)), txid);
+	}
+}
@@ -17,6 +17,20 @@ pub enum DogecoinReleaseTokenStatus {
 	Confirmed(String),
 }
 
+impl DogecoinReleaseTokenStatus {
+	// the stage name kept in `ticket.sub_status`
+	pub fn sub_status(&self) -> Option<&'static str> {
+		match self {
+			Self::Unknown => None,
+			Self::Pending => Some("Pending"),
+			Self::Signing => Some("Signing"),
+			Self::Sending(_) => Some("Sending"),
+			Self::Submitted(_) => Some("Submitted"),
+			Self::Confirmed(_) => Some("Confirmed"),
+		}
+	}
+}
+
 pub async fn sync_ticket_status_from_doge(db: &DbConn) -> Result<(), Box<dyn Error>> {
 	with_omnity_canister(
 		"OMNITY_CUSTOMS_DOGECOIN_CANISTER_ID",
@@ -39,23 +53,41 @@ pub async fn sync_ticket_status_from_doge(db: &DbConn) -> Result<(), Box<dyn Err
 					.await?
 					.convert_to_release_dogecoin_token_status();
 
-				if let DogecoinReleaseTokenStatus::Confirmed(tx_hash) = release_doge_token_status {
-					let _ticket_model = Mutation::update_ticket(
-						db,
-						unconfirmed_ticket.clone(),
-						Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
-						Some(Some(tx_hash)),
-						None,
-						None,
-						None,
-						None,
-					)
-					.await?;
+				let ticket = match release_doge_token_status.clone() {
+					DogecoinReleaseTokenStatus::Confirmed(tx_hash) => {
+						Mutation::update_ticket(
+							db,
+							unconfirmed_ticket.clone(),
+							Some(crate::entity::sea_orm_active_enums::TicketStatus::Finalized),
+							Some(Some(tx_hash)),
+							None,
+							None,
+							None,
+							None,
+						)
+						.await?
+					}
+					// broadcast but not yet confirmed, keep the hash without finalizing
+					DogecoinReleaseTokenStatus::Submitted(tx_hash)
+						if unconfirmed_ticket.tx_hash.as_ref() != Some(&tx_hash) =>
+					{
+						Mutation::update_ticket_tx_hash(
+							db,
+							unconfirmed_ticket.clone(),
+							Some(tx_hash),
+						)
+						.await?
+					}
+					_ => unconfirmed_ticket.clone(),
+				};
+
+				// info!(
+				// 	"dogecoin custom ticket id({:?}) status:{:?} and its hash is {:?} ",
+				// 	ticket.ticket_id, ticket.status, ticket.tx_hash
+				// );
 
-					// info!(
-					// 	"dogecoin custom ticket id({:?}) finally status:{:?} and its hash is {:?} ",
-					// 	ticket_model.ticket_id, ticket_model.status, ticket_model.tx_hash
-					// );
+				if let Some(sub_status) = release_doge_token_status.sub_status() {
+					Mutation::update_ticket_sub_status(db, ticket, sub_status).await?;
 				}
 			}
 
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+// only the sender lookups fall back to it, the confirmation tracker is opt-in
+pub const DEFAULT_ESPLORA_URL: &str = "https://mempool.space/api";
+pub const BTC_MIN_CONFIRMATIONS: u64 = 6;
+// seconds after its ticket time a release tx unknown to the api fails its ticket
+pub const BTC_NOT_FOUND_TIMEOUT: i64 = 24 * 3600;
+pub const TX_NOT_FOUND: &str = "TxNotFound";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct TxStatus {
+	pub confirmed: bool,
+	pub block_height: Option<u64>,
+}
+
+pub fn esplora_url() -> Option<String> {
+	std::env::var("BITCOIN_ESPLORA_URL")
+		.ok()
+		.filter(|url| !url.is_empty())
+}
+
+pub fn min_confirmations() -> u64 {
+	std::env::var("BTC_MIN_CONFIRMATIONS")
+		.ok()
+		.and_then(|confirmations| confirmations.parse().ok())
+		.unwrap_or(BTC_MIN_CONFIRMATIONS)
+}
+
+pub fn not_found_timeout() -> i64 {
+	std::env::var("BTC_NOT_FOUND_TIMEOUT")
+		.ok()
+		.and_then(|timeout| timeout.parse().ok())
+		.unwrap_or(BTC_NOT_FOUND_TIMEOUT)
+}
+
+// a tx mined in the tip block has one confirmation, a mempool tx has none
+pub fn confirmation_count(tip_height: u64, block_height: Option<u64>) -> u64 {
+	match block_height {
+		Some(height) if height <= tip_height => tip_height - height + 1,
+		_ => 0,
+	}
+}
+
+pub struct Esplora {
+	client: Client,
+	url: String,
+}
+
+impl Esplora {
+	pub fn new(url: String) -> Result<Self> {
+		let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+		Ok(Self::with_client(client, url))
+	}
+
+	pub fn with_client(client: Client, url: String) -> Self {
+		Self {
+			client,
+			url: url.trim_end_matches('/').to_owned(),
+		}
+	}
+
+	// `None` unless BITCOIN_ESPLORA_URL is set
+	pub fn from_env() -> Result<Option<Self>> {
+		esplora_url().map(Self::new).transpose()
+	}
+
+	pub async fn tip_height(&self) -> Result<u64> {
+		let response = self
+			.client
+			.get(format!("{}/blocks/tip/height", self.url))
+			.send()
+			.await?
+			.error_for_status()?
+			.text()
+			.await?;
+		response
+			.trim()
+			.parse()
+			.map_err(|_| anyhow!("invalid tip height: {}", response))
+	}
+
+	// `None` when the api does not know the txid
+	pub async fn tx_status(&self, txid: &str) -> Result<Option<TxStatus>> {
+		let response = self
+			.client
+			.get(format!("{}/tx/{}/status", self.url, txid))
+			.send()
+			.await?;
+		if matches!(
+			response.status(),
+			StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND
+		) {
+			return Ok(None);
+		}
+		Ok(Some(response.error_for_status()?.json().await?))
+	}
+
+	// the full tx, `None` when the api does not know the txid
+	pub async fn tx(&self, txid: &str) -> Result<Option<Value>> {
+		let response = self
+			.client
+			.get(format!("{}/tx/{}", self.url, txid))
+			.send()
+			.await?;
+		if matches!(
+			response.status(),
+			StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND
+		) {
+			return Ok(None);
+		}
+		Ok(Some(response.error_for_status()?.json().await?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::stub_server;
+
+	const TXID: &str = "4b6ad3b1d4b1a2dc5e6c4f2f3e6c0a0e8c1b5a7e9f0d2c4b6a8e0f1d3c5b7a9e";
+
+	#[test]
+	fn counts_confirmations_from_the_tip() {
+		assert_eq!(confirmation_count(840_005, Some(840_000)), 6);
+		assert_eq!(confirmation_count(840_000, Some(840_000)), 1);
+		assert_eq!(confirmation_count(840_000, None), 0);
+		assert_eq!(confirmation_count(839_999, Some(840_000)), 0);
+	}
+
+	#[tokio::test]
+	async fn reads_tip_height_and_tx_status() {
+		let url = stub_server::spawn(|request| match request.path.as_str() {
+			"/api/blocks/tip/height" => (200, "840005".to_owned()),
+			path if path == format!("/api/tx/{}/status", TXID) => (
+				200,
+				r#"{"confirmed":true,"block_height":840000,"block_hash":"00"}"#.to_owned(),
+			),
+			_ => (404, "Transaction not found".to_owned()),
+		})
+		.await;
+		let esplora = Esplora::new(format!("{}/api/", url)).unwrap();
+
+		assert_eq!(esplora.tip_height().await.unwrap(), 840_005);
+		assert_eq!(
+			esplora.tx_status(TXID).await.unwrap(),
+			Some(TxStatus {
+				confirmed: true,
+				block_height: Some(840_000),
+			})
+		);
+		assert_eq!(esplora.tx_status("unknown").await.unwrap(), None);
+	}
+}
@@ -2,6 +2,9 @@ use anyhow::*;
 use graphql_client::{GraphQLQuery, Response};
 use reqwest::Client;
 
+pub const DEFAULT_RUNES_GRAPHQL_URL: &str =
+	"https://runescan-hasura-mainnet-219952077564.us-central1.run.app/v1/graphql";
+
 #[allow(non_camel_case_types)]
 type numeric = i64;
 
@@ -13,29 +16,86 @@ type numeric = i64;
 )]
 pub struct AmountQuery;
 
-pub async fn query_terms_amount(variables: &str) -> Result<i64, anyhow::Error> {
+pub fn runes_graphql_url() -> String {
+	std::env::var("RUNES_GRAPHQL_URL")
+		.ok()
+		.filter(|url| !url.is_empty())
+		.unwrap_or_else(|| DEFAULT_RUNES_GRAPHQL_URL.to_owned())
+}
+
+// `Ok(None)` when the rune is unknown or was etched without mint terms
+pub async fn query_terms_amount(
+	client: &Client,
+	url: &str,
+	rune_id: &str,
+) -> Result<Option<i64>, anyhow::Error> {
 	let variables: amount_query::Variables = amount_query::Variables {
-		token_id: variables.to_string(),
+		token_id: rune_id.to_string(),
 	};
 	let request_body = AmountQuery::build_query(variables);
 
-	let client = Client::new();
-
 	let response = client
-		.post("https://runescan-hasura-mainnet-219952077564.us-central1.run.app/v1/graphql")
+		.post(url)
 		.json(&request_body)
 		.send()
-		.await?;
+		.await?
+		.error_for_status()?;
 
 	let response_body: Response<amount_query::ResponseData> = response.json().await?;
 
-	if let Some(data) = response_body.data {
-		if let Some(runes) = data.runes.first() {
-			if let Some(terms_amount) = runes.terms_amount {
-				return Ok(terms_amount);
-			}
-		}
+	if let Some(errors) = response_body.errors.filter(|errors| !errors.is_empty()) {
+		return Err(anyhow!("runes graphql errors: {:?}", errors));
 	}
+	let data = response_body
+		.data
+		.ok_or_else(|| anyhow!("Missing response data"))?;
 
-	Err(anyhow!("Failed to get terms amount from response"))
+	Ok(data.runes.first().and_then(|runes| runes.terms_amount))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::stub_server;
+	use serde_json::json;
+
+	#[tokio::test]
+	async fn queries_terms_amount_from_a_graphql_endpoint() {
+		let url = stub_server::spawn(|request| {
+			let query = request.json();
+			let body = match query["variables"]["token_id"].as_str() {
+				Some("HOPE•YOU•GET•RICH") => {
+					json!({ "data": { "runes": [{ "terms_amount": 1000 }] } })
+				}
+				Some("UNCOMMON•GOODS") => {
+					json!({ "data": { "runes": [{ "terms_amount": null }] } })
+				}
+				Some("BROKEN") => json!({ "errors": [{ "message": "field not found" }] }),
+				_ => json!({ "data": { "runes": [] } }),
+			};
+			(200, body.to_string())
+		})
+		.await;
+		let client = Client::new();
+
+		assert_eq!(
+			query_terms_amount(&client, &url, "HOPE•YOU•GET•RICH")
+				.await
+				.unwrap(),
+			Some(1000)
+		);
+		assert_eq!(
+			query_terms_amount(&client, &url, "UNCOMMON•GOODS")
+				.await
+				.unwrap(),
+			None
+		);
+		assert_eq!(
+			query_terms_amount(&client, &url, "NOT•ETCHED")
+				.await
+				.unwrap(),
+			None
+		);
+		assert!(query_terms_amount(&client, &url, "BROKEN").await.is_err());
+	}
 }
@@ -0,0 +1,563 @@
+//! Reconciles a btc mint ticket with the transfer ticket its hash links to.
+//!
+//! A mint ticket first carries the id of the transfer ticket that delivered its runes in
+//! either `tx_hash` or `intermediate_tx_hash`, that id is the linked hash `h`. The
+//! transfer ticket is then folded into the mint ticket: it is archived in
+//! `deleted_mint_ticket`, dropped from `ticket`, and its release tx becomes the
+//! `tx_hash` of the mint ticket while `h` is kept in `intermediate_tx_hash`.
+//!
+//! | `ticket` h | `deleted_mint_ticket` h | rows written               | mint ticket      |
+//! |------------|-------------------------|----------------------------|------------------|
+//! | none       | none                    | -                          | Unknown, None, h |
+//! | tx t       | none                    | archive and drop h         | Finalized, t, h  |
+//! | no tx      | none                    | archive and drop h         | Unknown, None, h |
+//! | none       | tx t                    | -                          | Finalized, t, h  |
+//! | none       | no tx                   | -                          | untouched        |
+//! | any        | tx t                    | drop h                     | Finalized, t, h  |
+//! | tx t       | no tx                   | write t to archive, drop h | Finalized, t, h  |
+//! | no tx      | no tx                   | drop h                     | untouched        |
+//!
+//! A mint ticket with both or neither hashes set is not reconciled. Once finalized it
+//! carries both and leaves the state machine, while the waiting state `Unknown, None, h`
+//! and a transfer archived without its release tx plan no further writes, so replaying a
+//! plan always ends in the same rows. The plan is made from the rows as they are in the
+//! transaction that writes it, with the mint ticket locked.
+//! Writing the mint ticket also records its `Mint` edge to h in `ticket_link`.
+
+use crate::entity::sea_orm_active_enums::TicketStatus;
+use crate::entity::{deleted_mint_ticket, ticket};
+use crate::service::{Delete, Mutation, Query};
+use sea_orm::{DbConn, DbErr, TransactionTrait};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintTicketState {
+	pub status: TicketStatus,
+	pub tx_hash: Option<String>,
+	pub intermediate_tx_hash: Option<String>,
+}
+
+impl MintTicketState {
+	fn of(ticket: &ticket::Model) -> Self {
+		Self {
+			status: ticket.status.clone(),
+			tx_hash: ticket.tx_hash.clone(),
+			intermediate_tx_hash: ticket.intermediate_tx_hash.clone(),
+		}
+	}
+}
+
+// the writes of one reconciliation, applied in field order within one transaction
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MintReconciliation {
+	pub archive: Option<deleted_mint_ticket::Model>,
+	// the archived row of h with the release tx of its ticket
+	pub release_archive: Option<deleted_mint_ticket::Model>,
+	pub remove: Option<String>,
+	pub mint: Option<MintTicketState>,
+}
+
+impl MintReconciliation {
+	pub fn is_empty(&self) -> bool {
+		self.archive.is_none()
+			&& self.release_archive.is_none()
+			&& self.remove.is_none()
+			&& self.mint.is_none()
+	}
+}
+
+// the id of the transfer ticket the mint ticket links to
+pub fn linked_hash(mint_ticket: &ticket::Model) -> Option<&str> {
+	match (&mint_ticket.tx_hash, &mint_ticket.intermediate_tx_hash) {
+		(Some(hash), None) | (None, Some(hash)) => Some(hash),
+		_ => None,
+	}
+}
+
+pub fn plan_mint_reconciliation(
+	mint_ticket: &ticket::Model,
+	existing_ticket: Option<&ticket::Model>,
+	removed_ticket: Option<&deleted_mint_ticket::Model>,
+) -> MintReconciliation {
+	let linked_hash = match linked_hash(mint_ticket) {
+		Some(hash) => hash.to_owned(),
+		None => return MintReconciliation::default(),
+	};
+	// a mint ticket never folds itself
+	let existing_ticket =
+		existing_ticket.filter(|existing| existing.ticket_id != mint_ticket.ticket_id);
+
+	let archive = match (existing_ticket, removed_ticket) {
+		(Some(existing), None) => Some(deleted_mint_ticket::Model::from(existing.clone())),
+		_ => None,
+	};
+	let release_archive = match (existing_ticket, removed_ticket) {
+		(Some(existing), Some(removed))
+			if removed.tx_hash.is_none() && existing.tx_hash.is_some() =>
+		{
+			Some(deleted_mint_ticket::Model {
+				tx_hash: existing.tx_hash.clone(),
+				status: existing.status.clone(),
+				..removed.clone()
+			})
+		}
+		_ => None,
+	};
+	let remove = existing_ticket.map(|existing| existing.ticket_id.clone());
+	let release_tx_hash = removed_ticket
+		.and_then(|removed| removed.tx_hash.clone())
+		.or_else(|| existing_ticket.and_then(|existing| existing.tx_hash.clone()));
+
+	let target = match release_tx_hash {
+		Some(tx_hash) => Some(MintTicketState {
+			status: TicketStatus::Finalized,
+			tx_hash: Some(tx_hash),
+			intermediate_tx_hash: Some(linked_hash),
+		}),
+		// the transfer was archived before its release, the mint ticket waits as it is
+		None if removed_ticket.is_some() => None,
+		None => Some(MintTicketState {
+			status: TicketStatus::Unknown,
+			tx_hash: None,
+			intermediate_tx_hash: Some(linked_hash),
+		}),
+	};
+	let mint = target.filter(|target| MintTicketState::of(mint_ticket) != *target);
+
+	MintReconciliation {
+		archive,
+		release_archive,
+		remove,
+		mint,
+	}
+}
+
+pub async fn reconcile_mint_ticket(db: &DbConn, mint_ticket: ticket::Model) -> Result<(), DbErr> {
+	let txn = db.begin().await?;
+	let mint_ticket = match Query::get_ticket_by_id_for_update(&txn, mint_ticket.ticket_id).await? {
+		Some(mint_ticket) => mint_ticket,
+		None => return Ok(()),
+	};
+	let linked_hash = match linked_hash(&mint_ticket) {
+		Some(hash) => hash.to_owned(),
+		None => return Ok(()),
+	};
+	let existing_ticket = Query::get_ticket_by_id(&txn, linked_hash.clone()).await?;
+	let removed_ticket = Query::get_deleted_ticket_by_id(&txn, linked_hash).await?;
+
+	let reconciliation = plan_mint_reconciliation(
+		&mint_ticket,
+		existing_ticket.as_ref(),
+		removed_ticket.as_ref(),
+	);
+	if reconciliation.is_empty() {
+		return Ok(());
+	}
+
+	if let Some(archived_ticket) = reconciliation.archive {
+		Mutation::save_deleted_mint_ticket(&txn, archived_ticket).await?;
+	}
+	if let Some(archived_ticket) = reconciliation.release_archive {
+		let (tx_hash, status) = (
+			archived_ticket.tx_hash.clone(),
+			archived_ticket.status.clone(),
+		);
+		Mutation::update_deleted_ticket_statu_and_tx_hash(&txn, archived_ticket, tx_hash, status)
+			.await?;
+	}
+	if let Some(ticket_id) = reconciliation.remove {
+		Delete::remove_ticket_by_id(&txn, ticket_id).await?;
+	}
+	if let Some(state) = reconciliation.mint {
+		Mutation::update_ticket(
+			&txn,
+			mint_ticket,
+			Some(state.status),
+			Some(state.tx_hash),
+			None,
+			None,
+			Some(state.intermediate_tx_hash),
+			None,
+		)
+		.await?;
+	}
+	txn.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+	use crate::entity::sea_orm_active_enums::TxAction;
+	use std::collections::BTreeMap;
+
+	const MINT: &str = "mint-ticket";
+	const LINKED: &str = "linked-transfer";
+	const RELEASE: &str = "release-tx";
+	const STALE: &str = "stale-release-tx";
+
+	#[derive(Clone, Copy, Debug)]
+	enum Link {
+		TxHash,
+		IntermediateTxHash,
+	}
+
+	#[derive(Clone, Copy, Debug)]
+	enum Row {
+		Absent,
+		Waiting,
+		Released(&'static str),
+	}
+
+	// the rows of both tables, reconciled the way `reconcile_mint_ticket` writes them
+	#[derive(Clone, Debug, PartialEq)]
+	struct Tables {
+		tickets: BTreeMap<String, ticket::Model>,
+		removed: BTreeMap<String, deleted_mint_ticket::Model>,
+	}
+
+	impl Tables {
+		fn new(link: Link, existing: Row, removed: Row) -> Self {
+			let mut mint_ticket = fixtures::ticket(MINT, "Bitcoin", "Bitcoin");
+			mint_ticket.action = TxAction::Mint;
+			mint_ticket.status = TicketStatus::WaitingForConfirmByDest;
+			match link {
+				Link::TxHash => mint_ticket.tx_hash = Some(LINKED.to_owned()),
+				Link::IntermediateTxHash => {
+					mint_ticket.intermediate_tx_hash = Some(LINKED.to_owned())
+				}
+			}
+
+			let linked_ticket = |tx_hash: Option<&str>| {
+				let mut linked_ticket = fixtures::ticket(LINKED, "Bitcoin", "eICP");
+				linked_ticket.tx_hash = tx_hash.map(str::to_owned);
+				linked_ticket
+			};
+			let mut tickets = BTreeMap::from([(MINT.to_owned(), mint_ticket)]);
+			let mut removed_tickets: BTreeMap<String, deleted_mint_ticket::Model> = BTreeMap::new();
+			match existing {
+				Row::Absent => {}
+				Row::Waiting => {
+					tickets.insert(LINKED.to_owned(), linked_ticket(None));
+				}
+				Row::Released(tx_hash) => {
+					tickets.insert(LINKED.to_owned(), linked_ticket(Some(tx_hash)));
+				}
+			}
+			match removed {
+				Row::Absent => {}
+				Row::Waiting => {
+					removed_tickets.insert(LINKED.to_owned(), linked_ticket(None).into());
+				}
+				Row::Released(tx_hash) => {
+					removed_tickets.insert(LINKED.to_owned(), linked_ticket(Some(tx_hash)).into());
+				}
+			}
+			Self {
+				tickets,
+				removed: removed_tickets,
+			}
+		}
+
+		fn mint_ticket(&self) -> &ticket::Model {
+			&self.tickets[MINT]
+		}
+
+		fn plan(&self) -> MintReconciliation {
+			let mint_ticket = self.mint_ticket();
+			let linked_hash = linked_hash(mint_ticket).unwrap_or_default();
+			plan_mint_reconciliation(
+				mint_ticket,
+				self.tickets.get(linked_hash),
+				self.removed.get(linked_hash),
+			)
+		}
+
+		fn apply(&mut self, reconciliation: MintReconciliation) {
+			if let Some(archived_ticket) = reconciliation.archive {
+				self.removed
+					.entry(archived_ticket.ticket_id.clone())
+					.or_insert(archived_ticket);
+			}
+			if let Some(archived_ticket) = reconciliation.release_archive {
+				self.removed
+					.insert(archived_ticket.ticket_id.clone(), archived_ticket);
+			}
+			if let Some(ticket_id) = reconciliation.remove {
+				self.tickets.remove(&ticket_id);
+			}
+			if let Some(state) = reconciliation.mint {
+				let mint_ticket = self.tickets.get_mut(MINT).unwrap();
+				mint_ticket.status = state.status;
+				mint_ticket.tx_hash = state.tx_hash;
+				mint_ticket.intermediate_tx_hash = state.intermediate_tx_hash;
+			}
+		}
+
+		// replays the task until nothing is left to write
+		fn settle(mut self) -> Self {
+			for _ in 0..4 {
+				let reconciliation = self.plan();
+				if reconciliation.is_empty() {
+					return self;
+				}
+				self.apply(reconciliation);
+			}
+			panic!("reconciliation does not settle: {:?}", self);
+		}
+	}
+
+	fn state(status: TicketStatus, tx_hash: Option<&str>) -> MintTicketState {
+		MintTicketState {
+			status,
+			tx_hash: tx_hash.map(str::to_owned),
+			intermediate_tx_hash: Some(LINKED.to_owned()),
+		}
+	}
+
+	fn finalized(tx_hash: &str) -> MintTicketState {
+		state(TicketStatus::Finalized, Some(tx_hash))
+	}
+
+	fn waiting() -> MintTicketState {
+		state(TicketStatus::Unknown, None)
+	}
+
+	#[test]
+	fn every_combination_settles_in_the_documented_rows() {
+		use Row::*;
+
+		// (ticket h, deleted_mint_ticket h, archived, mint ticket, release tx of the archive),
+		// the mint ticket is left untouched where it is `None`
+		let cases = [
+			(Absent, Absent, false, Some(waiting()), None),
+			(
+				Released(RELEASE),
+				Absent,
+				true,
+				Some(finalized(RELEASE)),
+				Some(RELEASE),
+			),
+			(Waiting, Absent, true, Some(waiting()), None),
+			(
+				Absent,
+				Released(RELEASE),
+				true,
+				Some(finalized(RELEASE)),
+				Some(RELEASE),
+			),
+			(Absent, Waiting, true, None, None),
+			(
+				Released(STALE),
+				Released(RELEASE),
+				true,
+				Some(finalized(RELEASE)),
+				Some(RELEASE),
+			),
+			(
+				Waiting,
+				Released(RELEASE),
+				true,
+				Some(finalized(RELEASE)),
+				Some(RELEASE),
+			),
+			(
+				Released(RELEASE),
+				Waiting,
+				true,
+				Some(finalized(RELEASE)),
+				Some(RELEASE),
+			),
+			(Waiting, Waiting, true, None, None),
+		];
+
+		for link in [Link::TxHash, Link::IntermediateTxHash] {
+			for (existing, removed, archived, mint_state, archived_tx_hash) in cases.clone() {
+				let initial = Tables::new(link, existing, removed);
+				let tables = initial.clone().settle();
+				let case = format!("{:?} {:?} {:?}", link, existing, removed);
+
+				assert_eq!(
+					MintTicketState::of(tables.mint_ticket()),
+					mint_state.unwrap_or_else(|| MintTicketState::of(initial.mint_ticket())),
+					"{}",
+					case
+				);
+				assert!(!tables.tickets.contains_key(LINKED), "{}", case);
+				assert_eq!(tables.removed.contains_key(LINKED), archived, "{}", case);
+				if archived {
+					assert_eq!(
+						tables.removed[LINKED].tx_hash.as_deref(),
+						archived_tx_hash,
+						"{}",
+						case
+					);
+				}
+				assert_eq!(tables.clone().settle(), tables, "{}", case);
+			}
+		}
+	}
+
+	#[test]
+	fn the_first_pass_archives_before_dropping() {
+		let tables = Tables::new(Link::TxHash, Row::Released(RELEASE), Row::Absent);
+		let reconciliation = tables.plan();
+
+		assert_eq!(
+			reconciliation.archive.map(|archived| archived.ticket_id),
+			Some(LINKED.to_owned())
+		);
+		assert_eq!(reconciliation.remove.as_deref(), Some(LINKED));
+		assert_eq!(reconciliation.mint, Some(finalized(RELEASE)));
+	}
+
+	#[test]
+	fn an_already_reconciled_pair_is_left_alone() {
+		let mut tables = Tables::new(Link::IntermediateTxHash, Row::Absent, Row::Waiting);
+		tables.apply(MintReconciliation {
+			mint: Some(waiting()),
+			..Default::default()
+		});
+
+		assert!(tables.plan().is_empty());
+	}
+
+	#[test]
+	fn mint_tickets_without_a_single_link_are_skipped() {
+		let mut mint_ticket = fixtures::ticket(MINT, "Bitcoin", "Bitcoin");
+		assert!(plan_mint_reconciliation(&mint_ticket, None, None).is_empty());
+
+		mint_ticket.tx_hash = Some(RELEASE.to_owned());
+		mint_ticket.intermediate_tx_hash = Some(LINKED.to_owned());
+		let linked_ticket = fixtures::ticket(LINKED, "Bitcoin", "eICP");
+		assert!(plan_mint_reconciliation(&mint_ticket, Some(&linked_ticket), None).is_empty());
+	}
+
+	#[test]
+	fn a_mint_ticket_never_folds_itself() {
+		let mut mint_ticket = fixtures::ticket(MINT, "Bitcoin", "Bitcoin");
+		mint_ticket.tx_hash = Some(MINT.to_owned());
+
+		let reconciliation = plan_mint_reconciliation(&mint_ticket, Some(&mint_ticket), None);
+		assert_eq!(reconciliation.archive, None);
+		assert_eq!(reconciliation.remove, None);
+		assert_eq!(
+			reconciliation.mint.map(|state| state.intermediate_tx_hash),
+			Some(Some(MINT.to_owned()))
+		);
+	}
+
+	#[test]
+	fn a_reconciled_mint_ticket_stays_linked_to_its_transfer() {
+		use crate::entity::ticket_link::TicketLinkKind;
+
+		let mut tables = Tables::new(Link::TxHash, Row::Released(RELEASE), Row::Absent);
+		let link = tables.mint_ticket().child_link().unwrap();
+		assert_eq!(
+			(link.child_id.as_str(), link.kind),
+			(LINKED, TicketLinkKind::Mint)
+		);
+
+		tables = tables.settle();
+		let link = tables.mint_ticket().child_link().unwrap();
+		assert_eq!(
+			(link.child_id.as_str(), link.kind),
+			(LINKED, TicketLinkKind::Mint)
+		);
+
+		// the release of the folded transfer leads on to the next hop
+		let transfer = ticket::Model::from_deleted_ticket(tables.removed[LINKED].clone());
+		let link = transfer.child_link().unwrap();
+		assert_eq!(
+			(link.child_id.as_str(), link.kind),
+			(RELEASE, TicketLinkKind::Release)
+		);
+	}
+
+	// the table a statement writes first, a recorded delete writes its table before the outbox
+	fn written_table(sql: &str) -> Option<String> {
+		["INSERT INTO ", "DELETE FROM ", "UPDATE "]
+			.iter()
+			.filter_map(|write| sql.find(write).map(|at| (at, at + write.len())))
+			.min()
+			.map(|(_, start)| {
+				sql[start..]
+					.split_whitespace()
+					.next()
+					.unwrap_or_default()
+					.trim_matches('"')
+					.to_owned()
+			})
+	}
+
+	#[tokio::test]
+	async fn a_reconciliation_is_written_in_one_transaction() {
+		use crate::entity::ticket_transition;
+		use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult};
+
+		let tables = Tables::new(Link::TxHash, Row::Released(RELEASE), Row::Absent);
+		let mint_ticket = tables.mint_ticket().clone();
+		let linked_ticket = tables.tickets[LINKED].clone();
+		let archived_ticket = deleted_mint_ticket::Model::from(linked_ticket.clone());
+		let reconciled = tables.clone().settle().mint_ticket().clone();
+		let db = MockDatabase::new(DatabaseBackend::Postgres)
+			// the locked mint ticket and h
+			.append_query_results([vec![mint_ticket.clone()], vec![linked_ticket]])
+			// no archive of h yet
+			.append_query_results([Vec::<deleted_mint_ticket::Model>::new()])
+			// the mint ticket locked and updated
+			.append_query_results([vec![mint_ticket.clone()], vec![reconciled]])
+			// its link to the archived h
+			.append_query_results([Vec::<ticket::Model>::new()])
+			.append_query_results([vec![archived_ticket]])
+			// no parents of the mint ticket
+			.append_query_results([Vec::<ticket::Model>::new()])
+			.append_query_results([Vec::<deleted_mint_ticket::Model>::new()])
+			.append_query_results([vec![ticket_transition::Model {
+				id: 1,
+				ticket_id: MINT.to_owned(),
+				kind: String::new(),
+				status: String::new(),
+				previous_status: None,
+				tx_hash: None,
+				intermediate_tx_hash: None,
+				occurred_at: 0,
+				inferred: false,
+			}]])
+			.append_exec_results(vec![
+				MockExecResult {
+					last_insert_id: 0,
+					rows_affected: 1,
+				};
+				16
+			])
+			.into_connection();
+
+		reconcile_mint_ticket(&db, mint_ticket).await.unwrap();
+
+		let log = db.into_transaction_log();
+		assert_eq!(log.len(), 1, "{:?}", log);
+		let statements = log[0].statements();
+		assert!(statements.first().unwrap().sql.starts_with("BEGIN"));
+		assert!(statements.last().unwrap().sql.starts_with("COMMIT"));
+		let written = statements
+			.iter()
+			.filter_map(|statement| written_table(&statement.sql))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			written,
+			[
+				"deleted_mint_ticket",
+				"outbox",
+				"bridge_fee_log_dirty",
+				"route_volume_dirty",
+				"ticket",
+				"ticket",
+				"outbox",
+				"ticket_link",
+				"outbox",
+				"ticket_transition",
+				"webhook_delivery",
+			]
+		);
+	}
+}
@@ -1,7 +1,10 @@
 pub mod bitcoin;
 pub mod doge;
+pub mod esplora;
 pub mod graphql;
+pub mod mint_reconciliation;
 pub mod sicp;
 pub mod solana_custom;
 
 pub const UPDATE_DELETED_MINT_TICKET_SYNC_INTERVAL: u64 = 30;
+pub const BTC_CONFIRMATION_SYNC_INTERVAL: u64 = 60;
@@ -10,9 +10,11 @@ pub struct Model {
 	#[sea_orm(primary_key, auto_increment = false)]
 	pub date: String,
 	pub fee_token_id: String,
-	pub amount: String,
+	#[sea_orm(column_type = "Decimal(Some((78, 0)))")]
+	pub amount: BigDecimal,
+	#[sea_orm(column_type = "Decimal(None)", nullable)]
+	pub amount_human: Option<BigDecimal>,
 	pub total_ticket: i32,
-	pub seqs: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -24,6 +24,8 @@ pub struct Model {
 	pub status: TicketStatus,
 	pub tx_hash: Option<String>,
 	pub date: String,
+	pub sub_status: Option<String>,
+	pub sub_status_first_seen: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
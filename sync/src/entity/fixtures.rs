@@ -0,0 +1,37 @@
+//! Entity models for tests.
+
+use super::sea_orm_active_enums::{TicketStatus, TicketType, TxAction};
+use super::ticket;
+
+// a bare ticket for the code paths that only look at its ids and chains
+pub fn ticket(ticket_id: &str, src_chain: &str, dst_chain: &str) -> ticket::Model {
+	ticket::Model {
+		ticket_id: ticket_id.to_owned(),
+		ticket_seq: None,
+		ticket_type: TicketType::Normal,
+		ticket_time: 0,
+		src_chain: src_chain.to_owned(),
+		dst_chain: dst_chain.to_owned(),
+		action: TxAction::Transfer,
+		token: String::new(),
+		amount: Some(0.into()),
+		sender: None,
+		receiver: String::new(),
+		memo: None,
+		status: TicketStatus::WaitingForConfirmByDest,
+		tx_hash: None,
+		intermediate_tx_hash: None,
+		bridge_fee: None,
+		date: String::new(),
+		sub_status: None,
+		sub_status_first_seen: None,
+		confirmations: None,
+		block_height: None,
+		memo_hex: None,
+		memo_json: None,
+		referral_code: None,
+		fee_token: None,
+		parse_error: None,
+		amount_human: None,
+	}
+}
@@ -2,14 +2,31 @@
 
 pub mod prelude;
 
+pub mod api_key;
+pub mod api_key_usage;
 pub mod bridge_fee_log;
+pub mod bridge_fee_log_detail;
+pub mod bridge_fee_log_dirty;
+pub mod bridge_fee_log_ticket;
 pub mod chain_meta;
+pub mod daily_stats;
 pub mod deleted_mint_ticket;
+#[cfg(test)]
+pub mod fixtures;
 pub mod launchpad;
+pub mod outbox;
 pub mod pending_ticket;
+pub mod route_volume;
+pub mod route_volume_dirty;
+pub mod rune_terms;
 pub mod sea_orm_active_enums;
+pub mod sender_resolution;
 pub mod ticket;
+pub mod ticket_link;
+pub mod ticket_transition;
 pub mod token_ledger_id_on_chain;
 pub mod token_meta;
 pub mod token_on_chain;
 pub mod token_volume;
+pub mod webhook_delivery;
+pub mod webhook_endpoint;
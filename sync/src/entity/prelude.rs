@@ -1,12 +1,27 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.2
 
+pub use super::api_key::Entity as ApiKey;
+pub use super::api_key_usage::Entity as ApiKeyUsage;
 pub use super::bridge_fee_log::Entity as BridgeFeeLog;
+pub use super::bridge_fee_log_detail::Entity as BridgeFeeLogDetail;
+pub use super::bridge_fee_log_dirty::Entity as BridgeFeeLogDirty;
+pub use super::bridge_fee_log_ticket::Entity as BridgeFeeLogTicket;
 pub use super::chain_meta::Entity as ChainMeta;
+pub use super::daily_stats::Entity as DailyStats;
 pub use super::deleted_mint_ticket::Entity as DeletedMintTicket;
 pub use super::launchpad::Entity as Launchpad;
+pub use super::outbox::Entity as Outbox;
 pub use super::pending_ticket::Entity as PendingTicket;
+pub use super::route_volume::Entity as RouteVolume;
+pub use super::route_volume_dirty::Entity as RouteVolumeDirty;
+pub use super::rune_terms::Entity as RuneTerms;
+pub use super::sender_resolution::Entity as SenderResolution;
 pub use super::ticket::Entity as Ticket;
+pub use super::ticket_link::Entity as TicketLink;
+pub use super::ticket_transition::Entity as TicketTransition;
 pub use super::token_ledger_id_on_chain::Entity as TokenLedgerIdOnChain;
 pub use super::token_meta::Entity as TokenMeta;
 pub use super::token_on_chain::Entity as TokenOnChain;
 pub use super::token_volume::Entity as TokenVolume;
+pub use super::webhook_delivery::Entity as WebhookDelivery;
+pub use super::webhook_endpoint::Entity as WebhookEndpoint;
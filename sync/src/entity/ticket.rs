@@ -17,15 +17,30 @@ pub struct Model {
 	pub dst_chain: String,
 	pub action: TxAction,
 	pub token: String,
-	pub amount: String,
+	#[sea_orm(column_type = "Decimal(Some((78, 0)))", nullable)]
+	pub amount: Option<BigDecimal>,
 	pub sender: Option<String>,
 	pub receiver: String,
 	pub memo: Option<String>,
 	pub status: TicketStatus,
 	pub tx_hash: Option<String>,
 	pub intermediate_tx_hash: Option<String>,
-	pub bridge_fee: Option<String>,
+	#[sea_orm(column_type = "Decimal(Some((78, 0)))", nullable)]
+	pub bridge_fee: Option<BigDecimal>,
 	pub date: String,
+	pub sub_status: Option<String>,
+	pub sub_status_first_seen: Option<Json>,
+	pub confirmations: Option<i32>,
+	pub block_height: Option<i64>,
+	#[sea_orm(column_type = "Text", nullable)]
+	pub memo_hex: Option<String>,
+	pub memo_json: Option<Json>,
+	pub referral_code: Option<String>,
+	pub fee_token: Option<String>,
+	#[sea_orm(column_type = "Text", nullable)]
+	pub parse_error: Option<String>,
+	#[sea_orm(column_type = "Decimal(None)", nullable)]
+	pub amount_human: Option<BigDecimal>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -9,7 +9,10 @@ pub struct Model {
 	pub chain_id: String,
 	#[sea_orm(primary_key, auto_increment = false)]
 	pub token_id: String,
-	pub amount: String,
+	#[sea_orm(column_type = "Decimal(Some((78, 0)))")]
+	pub amount: BigDecimal,
+	#[sea_orm(column_type = "Decimal(None)", nullable)]
+	pub amount_human: Option<BigDecimal>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
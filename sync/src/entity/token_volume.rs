@@ -8,7 +8,10 @@ pub struct Model {
 	#[sea_orm(primary_key, auto_increment = false)]
 	pub token_id: String,
 	pub ticket_count: String,
-	pub historical_volume: String,
+	#[sea_orm(column_type = "Decimal(Some((78, 0)))")]
+	pub historical_volume: BigDecimal,
+	#[sea_orm(column_type = "Decimal(None)", nullable)]
+	pub historical_volume_human: Option<BigDecimal>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
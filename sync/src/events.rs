@@ -0,0 +1,293 @@
+//! Ticket lifecycle events. The sync writes them to a postgres channel next to the ticket
+//! changes, the api process listens on it and fans them out to its subscribers.
+
+use crate::entity::sea_orm_active_enums::TicketStatus;
+use crate::entity::{deleted_mint_ticket, ticket};
+use crate::service::is_hex_address;
+use chrono::Utc;
+use log::{error, info, warn};
+use sea_orm::{sqlx::postgres::PgListener, ActiveEnum, DbConn};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast;
+use utoipa::{IntoParams, ToSchema};
+
+pub const TICKET_EVENTS_CHANNEL: &str = "ticket_events";
+// slow subscribers skip the events they missed past this many
+const EVENT_BUS_CAPACITY: usize = 1024;
+const LISTEN_RETRY_INTERVAL: u64 = 5;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketEventKind {
+	Created,
+	StatusChanged,
+	TxHashChanged,
+}
+
+impl TicketEventKind {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			TicketEventKind::Created => "created",
+			TicketEventKind::StatusChanged => "status_changed",
+			TicketEventKind::TxHashChanged => "tx_hash_changed",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TicketEvent {
+	pub kind: TicketEventKind,
+	pub ticket_id: String,
+	pub src_chain: String,
+	pub dst_chain: String,
+	pub token: String,
+	pub sender: Option<String>,
+	pub receiver: String,
+	pub status: String,
+	pub previous_status: Option<String>,
+	pub tx_hash: Option<String>,
+	pub intermediate_tx_hash: Option<String>,
+	// unix millis of the change
+	pub occurred_at: i64,
+}
+
+impl TicketEvent {
+	fn new(kind: TicketEventKind, ticket: &ticket::Model, previous_status: Option<String>) -> Self {
+		TicketEvent {
+			kind,
+			ticket_id: ticket.ticket_id.clone(),
+			src_chain: ticket.src_chain.clone(),
+			dst_chain: ticket.dst_chain.clone(),
+			token: ticket.token.clone(),
+			sender: ticket.sender.clone(),
+			receiver: ticket.receiver.clone(),
+			status: ticket.status.to_value(),
+			previous_status,
+			tx_hash: ticket.tx_hash.clone(),
+			intermediate_tx_hash: ticket.intermediate_tx_hash.clone(),
+			occurred_at: Utc::now().timestamp_millis(),
+		}
+	}
+
+	pub fn created(ticket: &ticket::Model) -> Self {
+		Self::new(TicketEventKind::Created, ticket, None)
+	}
+
+	// a status change also carries the new tx hash, other updates are not events
+	pub fn changed(before: &ticket::Model, after: &ticket::Model) -> Option<Self> {
+		change_kind(
+			(
+				&before.status,
+				&before.tx_hash,
+				&before.intermediate_tx_hash,
+			),
+			(&after.status, &after.tx_hash, &after.intermediate_tx_hash),
+		)
+		.map(|(kind, previous_status)| Self::new(kind, after, previous_status))
+	}
+
+	// an archived transfer ticket keeps its id, it has no intermediate tx
+	pub fn archived_changed(
+		before: &deleted_mint_ticket::Model,
+		after: &deleted_mint_ticket::Model,
+	) -> Option<Self> {
+		change_kind(
+			(&before.status, &before.tx_hash, &None),
+			(&after.status, &after.tx_hash, &None),
+		)
+		.map(|(kind, previous_status)| TicketEvent {
+			kind,
+			ticket_id: after.ticket_id.clone(),
+			src_chain: after.src_chain.clone(),
+			dst_chain: after.dst_chain.clone(),
+			token: after.token.clone(),
+			sender: after.sender.clone(),
+			receiver: after.receiver.clone(),
+			status: after.status.to_value(),
+			previous_status,
+			tx_hash: after.tx_hash.clone(),
+			intermediate_tx_hash: None,
+			occurred_at: Utc::now().timestamp_millis(),
+		})
+	}
+}
+
+type TicketState<'a> = (&'a TicketStatus, &'a Option<String>, &'a Option<String>);
+
+fn change_kind(
+	before: TicketState,
+	after: TicketState,
+) -> Option<(TicketEventKind, Option<String>)> {
+	if before.0 != after.0 {
+		Some((TicketEventKind::StatusChanged, Some(before.0.to_value())))
+	} else if before.1 != after.1 || before.2 != after.2 {
+		Some((TicketEventKind::TxHashChanged, None))
+	} else {
+		None
+	}
+}
+
+// the events a subscriber asked for, every set field has to match
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TicketEventFilter {
+	pub ticket_id: Option<String>,
+	pub sender: Option<String>,
+	pub receiver: Option<String>,
+	// either the sender or the receiver
+	pub address: Option<String>,
+}
+
+impl TicketEventFilter {
+	pub fn matches(&self, event: &TicketEvent) -> bool {
+		// hex addresses match in any case, as in the ticket queries
+		let same = |address: &String, other: &str| {
+			if is_hex_address(address) {
+				address.eq_ignore_ascii_case(other)
+			} else {
+				address == other
+			}
+		};
+		let is_sender = |address: &String| {
+			event
+				.sender
+				.as_deref()
+				.is_some_and(|sender| same(address, sender))
+		};
+		let is_receiver = |address: &String| same(address, &event.receiver);
+		self.ticket_id
+			.as_ref()
+			.is_none_or(|ticket_id| *ticket_id == event.ticket_id)
+			&& self.sender.as_ref().is_none_or(is_sender)
+			&& self.receiver.as_ref().is_none_or(is_receiver)
+			&& self
+				.address
+				.as_ref()
+				.is_none_or(|address| is_sender(address) || is_receiver(address))
+	}
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+	sender: broadcast::Sender<TicketEvent>,
+}
+
+impl Default for EventBus {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl EventBus {
+	pub fn new() -> Self {
+		let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+		EventBus { sender }
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<TicketEvent> {
+		self.sender.subscribe()
+	}
+
+	pub fn publish(&self, event: TicketEvent) {
+		// no subscriber is not an error
+		let _ = self.sender.send(event);
+	}
+}
+
+// forwards the events of the postgres channel to the bus, reconnecting when the connection drops
+pub async fn listen(db: Arc<DbConn>, bus: EventBus) {
+	loop {
+		if let Err(e) = forward(&db, &bus).await {
+			error!("ticket event listener failed: {:?}", e);
+		}
+		tokio::time::sleep(Duration::from_secs(LISTEN_RETRY_INTERVAL)).await;
+	}
+}
+
+async fn forward(db: &DbConn, bus: &EventBus) -> Result<(), sea_orm::sqlx::Error> {
+	let mut listener = PgListener::connect_with(db.get_postgres_connection_pool()).await?;
+	listener.listen(TICKET_EVENTS_CHANNEL).await?;
+	info!("listening to {}", TICKET_EVENTS_CHANNEL);
+	loop {
+		let notification = listener.recv().await?;
+		match serde_json::from_str::<TicketEvent>(notification.payload()) {
+			Ok(event) => bus.publish(event),
+			Err(e) => warn!("invalid ticket event {}: {:?}", notification.payload(), e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+
+	#[test]
+	fn only_status_and_hash_updates_are_events() {
+		let before = fixtures::ticket("t1", "Bitcoin", "eICP");
+
+		let mut after = before.clone();
+		after.confirmations = Some(3);
+		assert_eq!(TicketEvent::changed(&before, &after), None);
+
+		after.tx_hash = Some("0xabc".to_owned());
+		let event = TicketEvent::changed(&before, &after).unwrap();
+		assert_eq!(event.kind, TicketEventKind::TxHashChanged);
+		assert_eq!(event.tx_hash.as_deref(), Some("0xabc"));
+
+		after.status = TicketStatus::Finalized;
+		let event = TicketEvent::changed(&before, &after).unwrap();
+		assert_eq!(event.kind, TicketEventKind::StatusChanged);
+		assert_eq!(event.status, "Finalized");
+		assert_eq!(
+			event.previous_status.as_deref(),
+			Some("WaitingForConfirmByDest")
+		);
+	}
+
+	#[test]
+	fn archived_tickets_raise_events_too() {
+		let before: deleted_mint_ticket::Model =
+			fixtures::ticket("t1", "Bitcoin", "Bitcoin").into();
+
+		let mut after = before.clone();
+		after.tx_hash = Some("release".to_owned());
+		after.status = TicketStatus::Finalized;
+		let event = TicketEvent::archived_changed(&before, &after).unwrap();
+		assert_eq!(event.kind, TicketEventKind::StatusChanged);
+		assert_eq!(event.ticket_id, "t1");
+		assert_eq!(event.tx_hash.as_deref(), Some("release"));
+
+		assert_eq!(TicketEvent::archived_changed(&after, &after), None);
+	}
+
+	#[test]
+	fn filters_match_either_side_of_an_address() {
+		let mut ticket = fixtures::ticket("t1", "Bitcoin", "eICP");
+		ticket.sender = Some("bc1sender".to_owned());
+		ticket.receiver = "receiver".to_owned();
+		let event = TicketEvent::created(&ticket);
+
+		let by_address = |address: &str| TicketEventFilter {
+			address: Some(address.to_owned()),
+			..Default::default()
+		};
+		assert!(TicketEventFilter::default().matches(&event));
+		assert!(by_address("bc1sender").matches(&event));
+		assert!(by_address("receiver").matches(&event));
+		assert!(!by_address("someone").matches(&event));
+		assert!(!by_address("RECEIVER").matches(&event));
+
+		ticket.receiver = "0xAbCd".to_owned();
+		let event = TicketEvent::created(&ticket);
+		assert!(by_address("0xabcd").matches(&event));
+
+		let filter = TicketEventFilter {
+			ticket_id: Some("t1".to_owned()),
+			sender: Some("receiver".to_owned()),
+			..Default::default()
+		};
+		assert!(!filter.matches(&event));
+	}
+}
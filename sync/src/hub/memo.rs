@@ -0,0 +1,175 @@
+//! Decodes ticket memos into the structured ticket columns.
+
+use crate::ChainId;
+use serde_json::Value;
+
+// the fields kept for a ticket memo
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecodedMemo {
+	// the memo as text when it is valid utf-8
+	pub memo: Option<String>,
+	pub memo_hex: Option<String>,
+	pub memo_json: Option<Value>,
+	pub bridge_fee: Option<String>,
+	pub referral_code: Option<String>,
+	pub fee_token: Option<String>,
+}
+
+impl DecodedMemo {
+	// decodes the memo with the schema of the chain the ticket comes from
+	pub fn decode(src_chain: &str, memo: Option<&[u8]>) -> Self {
+		let memo = match memo {
+			Some(memo) if !memo.is_empty() => memo,
+			_ => return Self::default(),
+		};
+		let mut decoded = Self {
+			memo: std::str::from_utf8(memo).ok().map(str::to_owned),
+			memo_hex: Some(hex::encode(memo)),
+			..Default::default()
+		};
+		MemoDecoders::new()
+			.decoder_for(src_chain)
+			.decode(memo, &mut decoded);
+		decoded
+	}
+}
+
+pub trait MemoDecoder: Send + Sync {
+	// fills the parsed fields of `decoded` from the raw memo
+	fn decode(&self, memo: &[u8], decoded: &mut DecodedMemo);
+}
+
+// json memos as sent by the omnity front ends, e.g. `{"bridge_fee":"1000"}`
+pub struct JsonMemoDecoder;
+
+const BRIDGE_FEE_KEYS: [&str; 1] = ["bridge_fee"];
+const REFERRAL_CODE_KEYS: [&str; 4] = ["referral_code", "referral", "partner_code", "partner"];
+const FEE_TOKEN_KEYS: [&str; 1] = ["fee_token"];
+
+impl MemoDecoder for JsonMemoDecoder {
+	fn decode(&self, memo: &[u8], decoded: &mut DecodedMemo) {
+		let json = match serde_json::from_slice::<Value>(memo) {
+			Ok(json @ Value::Object(_)) => json,
+			_ => return,
+		};
+		decoded.bridge_fee = field(&json, &BRIDGE_FEE_KEYS);
+		decoded.referral_code = field(&json, &REFERRAL_CODE_KEYS);
+		decoded.fee_token = field(&json, &FEE_TOKEN_KEYS);
+		decoded.memo_json = Some(json);
+	}
+}
+
+// the first of `keys` holding a string or a number, numbers are kept in their json form
+fn field(json: &Value, keys: &[&str]) -> Option<String> {
+	keys.iter().find_map(|key| match json.get(key)? {
+		Value::String(value) => Some(value.to_owned()),
+		Value::Number(value) => Some(value.to_string()),
+		_ => None,
+	})
+}
+
+#[derive(Default)]
+pub struct MemoDecoders {
+	decoders: Vec<(Vec<ChainId>, Box<dyn MemoDecoder>)>,
+}
+
+impl MemoDecoders {
+	// chains with their own memo schema get an entry here, the rest fall back to json
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_decoder(mut self, chains: Vec<ChainId>, decoder: Box<dyn MemoDecoder>) -> Self {
+		self.decoders.push((chains, decoder));
+		self
+	}
+
+	pub fn decoder_for(&self, src_chain: &str) -> &dyn MemoDecoder {
+		self.decoders
+			.iter()
+			.find(|(chains, _)| chains.iter().any(|chain| chain == src_chain))
+			.map_or(&JsonMemoDecoder, |(_, decoder)| decoder.as_ref())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn decodes_known_json_keys() {
+		let memo = br#"{"bridge_fee":"1000","partner":"omnity","fee_token":"ICP","extra":1}"#;
+		let decoded = DecodedMemo::decode("Bitcoin", Some(memo));
+
+		assert_eq!(decoded.memo.as_deref(), std::str::from_utf8(memo).ok());
+		assert_eq!(decoded.memo_hex, Some(hex::encode(memo)));
+		assert_eq!(
+			decoded.memo_json,
+			Some(
+				json!({ "bridge_fee": "1000", "partner": "omnity", "fee_token": "ICP", "extra": 1 })
+			)
+		);
+		assert_eq!(decoded.bridge_fee.as_deref(), Some("1000"));
+		assert_eq!(decoded.referral_code.as_deref(), Some("omnity"));
+		assert_eq!(decoded.fee_token.as_deref(), Some("ICP"));
+	}
+
+	#[test]
+	fn numeric_fees_keep_their_digits() {
+		let decoded = DecodedMemo::decode("eICP", Some(br#"{"bridge_fee":20000}"#));
+
+		assert_eq!(decoded.bridge_fee.as_deref(), Some("20000"));
+		assert_eq!(decoded.referral_code, None);
+	}
+
+	#[test]
+	fn binary_and_text_memos_keep_their_raw_bytes() {
+		let binary = [0xff, 0x00, 0x9c];
+		let decoded = DecodedMemo::decode("Ethereum", Some(&binary));
+		assert_eq!(
+			decoded,
+			DecodedMemo {
+				memo_hex: Some("ff009c".to_owned()),
+				..Default::default()
+			}
+		);
+
+		let decoded = DecodedMemo::decode("Ethereum", Some(b"gm"));
+		assert_eq!(decoded.memo.as_deref(), Some("gm"));
+		assert_eq!(decoded.memo_json, None);
+
+		assert_eq!(
+			DecodedMemo::decode("Ethereum", Some(b"")),
+			DecodedMemo::default()
+		);
+		assert_eq!(
+			DecodedMemo::decode("Ethereum", None),
+			DecodedMemo::default()
+		);
+	}
+
+	#[test]
+	fn chains_can_bring_their_own_schema() {
+		struct ReferralOnly;
+		impl MemoDecoder for ReferralOnly {
+			fn decode(&self, memo: &[u8], decoded: &mut DecodedMemo) {
+				decoded.referral_code = std::str::from_utf8(memo).ok().map(str::to_owned);
+			}
+		}
+		let decoders =
+			MemoDecoders::new().with_decoder(vec!["eSolana".to_owned()], Box::new(ReferralOnly));
+
+		let mut decoded = DecodedMemo::default();
+		decoders
+			.decoder_for("eSolana")
+			.decode(b"ref-42", &mut decoded);
+		assert_eq!(decoded.referral_code.as_deref(), Some("ref-42"));
+
+		let mut decoded = DecodedMemo::default();
+		decoders
+			.decoder_for("Bitcoin")
+			.decode(b"ref-42", &mut decoded);
+		assert_eq!(decoded.referral_code, None);
+	}
+}
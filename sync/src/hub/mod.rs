@@ -1,13 +1,16 @@
+pub mod memo;
+
 use crate::entity::token_meta;
 use crate::{
-	bridge_fee_log, pending_ticket,
+	pending_ticket,
 	service::{Mutation, Query},
-	ticket, token_volume, with_omnity_canister, Arg, ChainId, TokenId,
+	ticket, with_omnity_canister, Arg, ChainId, TokenId,
 };
-use chrono::{DateTime, Datelike, NaiveDate, Utc};
-use log::info;
+use chrono::{DateTime, NaiveDate};
+use memo::DecodedMemo;
+// use log::info;
 use sea_orm::DbConn;
-use std::{error::Error, str};
+use std::error::Error;
 
 pub const FETCH_LIMIT: u64 = 50;
 pub const CHAIN_SYNC_INTERVAL: u64 = 1800; // 30 min
@@ -15,167 +18,49 @@ pub const TOKEN_SYNC_INTERVAL: u64 = 1800; // 30 min
 pub const TICKET_SYNC_INTERVAL: u64 = 8;
 pub const TOKEN_ON_CHAIN_SYNC_INTERVAL: u64 = 600; // 10 min
 pub const TOKEN_VOLUME_SYNC_INTERVAL: u64 = 60; // 1 min
-pub const FEE_LOG_SYNC_INTERVAL: u64 = 18000; //5 hrs
+pub const FEE_LOG_SYNC_INTERVAL: u64 = 300; // 5 min
+pub const FEE_LOG_BATCH: u64 = 100;
+pub const AMOUNT_NORMALIZE_INTERVAL: u64 = 60; // 1 min
+pub const ROUTE_VOLUME_BATCH: u64 = 100;
 
+// rebuilds the fee log of the days whose fee paying tickets were written or removed since the
+// last run, the days are marked in the same transaction as the tickets
 pub async fn sync_bridge_fee_log(db: &DbConn) -> Result<(), Box<dyn Error>> {
 	// info!("bridge fee log在工作...");
-
-	for chain in Query::get_all_chain(db).await? {
-		let mut date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap_or_default();
-		let datetime = date.and_hms_opt(0, 0, 0).unwrap_or_default();
-		let mut start_time_nano = datetime.and_utc().timestamp_nanos_opt().unwrap_or_default();
-		let one_day_nano: i64 = 24 * 60 * 60 * 1_000_000_000;
-		let mut end_time_nano: i64 = start_time_nano + one_day_nano;
-
-		let now = Utc::now();
-		let today = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day()).unwrap_or_default();
-		let midnight = today.and_hms_opt(0, 0, 0).unwrap_or_default();
-		let today_nano = midnight.and_utc().timestamp_nanos_opt().unwrap_or_default();
-
-		while end_time_nano <= today_nano {
-			let mut total_amount = 0;
-			let mut total_ticket = 0;
-			let mut seqs = String::new();
-
-			let ticket_list = Query::get_not_null_fee_tickets(
-				db,
-				chain.clone().chain_id,
-				start_time_nano,
-				end_time_nano - 1,
-			)
-			.await?;
-
-			if ticket_list.len() != 0 {
-				for ticket in ticket_list.clone() {
-					let amount = ticket
-						.bridge_fee
-						.unwrap_or_default()
-						.parse::<u128>()
-						.unwrap_or_default();
-					total_amount += amount;
-					total_ticket += 1;
-					seqs.push_str(ticket.ticket_seq.unwrap_or_default().to_string().as_str());
-					seqs.push_str("/");
-				}
-			}
-			if total_ticket != 0 {
-				date = DateTime::from_timestamp_nanos(start_time_nano).date_naive();
-				let bridge_fee_log = bridge_fee_log::Model::new(
-					chain.clone().chain_id,
-					date.to_string(),
-					chain.clone().fee_token.unwrap_or_default(),
-					total_amount.to_string(),
-					total_ticket,
-					seqs.clone(),
-				);
-				Mutation::save_bridge_fee_log(db, bridge_fee_log).await?;
-			}
-
-			start_time_nano = end_time_nano;
-			end_time_nano += one_day_nano;
+	loop {
+		let rebuilt = Mutation::refresh_bridge_fee_logs(db, FEE_LOG_BATCH).await?;
+		if (rebuilt as u64) < FEE_LOG_BATCH {
+			return Ok(());
 		}
 	}
-	Ok(())
 }
 
+// rebuilds the route volumes of the days whose tickets were written, settled or removed since
+// the last run, the days are marked in the same transaction as the tickets
 pub async fn update_volume(db: &DbConn) -> Result<(), Box<dyn Error>> {
-	// info!("token volumes在工作 ...");
-
-	for token in Query::get_all_tokens(db).await? {
-		let token_tickets = Query::get_token_tickets(db, token.clone().token_id).await?;
-		let mut total: u128 = 0;
-		let total_len = token_tickets.len();
-		let mut count = 0;
-		for t in token_tickets {
-			total += t.amount.parse::<u128>().unwrap_or(0);
-			count += 1;
-			if count == total_len {
-				let _token_volume =
-					token_volume::Model::new(token.clone().token_id, total_len, total);
-				Mutation::save_token_volume(db, _token_volume.clone().into()).await?;
-			}
+	loop {
+		let rebuilt = Mutation::refresh_dirty_route_volumes(db, ROUTE_VOLUME_BATCH).await?;
+		if (rebuilt as u64) < ROUTE_VOLUME_BATCH {
+			break;
 		}
 	}
+	Mutation::refresh_token_volumes(db).await?;
 	Ok(())
 }
 
-pub async fn update_sender(db: &DbConn) -> Result<(), Box<dyn Error>> {
-	// Find the tickets with no sender
-	let null_sender_tickets = Query::get_null_sender_tickets(db).await?;
-	// info!("There are {:?} senders are null", null_sender_tickets.len());
-	if null_sender_tickets.len() > 0 {
-		loop {
-			for ticket in null_sender_tickets.clone() {
-				let client = reqwest::Client::builder()
-					.timeout(std::time::Duration::new(30, 0))
-					.build()?;
-				let url = "https://mempool.space/api/tx/".to_string() + &ticket.clone().ticket_id;
-				match client.get(url).header("Origin", "*").send().await {
-					Ok(response) => {
-						match response.text().await {
-							Ok(body) => {
-								let mut a = match serde_json::from_str::<serde_json::Value>(&body) {
-									Ok(v) => v,
-									Err(_) => {
-										let _updated_ticket = Mutation::update_ticket(
-											db,
-											ticket.clone(),
-											None,
-											None,
-											None,
-											Some(Some("unavailable sender".to_string())),
-											None,
-											None,
-										)
-										.await?;
-										// info!(
-										// 	"Ticket id({:?}) has changed its sender to {:?}",
-										// 	ticket.ticket_id, updated_ticket.sender
-										// );
-										continue;
-									}
-								};
-
-								if let Some(vin) = a.get_mut("vin") {
-									if let Some(sender) =
-										vin[0]["prevout"]["scriptpubkey_address"].as_str()
-									{
-										let _sender = sender.to_string();
-										// Insert the sender into the ticket meta
-										let _updated_ticket = Mutation::update_ticket(
-											db,
-											ticket.clone(),
-											None,
-											None,
-											None,
-											Some(Some(_sender)),
-											None,
-											None,
-										)
-										.await?;
-
-										// info!(
-										// 	"Ticket id({:?}) has changed its sender to {:?}",
-										// 	ticket.ticket_id, updated_ticket.sender
-										// );
-									}
-								};
-							}
-							Err(e) => {
-								info!("Mempool error: {:?}", e);
-								continue;
-							}
-						}
-					}
-					Err(e) => {
-						info!("Mempool errors: {:?}", e);
-						continue;
-					}
-				}
-			}
-			break;
-		}
-	}
+// the daily stats are the route volumes, the sync keeps them current from the marked days
+pub async fn backfill_daily_stats(
+	db: &DbConn,
+	since: Option<NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+	Mutation::refresh_route_volumes(db, since).await?;
+	Mutation::refresh_token_volumes(db).await?;
+	Mutation::normalize_amounts(db).await?;
+	Ok(())
+}
+
+pub async fn normalize_amounts(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	Mutation::normalize_amounts(db).await?;
 	Ok(())
 }
 
@@ -370,34 +255,11 @@ pub async fn sync_tickets(db: &DbConn) -> Result<(), Box<dyn Error>> {
 			}
 
 			for (seq, ticket) in new_tickets.iter() {
-				let mut updated_memo = None;
-				let mut bridge_fee = None;
-				if let Some(memo) = ticket.clone().memo {
-					if memo.len() > 0 {
-						if let Ok(new_ticket_memo) = str::from_utf8(&memo) {
-							updated_memo = Some(new_ticket_memo.to_string());
-							// DeletedMintTickets are for minting BTC->ICP/EVM, only added if
-							// minting from Ethereum is active.
-							if let Ok(parsed) =
-								serde_json::from_str::<serde_json::Value>(&new_ticket_memo)
-							{
-								if let Some(fee) = parsed.get("bridge_fee") {
-									bridge_fee = Some(fee.to_string());
-								}
-							}
-						}
-					}
-				}
+				let memo = DecodedMemo::decode(&ticket.src_chain, ticket.memo.as_deref());
 				let date =
 					DateTime::from_timestamp_nanos(ticket.clone().ticket_time as i64).to_string();
-				let ticket_modle = ticket::Model::from_omnity_ticket(
-					*seq,
-					ticket.clone(),
-					updated_memo,
-					bridge_fee,
-					date,
-				)
-				.into();
+				let ticket_modle =
+					ticket::Model::from_omnity_ticket(*seq, ticket.clone(), memo, date).into();
 				Mutation::save_ticket(db, ticket_modle).await?;
 			}
 		}
@@ -434,32 +296,15 @@ pub async fn sync_tickets(db: &DbConn) -> Result<(), Box<dyn Error>> {
 			}
 
 			for (_ticket_id, pending_ticket) in new_pending_tickets.iter() {
-				let mut updated_memo = None;
-				let mut bridge_fee = None;
-				if let Some(memo) = pending_ticket.clone().memo {
-					if memo.len() > 0 {
-						if let Ok(new_ticket_memo) = str::from_utf8(&memo) {
-							updated_memo = Some(new_ticket_memo.to_string());
-							// DeletedMintTickets are for minting BTC->ICP/EVM, only added if
-							// minting from Ethereum is active.
-							if let Ok(parsed) =
-								serde_json::from_str::<serde_json::Value>(&new_ticket_memo)
-							{
-								if let Some(fee) = parsed.get("bridge_fee") {
-									bridge_fee = Some(fee.to_string());
-								}
-							}
-						}
-					}
-				}
+				let memo =
+					DecodedMemo::decode(&pending_ticket.src_chain, pending_ticket.memo.as_deref());
 
 				let date =
 					DateTime::from_timestamp_nanos(pending_ticket.clone().ticket_time as i64)
 						.to_string();
 				let ticket_model = ticket::Model::from_omnity_pending_ticket(
 					pending_ticket.clone().to_owned(),
-					updated_memo,
-					bridge_fee,
+					memo,
 					date,
 				)
 				.into();
@@ -1,11 +1,18 @@
+pub mod api;
+pub mod change_stream;
 pub mod customs;
 pub mod entity;
+pub mod events;
 pub mod hub;
 pub mod routes;
+pub mod sender;
 pub mod service;
+#[cfg(test)]
+pub(crate) mod stub_server;
 pub mod tasks;
 pub mod types;
 pub mod utils;
+pub mod webhook;
 
 pub use customs::*;
 pub use entity::*;
@@ -1,16 +1,125 @@
 use anyhow::anyhow;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
 use dotenvy::dotenv;
-use log::LevelFilter;
+use log::{info, LevelFilter};
 use log4rs::{
 	append::console::ConsoleAppender,
 	config::{Appender, Root},
 };
-use omnity_indexer_sync::{tasks::execute_sync_tasks, utils::*};
+use omnity_indexer_sync::{
+	api::{self, auth},
+	change_stream::OutboxSink,
+	hub,
+	service::{Delete, Mutation, Query},
+	tasks::execute_sync_tasks,
+	utils::*,
+	webhook,
+};
 // use warp::Filter;
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+	#[command(subcommand)]
+	command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Run the sync tasks (the default)
+	Sync,
+	/// Rebuild the route volumes and daily stats from the tickets
+	BackfillDailyStats {
+		/// Only rebuild the days from this date on, e.g. 2025-01-01
+		#[arg(long)]
+		since: Option<NaiveDate>,
+	},
+	/// Serve the REST api
+	Serve {
+		/// The address to listen on, defaults to API_LISTEN_ADDR or 0.0.0.0:8000
+		#[arg(long)]
+		listen: Option<String>,
+	},
+	/// Manage the webhook endpoints
+	Webhook {
+		#[command(subcommand)]
+		command: WebhookCommand,
+	},
+	/// Manage the api keys
+	ApiKey {
+		#[command(subcommand)]
+		command: ApiKeyCommand,
+	},
+}
+
+#[derive(Subcommand)]
+enum WebhookCommand {
+	/// Register an endpoint, the signing secret is generated unless given
+	Add {
+		url: String,
+		#[arg(long)]
+		secret: Option<String>,
+		/// Only tickets from or to this chain
+		#[arg(long)]
+		chain: Option<String>,
+		/// Only tickets of this token
+		#[arg(long)]
+		token: Option<String>,
+		/// Only tickets with this sender or receiver
+		#[arg(long)]
+		address: Option<String>,
+	},
+	/// List the endpoints
+	List,
+	/// Remove an endpoint and its deliveries
+	Remove { id: i64 },
+	/// Queue a delivery again from its first attempt, e.g. a dead-lettered one
+	Retry { delivery_id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ApiKeyCommand {
+	/// Create a key, it is printed once as only its hash is stored
+	Add {
+		name: String,
+		/// Requests per minute
+		#[arg(long, default_value_t = auth::DEFAULT_KEY_RATE_LIMIT)]
+		rate_limit: i32,
+		/// Requests per utc day, unlimited by default
+		#[arg(long)]
+		daily_quota: Option<i64>,
+	},
+	/// List the keys
+	List,
+	/// Change the limits of a key
+	Update {
+		id: i64,
+		/// Requests per minute
+		#[arg(long)]
+		rate_limit: Option<i32>,
+		/// Requests per utc day
+		#[arg(long, conflicts_with = "no_daily_quota")]
+		daily_quota: Option<i64>,
+		/// Lift the daily quota
+		#[arg(long)]
+		no_daily_quota: bool,
+	},
+	/// Revoke a key, its usage is kept
+	Revoke { id: i64 },
+	/// Print the daily request counts of a key
+	Usage {
+		id: i64,
+		/// The number of days back from today
+		#[arg(long, default_value_t = 30)]
+		days: i64,
+	},
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	dotenv().ok();
+	let cli = Cli::parse();
 	let stdout = ConsoleAppender::builder().build();
 	let config = log4rs::config::Config::builder()
 		.appender(Appender::builder().build("stdout", Box::new(stdout)))
@@ -25,7 +134,131 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 	let db_url = std::env::var("DATABASE_URL").map_err(|_| anyhow!("DATABASE_URL is not found"))?;
 	let db = Database::new(db_url.clone()).await;
-	execute_sync_tasks(db.get_connection()).await;
+	match cli.command.unwrap_or(Command::Sync) {
+		Command::Sync => execute_sync_tasks(db.get_connection(), OutboxSink::from_env()?).await,
+		Command::BackfillDailyStats { since } => {
+			hub::backfill_daily_stats(&db.get_connection(), since).await?;
+			info!("daily stats rebuilt: since={:?}", since);
+		}
+		Command::Serve { listen } => {
+			let addr = listen.unwrap_or_else(api::api_listen_addr);
+			api::serve(db.get_connection(), &addr).await?;
+		}
+		Command::Webhook { command } => run_webhook_command(&db, command).await?,
+		Command::ApiKey { command } => run_api_key_command(&db, command).await?,
+	}
+
+	Ok(())
+}
+
+async fn run_webhook_command(
+	db: &Database,
+	command: WebhookCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let db = db.get_connection();
+	match command {
+		WebhookCommand::Add {
+			url,
+			secret,
+			chain,
+			token,
+			address,
+		} => {
+			let secret = secret.unwrap_or_else(webhook::new_secret);
+			let endpoint =
+				Mutation::save_webhook_endpoint(&db, url, secret, chain, token, address).await?;
+			println!(
+				"endpoint {} added, secret: {}",
+				endpoint.id, endpoint.secret
+			);
+		}
+		WebhookCommand::List => {
+			for endpoint in Query::get_webhook_endpoints(&db).await? {
+				println!(
+					"{}\t{}\tchain={}\ttoken={}\taddress={}\tactive={}",
+					endpoint.id,
+					endpoint.url,
+					endpoint.chain.unwrap_or_default(),
+					endpoint.token.unwrap_or_default(),
+					endpoint.address.unwrap_or_default(),
+					endpoint.active
+				);
+			}
+		}
+		WebhookCommand::Remove { id } => {
+			let result = Delete::remove_webhook_endpoint(&db, id).await?;
+			println!("{} endpoint removed", result.rows_affected);
+		}
+		WebhookCommand::Retry { delivery_id } => {
+			let retried = Mutation::retry_webhook_delivery(&db, delivery_id).await?;
+			println!("{} delivery queued", retried);
+		}
+	}
+	Ok(())
+}
 
+async fn run_api_key_command(
+	db: &Database,
+	command: ApiKeyCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let db = db.get_connection();
+	match command {
+		ApiKeyCommand::Add {
+			name,
+			rate_limit,
+			daily_quota,
+		} => {
+			let key = auth::new_api_key();
+			let api_key = Mutation::save_api_key(
+				&db,
+				name,
+				auth::api_key_prefix(&key),
+				auth::hash_api_key(&key),
+				rate_limit,
+				daily_quota,
+			)
+			.await?;
+			println!("api key {} added: {}", api_key.id, key);
+		}
+		ApiKeyCommand::List => {
+			for api_key in Query::get_api_keys(&db).await? {
+				println!(
+					"{}\t{}\t{}...\trate_limit={}/min\tdaily_quota={}\tactive={}",
+					api_key.id,
+					api_key.name,
+					api_key.prefix,
+					api_key.rate_limit,
+					api_key
+						.daily_quota
+						.map_or("unlimited".to_owned(), |quota| quota.to_string()),
+					api_key.active
+				);
+			}
+		}
+		ApiKeyCommand::Update {
+			id,
+			rate_limit,
+			daily_quota,
+			no_daily_quota,
+		} => {
+			let daily_quota = if no_daily_quota {
+				Some(None)
+			} else {
+				daily_quota.map(Some)
+			};
+			let updated = Mutation::update_api_key_limits(&db, id, rate_limit, daily_quota).await?;
+			println!("{} api key updated", updated);
+		}
+		ApiKeyCommand::Revoke { id } => {
+			let revoked = Mutation::revoke_api_key(&db, id).await?;
+			println!("{} api key revoked", revoked);
+		}
+		ApiKeyCommand::Usage { id, days } => {
+			let since = Utc::now().date_naive() - Duration::days(days.max(1) - 1);
+			for usage in Query::get_api_key_usage(&db, id, since).await? {
+				println!("{}\t{}", usage.date, usage.request_count);
+			}
+		}
+	}
 	Ok(())
 }
@@ -1,7 +1,7 @@
 use crate::entity::sea_orm_active_enums::TicketStatus;
 use crate::routes::MintTokenStatus;
 use crate::service::{Mutation, Query};
-use crate::{token_ledger_id_on_chain, with_omnity_canister, Arg, ChainId};
+use crate::{is_canister_configured, token_ledger_id_on_chain, with_omnity_canister, Arg, ChainId};
 // use log::info;
 use sea_orm::DbConn;
 use serde::{Deserialize, Serialize};
@@ -13,21 +13,38 @@ struct OsmoRoute {
 	pub chain: ChainId,
 }
 
+pub struct OsmoRoutes {
+	routes: Vec<OsmoRoute>,
+}
+
+impl OsmoRoutes {
+	// only the routes whose canister id is configured are synced
+	pub fn new() -> Self {
+		let routes = vec![
+			OsmoRoute {
+				canister: "OSMOSIS_TEST5_CHAIN_ID",
+				chain: "osmo-test-5".to_owned(),
+			},
+			OsmoRoute {
+				canister: "OSMOSIS1_CHAIN_ID",
+				chain: "osmosis-1".to_owned(),
+			},
+		];
+		Self {
+			routes: routes
+				.into_iter()
+				.filter(|route| is_canister_configured(route.canister))
+				.collect(),
+		}
+	}
+}
+
 pub async fn sync_all_tickets_status_from_cosmwasm_route(
 	db: &DbConn,
 ) -> Result<(), Box<dyn Error>> {
-	let osmosis_routes: Vec<OsmoRoute> = vec![
-		// OsmoRoute {
-		// 	canister: "OSMOSIS_TEST5_CHAIN_ID",
-		// 	chain: "osmo-test-5".to_owned(),
-		// },
-		OsmoRoute {
-			canister: "OSMOSIS1_CHAIN_ID",
-			chain: "osmosis-1".to_owned(),
-		},
-	];
+	let osmosis_routes = OsmoRoutes::new();
 
-	for osmosis_route in osmosis_routes.iter() {
+	for osmosis_route in osmosis_routes.routes.iter() {
 		with_omnity_canister(osmosis_route.canister, |agent, canister_id| async move {
 			// info!("osmosis状态更新在工作 ... ");
 			let unconfirmed_tickets =
@@ -74,7 +91,25 @@ pub async fn sync_all_tickets_status_from_cosmwasm_route(
 }
 
 pub async fn sync_all_cosmwasm_token_ledger_id_on_chain(db: &DbConn) -> Result<(), Box<dyn Error>> {
-	with_omnity_canister("OSMOSIS1_CHAIN_ID", |agent, canister_id| async move {
+	let osmosis_routes = OsmoRoutes::new();
+
+	for osmosis_route in osmosis_routes.routes.iter() {
+		sync_cosmwasm_token_ledger_id_on_chain(
+			db,
+			osmosis_route.canister,
+			osmosis_route.chain.clone(),
+		)
+		.await?;
+	}
+	Ok(())
+}
+
+async fn sync_cosmwasm_token_ledger_id_on_chain(
+	db: &DbConn,
+	canister: &str,
+	chain: ChainId,
+) -> Result<(), Box<dyn Error>> {
+	with_omnity_canister(canister, |agent, canister_id| async move {
 		// info!("cosmwasm token_ledger_id_on_chain状态更新在工作 ... ");
 		let token_ledgers = Arg::V(Vec::<u8>::new())
 			.query_method(
@@ -91,7 +126,7 @@ pub async fn sync_all_cosmwasm_token_ledger_id_on_chain(db: &DbConn) -> Result<(
 		for cosmwasm_token_resp in token_ledgers {
 			if let Some(token_denom) = &cosmwasm_token_resp.token_denom {
 				let token_ledger_id_on_chain_model = token_ledger_id_on_chain::Model::new(
-					"osmosis-1".to_owned(),
+					chain.clone(),
 					cosmwasm_token_resp.token_id,
 					token_denom.to_owned(),
 				);
@@ -103,7 +138,5 @@ pub async fn sync_all_cosmwasm_token_ledger_id_on_chain(db: &DbConn) -> Result<(
 		}
 		Ok(())
 	})
-	.await?;
-
-	Ok(())
+	.await
 }
@@ -1,9 +1,14 @@
 use crate::entity::{sea_orm_active_enums::TicketStatus, ticket};
+use crate::routes::evm_verifier::{
+	min_confirmations, not_found_expired, not_found_timeout, EvmRpc, Verification,
+};
 use crate::routes::MintTokenStatus;
 use crate::service::{Mutation, Query};
 use crate::{token_ledger_id_on_chain, with_omnity_canister, Arg, ChainId};
+use chrono::Utc;
 // use log::info;
-use sea_orm::DbConn;
+use log::warn;
+use sea_orm::{DbConn, DbErr};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 
@@ -11,6 +16,8 @@ use std::error::Error;
 struct EvmRoute {
 	pub canister: &'static str,
 	pub chain: ChainId,
+	// env var of the optional json-rpc endpoint used to verify destination txs
+	pub rpc: &'static str,
 }
 
 pub struct EvmRoutes {
@@ -24,54 +31,71 @@ impl EvmRoutes {
 				// EvmRoute {
 				// 	canister: "BEVM_CHAIN_ID",
 				// 	chain: "bevm".to_owned(),
+				// 	rpc: "BEVM_RPC_URL",
 				// },
 				EvmRoute {
 					canister: "BITLAYER_CHAIN_ID",
 					chain: "Bitlayer".to_owned(),
+					rpc: "BITLAYER_RPC_URL",
 				},
 				// EvmRoute {
 				// 	canister: "XLAYER_CHAIN_ID",
 				// 	chain: "X Layer".to_owned(),
+				// 	rpc: "XLAYER_RPC_URL",
 				// },
 				// EvmRoute {
 				// 	canister: "BSQUARE_CHAIN_ID",
 				// 	chain: "B² Network".to_owned(),
+				// 	rpc: "BSQUARE_RPC_URL",
 				// },
 				// EvmRoute {
 				// 	canister: "MERLIN_CHAIN_ID",
 				// 	chain: "Merlin".to_owned(),
+				// 	rpc: "MERLIN_RPC_URL",
 				// },
 				// EvmRoute {
 				// 	canister: "BOB_CHAIN_ID",
 				// 	chain: "Bob".to_owned(),
+				// 	rpc: "BOB_RPC_URL",
 				// },
 				// EvmRoute {
 				// 	canister: "ROOTSTOCK_CHAIN_ID",
 				// 	chain: "RootStock".to_owned(),
+				// 	rpc: "ROOTSTOCK_RPC_URL",
 				// },
 				EvmRoute {
 					canister: "BITFINITY_CHAIN_ID",
 					chain: "Bitfinity".to_owned(),
+					rpc: "BITFINITY_RPC_URL",
 				},
 				// EvmRoute {
 				// 	canister: "AILAYER_CHAIN_ID",
 				// 	chain: "AILayer".to_owned(),
+				// 	rpc: "AILAYER_RPC_URL",
 				// },
 				EvmRoute {
 					canister: "EVM_CANISTER_ID",
 					chain: "Ethereum".to_owned(),
+					rpc: "ETHEREUM_RPC_URL",
 				},
 				// EvmRoute {
 				// 	canister: "CORE_CANISTER_ID",
 				// 	chain: "Core".to_owned(),
+				// 	rpc: "CORE_RPC_URL",
 				// },
 				// EvmRoute {
 				// 	canister: "BASE_CANISTER_ID",
 				// 	chain: "Base".to_owned(),
+				// 	rpc: "BASE_RPC_URL",
 				// },
 			],
 		}
 	}
+
+	// every bridged chain with the env var of its json-rpc endpoint
+	pub fn rpcs(&self) -> impl Iterator<Item = (&ChainId, &'static str)> {
+		self.routes.iter().map(|route| (&route.chain, route.rpc))
+	}
 }
 
 pub async fn sync_all_token_ledger_id_from_evm_route(db: &DbConn) -> Result<(), Box<dyn Error>> {
@@ -97,7 +121,9 @@ pub async fn sync_all_tickets_status_from_evm_route(db: &DbConn) -> Result<(), B
 					db,
 					evm_route.canister,
 					evm_route.chain.clone(),
+					evm_route.rpc,
 					unconfirmed_ticket,
+					false,
 				)
 				.await?;
 			}
@@ -109,7 +135,9 @@ pub async fn sync_all_tickets_status_from_evm_route(db: &DbConn) -> Result<(), B
 					db,
 					evm_route.canister,
 					evm_route.chain.clone(),
+					evm_route.rpc,
 					_unconfirmed_ticket,
+					true,
 				)
 				.await?;
 			}
@@ -164,8 +192,10 @@ async fn sync_all_evm_token_ledger_id_on_chain(
 async fn sync_ticket_status_from_evm_route(
 	db: &DbConn,
 	canister: &str,
-	_chain: ChainId,
+	chain: ChainId,
+	rpc: &str,
 	ticket: ticket::Model,
+	archived: bool,
 ) -> Result<(), Box<dyn Error>> {
 	with_omnity_canister(canister, |agent, canister_id| async move {
 		// info!("evm状态更新在工作 ... ");
@@ -182,10 +212,43 @@ async fn sync_ticket_status_from_evm_route(
 			.convert_to_mint_token_status();
 
 		if let MintTokenStatus::Finalized { tx_hash } = mint_evm_token_status {
+			let verification = match verify_destination_tx(db, chain, rpc, &ticket, &tx_hash).await
+			{
+				Ok(verification) => verification,
+				Err(e) => {
+					warn!(
+						"evm tx verification error: ticket_id={}, tx_hash={}, error={}",
+						ticket.ticket_id, tx_hash, e
+					);
+					return Ok(());
+				}
+			};
+			let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+			let status = match &verification {
+				Some(Verification::Verified { .. }) | None => TicketStatus::Finalized,
+				Some(Verification::NotFound)
+					if not_found_expired(&ticket, now, not_found_timeout()) =>
+				{
+					TicketStatus::Failed
+				}
+				// not final yet, the next pass checks the tx again. a reverted or mismatched tx
+				// waits too, the route may still report another one
+				Some(verification) => {
+					save_sub_status(db, ticket, archived, Some(verification.sub_status())).await?;
+					return Ok(());
+				}
+			};
+			// a failed ticket keeps why it failed, a finalized one drops the stage it waited in
+			let sub_status = match &verification {
+				Some(Verification::Verified { .. }) | None => None,
+				Some(verification) => Some(verification.sub_status()),
+			};
+			save_sub_status(db, ticket.clone(), archived, sub_status).await?;
+
 			if let Ok(_ticket_model) = Mutation::update_ticket(
 				db,
 				ticket.clone(),
-				Some(TicketStatus::Finalized),
+				Some(status.clone()),
 				Some(Some(tx_hash.clone())),
 				None,
 				None,
@@ -202,7 +265,7 @@ async fn sync_ticket_status_from_evm_route(
 				db,
 				ticket.into(),
 				Some(tx_hash),
-				TicketStatus::Finalized,
+				status,
 			)
 			.await
 			{
@@ -216,3 +279,49 @@ async fn sync_ticket_status_from_evm_route(
 	})
 	.await
 }
+
+// an archived transfer keeps its sub status on the archived row
+async fn save_sub_status(
+	db: &DbConn,
+	ticket: ticket::Model,
+	archived: bool,
+	sub_status: Option<&str>,
+) -> Result<(), DbErr> {
+	match (archived, sub_status) {
+		(false, Some(sub_status)) => {
+			Mutation::update_ticket_sub_status(db, ticket, sub_status).await?;
+		}
+		(false, None) => {
+			Mutation::clear_ticket_sub_status(db, ticket).await?;
+		}
+		(true, Some(sub_status)) => {
+			Mutation::update_deleted_ticket_sub_status(db, ticket.into(), sub_status).await?;
+		}
+		(true, None) => {
+			Mutation::clear_deleted_ticket_sub_status(db, ticket.into()).await?;
+		}
+	}
+	Ok(())
+}
+
+// checks the destination tx against the route's rpc endpoint, `None` when no verifier is configured
+async fn verify_destination_tx(
+	db: &DbConn,
+	chain: ChainId,
+	rpc: &str,
+	ticket: &ticket::Model,
+	tx_hash: &str,
+) -> anyhow::Result<Option<Verification>> {
+	let evm_rpc = match EvmRpc::from_env(rpc)? {
+		Some(evm_rpc) => evm_rpc,
+		None => return Ok(None),
+	};
+	let contract = Query::get_token_ledger_id_on_chain_by_id(db, chain, ticket.token.clone())
+		.await?
+		.map(|ledger| ledger.contract_id);
+
+	let verification = evm_rpc
+		.verify_transaction(tx_hash, contract.as_deref(), min_confirmations())
+		.await?;
+	Ok(Some(verification))
+}
@@ -0,0 +1,287 @@
+use crate::entity::ticket;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const EVM_MIN_CONFIRMATIONS: u64 = 12;
+// seconds a tx reported as finalized may stay unknown to the rpc before its ticket fails
+pub const EVM_NOT_FOUND_TIMEOUT: i64 = 24 * 3600;
+
+// the outcome of checking a destination tx reported as finalized by an evm route
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verification {
+	Verified { confirmations: u64 },
+	AwaitingConfirmations { confirmations: u64 },
+	Reverted,
+	ContractMismatch,
+	NotFound,
+}
+
+impl Verification {
+	// the stage name kept in `ticket.sub_status`
+	pub fn sub_status(&self) -> &'static str {
+		match self {
+			Self::Verified { .. } => "Verified",
+			Self::AwaitingConfirmations { .. } => "AwaitingConfirmations",
+			Self::Reverted => "Reverted",
+			Self::ContractMismatch => "ContractMismatch",
+			Self::NotFound => "NotFound",
+		}
+	}
+}
+
+pub fn min_confirmations() -> u64 {
+	std::env::var("EVM_MIN_CONFIRMATIONS")
+		.ok()
+		.and_then(|confirmations| confirmations.parse().ok())
+		.unwrap_or(EVM_MIN_CONFIRMATIONS)
+}
+
+pub fn not_found_timeout() -> i64 {
+	std::env::var("EVM_NOT_FOUND_TIMEOUT")
+		.ok()
+		.and_then(|timeout| timeout.parse().ok())
+		.unwrap_or(EVM_NOT_FOUND_TIMEOUT)
+}
+
+// whether the rpc has not known the tx of the ticket for longer than `timeout` seconds at `now`
+pub fn not_found_expired(ticket: &ticket::Model, now: i64, timeout: i64) -> bool {
+	ticket
+		.sub_status_first_seen
+		.as_ref()
+		.and_then(|first_seen| first_seen.get(Verification::NotFound.sub_status()))
+		.and_then(|at| at.as_i64())
+		.is_some_and(|at| now - at > timeout * 1_000_000_000)
+}
+
+// shared by every route and pass, the client pools its connections
+static HTTP_CLIENT: Lazy<reqwest::Result<Client>> =
+	Lazy::new(|| Client::builder().timeout(Duration::from_secs(30)).build());
+
+pub struct EvmRpc {
+	client: Client,
+	url: String,
+}
+
+impl EvmRpc {
+	pub fn new(url: String) -> Result<Self> {
+		let client = HTTP_CLIENT
+			.as_ref()
+			.map_err(|e| anyhow!("evm rpc http client: {}", e))?;
+		Ok(Self::with_client(client.clone(), url))
+	}
+
+	pub fn with_client(client: Client, url: String) -> Self {
+		Self { client, url }
+	}
+
+	// the verifier is optional, a route without a configured rpc endpoint is trusted as is
+	pub fn from_env(rpc: &str) -> Result<Option<Self>> {
+		match std::env::var(rpc) {
+			Ok(url) if !url.is_empty() => Self::new(url).map(Some),
+			_ => Ok(None),
+		}
+	}
+
+	pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+		let body = json!({
+			"jsonrpc": "2.0",
+			"id": 1,
+			"method": method,
+			"params": params,
+		});
+		let response: Value = self
+			.client
+			.post(&self.url)
+			.json(&body)
+			.send()
+			.await?
+			.json()
+			.await?;
+
+		if let Some(error) = response.get("error") {
+			return Err(anyhow!("rpc error: method={}, error={}", method, error));
+		}
+		Ok(response.get("result").cloned().unwrap_or(Value::Null))
+	}
+
+	pub async fn block_number(&self) -> Result<u64> {
+		parse_quantity(&self.request("eth_blockNumber", json!([])).await?)
+	}
+
+	pub async fn transaction_receipt(&self, tx_hash: &str) -> Result<Option<Value>> {
+		let receipt = self
+			.request("eth_getTransactionReceipt", json!([tx_hash]))
+			.await?;
+		Ok((!receipt.is_null()).then_some(receipt))
+	}
+
+	// checks the receipt status, that `contract` took part in the tx and the confirmation depth
+	pub async fn verify_transaction(
+		&self,
+		tx_hash: &str,
+		contract: Option<&str>,
+		min_confirmations: u64,
+	) -> Result<Verification> {
+		let receipt = match self.transaction_receipt(tx_hash).await? {
+			Some(receipt) => receipt,
+			None => return Ok(Verification::NotFound),
+		};
+
+		if parse_quantity(&receipt["status"])? != 1 {
+			return Ok(Verification::Reverted);
+		}
+
+		if let Some(contract) = contract {
+			if !touches_contract(&receipt, contract) {
+				return Ok(Verification::ContractMismatch);
+			}
+		}
+
+		let block_number = parse_quantity(&receipt["blockNumber"])?;
+		let latest = self.block_number().await?;
+		let confirmations = (latest + 1).saturating_sub(block_number);
+		if confirmations < min_confirmations {
+			return Ok(Verification::AwaitingConfirmations { confirmations });
+		}
+		Ok(Verification::Verified { confirmations })
+	}
+}
+
+// the route mints through its port contract, so the token contract shows up in the logs
+fn touches_contract(receipt: &Value, contract: &str) -> bool {
+	let is_contract = |address: &Value| {
+		address
+			.as_str()
+			.is_some_and(|address| address.eq_ignore_ascii_case(contract))
+	};
+
+	is_contract(&receipt["to"])
+		|| receipt["logs"]
+			.as_array()
+			.is_some_and(|logs| logs.iter().any(|log| is_contract(&log["address"])))
+}
+
+pub fn parse_quantity(value: &Value) -> Result<u64> {
+	let quantity = value
+		.as_str()
+		.ok_or_else(|| anyhow!("invalid quantity: {}", value))?;
+	Ok(u64::from_str_radix(quantity.trim_start_matches("0x"), 16)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+	use crate::stub_server;
+
+	const TX_HASH: &str = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+	const TOKEN: &str = "0x51cb5e5e6b2bd0d2b8d7b4e2a3c3a7e1ad9e7d10";
+
+	async fn stub_rpc(status: &'static str, latest: &'static str) -> EvmRpc {
+		let url = stub_server::spawn(move |request| {
+			let call = request.json();
+			let result = match call["method"].as_str() {
+				Some("eth_getTransactionReceipt") => json!({
+					"transactionHash": TX_HASH,
+					"status": status,
+					"blockNumber": "0x64",
+					"to": "0x1111111111111111111111111111111111111111",
+					"logs": [{ "address": TOKEN.to_uppercase().replace("0X", "0x") }],
+				}),
+				Some("eth_blockNumber") => json!(latest),
+				_ => Value::Null,
+			};
+			(
+				200,
+				json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }).to_string(),
+			)
+		})
+		.await;
+		EvmRpc::new(url).unwrap()
+	}
+
+	#[tokio::test]
+	async fn verifies_receipt_status_contract_and_depth() {
+		let rpc = stub_rpc("0x1", "0x6f").await;
+
+		assert_eq!(
+			rpc.verify_transaction(TX_HASH, Some(TOKEN), 12)
+				.await
+				.unwrap(),
+			Verification::Verified { confirmations: 12 }
+		);
+		assert_eq!(
+			rpc.verify_transaction(TX_HASH, Some(TOKEN), 13)
+				.await
+				.unwrap(),
+			Verification::AwaitingConfirmations { confirmations: 12 }
+		);
+		assert_eq!(
+			rpc.verify_transaction(
+				TX_HASH,
+				Some("0x2222222222222222222222222222222222222222"),
+				1
+			)
+			.await
+			.unwrap(),
+			Verification::ContractMismatch
+		);
+	}
+
+	#[tokio::test]
+	async fn reverted_transactions_are_reported() {
+		let rpc = stub_rpc("0x0", "0x6f").await;
+
+		assert_eq!(
+			rpc.verify_transaction(TX_HASH, Some(TOKEN), 1)
+				.await
+				.unwrap(),
+			Verification::Reverted
+		);
+	}
+
+	#[test]
+	fn unknown_txs_expire_after_the_timeout() {
+		let mut ticket = fixtures::ticket("t1", "Bitcoin", "Bitlayer");
+		let now = 100_000 * 1_000_000_000;
+		assert!(!not_found_expired(&ticket, now, 3600));
+
+		ticket.sub_status_first_seen = Some(json!({ "NotFound": now - 3600 * 1_000_000_000 }));
+		assert!(!not_found_expired(&ticket, now, 3600));
+		assert!(not_found_expired(&ticket, now + 1, 3600));
+	}
+
+	// runs against a local node, e.g. `anvil` or `npx hardhat node`:
+	// EVM_TEST_RPC_URL=http://127.0.0.1:8545 cargo test -- --ignored
+	#[tokio::test]
+	#[ignore]
+	async fn verifies_a_transfer_on_a_local_node() {
+		let url = std::env::var("EVM_TEST_RPC_URL")
+			.unwrap_or_else(|_| "http://127.0.0.1:8545".to_string());
+		let rpc = EvmRpc::new(url).unwrap();
+		let accounts = rpc.request("eth_accounts", json!([])).await.unwrap();
+		let (from, to) = (accounts[0].as_str().unwrap(), accounts[1].as_str().unwrap());
+		let tx_hash = rpc
+			.request(
+				"eth_sendTransaction",
+				json!([{ "from": from, "to": to, "value": "0x1" }]),
+			)
+			.await
+			.unwrap();
+		let tx_hash = tx_hash.as_str().unwrap();
+
+		assert_eq!(
+			rpc.verify_transaction(tx_hash, Some(to), 1).await.unwrap(),
+			Verification::Verified { confirmations: 1 }
+		);
+		assert_eq!(
+			rpc.verify_transaction(tx_hash, Some(from), 1)
+				.await
+				.unwrap(),
+			Verification::ContractMismatch
+		);
+	}
+}
@@ -1,5 +1,6 @@
 pub mod cosmwasm;
 pub mod evm;
+pub mod evm_verifier;
 pub mod icp;
 pub mod solana;
 pub mod sui;
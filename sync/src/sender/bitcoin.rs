@@ -0,0 +1,58 @@
+use super::{RateLimiter, SenderResolver};
+use crate::customs::bitcoin::{brc20_source_txid, BITCOIN_CHAIN_ID, BRC20_CHAIN_ID};
+use crate::customs::esplora::{esplora_url, Esplora, DEFAULT_ESPLORA_URL};
+use crate::entity::ticket;
+use crate::ChainId;
+use anyhow::Result;
+use reqwest::Client;
+use sea_orm::prelude::async_trait::async_trait;
+use serde_json::Value;
+use std::time::Duration;
+
+// mempool.space bans clients that go much beyond a few requests per second
+pub const ESPLORA_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct EsploraResolver {
+	esplora: Esplora,
+	limiter: RateLimiter,
+}
+
+impl EsploraResolver {
+	pub fn new(client: Client, url: String) -> Self {
+		Self {
+			esplora: Esplora::with_client(client, url),
+			limiter: RateLimiter::new(ESPLORA_REQUEST_INTERVAL),
+		}
+	}
+
+	pub fn from_env(client: Client) -> Self {
+		Self::new(
+			client,
+			esplora_url().unwrap_or_else(|| DEFAULT_ESPLORA_URL.to_owned()),
+		)
+	}
+}
+
+#[async_trait]
+impl SenderResolver for EsploraResolver {
+	fn chains(&self) -> Vec<ChainId> {
+		vec![BITCOIN_CHAIN_ID.to_owned(), BRC20_CHAIN_ID.to_owned()]
+	}
+
+	async fn resolve(&self, ticket: &ticket::Model) -> Result<Option<String>> {
+		let txid = brc20_source_txid(&ticket.ticket_id);
+		self.limiter.wait().await;
+		Ok(self
+			.esplora
+			.tx(txid)
+			.await?
+			.as_ref()
+			.and_then(first_input_address))
+	}
+}
+
+fn first_input_address(tx: &Value) -> Option<String> {
+	tx["vin"][0]["prevout"]["scriptpubkey_address"]
+		.as_str()
+		.map(str::to_owned)
+}
@@ -0,0 +1,59 @@
+use super::{base_url, get_json, RateLimiter, SenderResolver};
+use crate::customs::doge::DOGECOIN_CUSTOM_CHAIN_ID;
+use crate::entity::ticket;
+use crate::ChainId;
+use anyhow::Result;
+use reqwest::Client;
+use sea_orm::prelude::async_trait::async_trait;
+use serde_json::Value;
+use std::time::Duration;
+
+pub const DEFAULT_DOGECOIN_EXPLORER_URL: &str = "https://api.blockcypher.com/v1/doge/main";
+// the blockcypher free tier allows 3 requests per second
+pub const DOGECOIN_EXPLORER_REQUEST_INTERVAL: Duration = Duration::from_millis(350);
+
+// reads txs from a blockcypher compatible api
+pub struct DogeResolver {
+	client: Client,
+	url: String,
+	limiter: RateLimiter,
+}
+
+impl DogeResolver {
+	pub fn new(client: Client, url: String) -> Self {
+		Self {
+			client,
+			url,
+			limiter: RateLimiter::new(DOGECOIN_EXPLORER_REQUEST_INTERVAL),
+		}
+	}
+
+	pub fn from_env(client: Client) -> Self {
+		Self::new(
+			client,
+			base_url("DOGECOIN_EXPLORER_URL", DEFAULT_DOGECOIN_EXPLORER_URL),
+		)
+	}
+}
+
+#[async_trait]
+impl SenderResolver for DogeResolver {
+	fn chains(&self) -> Vec<ChainId> {
+		vec![DOGECOIN_CUSTOM_CHAIN_ID.to_owned()]
+	}
+
+	async fn resolve(&self, ticket: &ticket::Model) -> Result<Option<String>> {
+		let txid = ticket
+			.ticket_id
+			.split(':')
+			.next()
+			.unwrap_or(&ticket.ticket_id);
+		self.limiter.wait().await;
+		let tx = get_json(&self.client, format!("{}/txs/{}", self.url, txid)).await?;
+		Ok(tx.as_ref().and_then(first_input_address))
+	}
+}
+
+fn first_input_address(tx: &Value) -> Option<String> {
+	tx["inputs"][0]["addresses"][0].as_str().map(str::to_owned)
+}
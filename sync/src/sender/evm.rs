@@ -0,0 +1,69 @@
+use super::{RateLimiter, SenderResolver};
+use crate::entity::ticket;
+use crate::routes::evm::EvmRoutes;
+use crate::routes::evm_verifier::EvmRpc;
+use crate::ChainId;
+use anyhow::Result;
+use reqwest::Client;
+use sea_orm::prelude::async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const EVM_RPC_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+// reads the `from` of the source tx through the json-rpc endpoint of the evm route
+pub struct EvmResolver {
+	rpcs: HashMap<ChainId, (EvmRpc, RateLimiter)>,
+}
+
+impl EvmResolver {
+	pub fn new(rpcs: HashMap<ChainId, EvmRpc>) -> Self {
+		let rpcs = rpcs
+			.into_iter()
+			.map(|(chain, rpc)| (chain, (rpc, RateLimiter::new(EVM_RPC_REQUEST_INTERVAL))))
+			.collect();
+		Self { rpcs }
+	}
+
+	// chains whose rpc env var is unset are not resolved
+	pub fn from_env(client: Client) -> Self {
+		let rpcs = EvmRoutes::new()
+			.rpcs()
+			.filter_map(|(chain, rpc)| {
+				std::env::var(rpc)
+					.ok()
+					.filter(|url| !url.is_empty())
+					.map(|url| (chain.clone(), EvmRpc::with_client(client.clone(), url)))
+			})
+			.collect();
+		Self::new(rpcs)
+	}
+}
+
+#[async_trait]
+impl SenderResolver for EvmResolver {
+	fn chains(&self) -> Vec<ChainId> {
+		self.rpcs.keys().cloned().collect()
+	}
+
+	async fn resolve(&self, ticket: &ticket::Model) -> Result<Option<String>> {
+		let (rpc, limiter) = match self.rpcs.get(&ticket.src_chain) {
+			Some(rpc) => rpc,
+			None => return Ok(None),
+		};
+		limiter.wait().await;
+		let tx = rpc
+			.request(
+				"eth_getTransactionByHash",
+				json!([evm_source_tx_hash(&ticket.ticket_id)]),
+			)
+			.await?;
+		Ok(tx["from"].as_str().map(str::to_owned))
+	}
+}
+
+// evm ticket ids start with the hash of the tx that emitted them
+pub fn evm_source_tx_hash(ticket_id: &str) -> &str {
+	ticket_id.get(..66).unwrap_or(ticket_id)
+}
@@ -0,0 +1,67 @@
+use super::{base_url, get_json, RateLimiter, SenderResolver};
+use crate::customs::sicp::ICP_CUSTOM_CHAIN_ID;
+use crate::entity::ticket;
+use crate::ChainId;
+use anyhow::Result;
+use reqwest::Client;
+use sea_orm::prelude::async_trait::async_trait;
+use std::time::Duration;
+
+pub const DEFAULT_ICP_LEDGER_API_URL: &str = "https://ledger-api.internetcomputer.org";
+pub const ICP_LEDGER_API_REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+// looks the ticket up as a block of the icp ledger
+pub struct IcpResolver {
+	client: Client,
+	url: String,
+	limiter: RateLimiter,
+}
+
+impl IcpResolver {
+	pub fn new(client: Client, url: String) -> Self {
+		Self {
+			client,
+			url,
+			limiter: RateLimiter::new(ICP_LEDGER_API_REQUEST_INTERVAL),
+		}
+	}
+
+	pub fn from_env(client: Client) -> Self {
+		Self::new(
+			client,
+			base_url("ICP_LEDGER_API_URL", DEFAULT_ICP_LEDGER_API_URL),
+		)
+	}
+}
+
+#[async_trait]
+impl SenderResolver for IcpResolver {
+	fn chains(&self) -> Vec<ChainId> {
+		vec![ICP_CUSTOM_CHAIN_ID.to_owned()]
+	}
+
+	async fn resolve(&self, ticket: &ticket::Model) -> Result<Option<String>> {
+		let block_index = match icp_block_index(&ticket.ticket_id) {
+			Some(block_index) => block_index,
+			None => return Ok(None),
+		};
+		self.limiter.wait().await;
+		let block = get_json(
+			&self.client,
+			format!("{}/transactions/{}", self.url, block_index),
+		)
+		.await?;
+		Ok(block
+			.as_ref()
+			.and_then(|block| block["from_account_identifier"].as_str())
+			.map(str::to_owned))
+	}
+}
+
+// the icp custom keys tickets by the ledger block index of the deposit
+pub fn icp_block_index(ticket_id: &str) -> Option<u64> {
+	ticket_id
+		.rsplit(|c: char| !c.is_ascii_digit())
+		.next()
+		.and_then(|block_index| block_index.parse().ok())
+}
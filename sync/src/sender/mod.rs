@@ -0,0 +1,302 @@
+//! Resolves the sender of a ticket from the tx that created it on its source chain.
+
+pub mod bitcoin;
+pub mod doge;
+pub mod evm;
+pub mod icp;
+
+use crate::entity::ticket;
+use crate::service::{Delete, Mutation, Query};
+use crate::ChainId;
+use anyhow::Result;
+use chrono::Utc;
+use log::info;
+use once_cell::sync::Lazy;
+use reqwest::{Client, StatusCode};
+use sea_orm::prelude::async_trait::async_trait;
+use sea_orm::DbConn;
+use serde_json::Value;
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+pub use bitcoin::EsploraResolver;
+pub use doge::DogeResolver;
+pub use evm::EvmResolver;
+pub use icp::IcpResolver;
+
+pub const SENDER_BATCH_SIZE: u64 = 50;
+pub const SENDER_MAX_ATTEMPTS: i32 = 5;
+pub const SENDER_RETRY_INTERVAL: i64 = 600; // 10 min
+
+// one client and one set of rate limiters for every backfill run
+static RESOLVERS: Lazy<reqwest::Result<SenderResolvers>> = Lazy::new(|| {
+	let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+	Ok(SenderResolvers::new(client))
+});
+
+#[async_trait]
+pub trait SenderResolver: Send + Sync {
+	// the source chains whose tickets are looked up by this resolver
+	fn chains(&self) -> Vec<ChainId>;
+
+	// `Ok(None)` when the source tx is unknown or carries no sender,
+	// errors are transient and the ticket is retried later
+	async fn resolve(&self, ticket: &ticket::Model) -> Result<Option<String>>;
+}
+
+pub struct SenderResolvers {
+	resolvers: Vec<Box<dyn SenderResolver>>,
+}
+
+impl SenderResolvers {
+	pub fn new(client: Client) -> Self {
+		Self {
+			resolvers: vec![
+				Box::new(EsploraResolver::from_env(client.clone())),
+				Box::new(DogeResolver::from_env(client.clone())),
+				Box::new(EvmResolver::from_env(client.clone())),
+				Box::new(IcpResolver::from_env(client)),
+			],
+		}
+	}
+
+	// tickets from chains without a resolver keep their null sender
+	pub fn resolver_for(&self, src_chain: &str) -> Option<&dyn SenderResolver> {
+		self.resolvers
+			.iter()
+			.find(|resolver| resolver.chains().iter().any(|chain| chain == src_chain))
+			.map(|resolver| resolver.as_ref())
+	}
+
+	pub fn chains(&self) -> Vec<ChainId> {
+		self.resolvers
+			.iter()
+			.flat_map(|resolver| resolver.chains())
+			.collect()
+	}
+}
+
+// spaces out the requests sent to one provider
+pub struct RateLimiter {
+	interval: Duration,
+	next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+	pub fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+			next: Mutex::new(Instant::now()),
+		}
+	}
+
+	pub async fn wait(&self) {
+		let mut next = self.next.lock().await;
+		tokio::time::sleep_until(*next).await;
+		*next = Instant::now() + self.interval;
+	}
+}
+
+// resolves a bounded batch of the newest null-sender tickets, failed or empty lookups are
+// retried after `SENDER_RETRY_INTERVAL` until `SENDER_MAX_ATTEMPTS` is reached
+pub async fn backfill_senders(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	let resolvers = RESOLVERS
+		.as_ref()
+		.map_err(|e| format!("sender http client: {}", e))?;
+	let retry_before = Utc::now().timestamp_nanos_opt().unwrap_or_default()
+		- SENDER_RETRY_INTERVAL * 1_000_000_000;
+	let null_sender_tickets = Query::get_null_sender_tickets(
+		db,
+		resolvers.chains(),
+		SENDER_MAX_ATTEMPTS,
+		retry_before,
+		SENDER_BATCH_SIZE,
+	)
+	.await?;
+
+	for ticket in null_sender_tickets {
+		let resolver = match resolvers.resolver_for(&ticket.src_chain) {
+			Some(resolver) => resolver,
+			None => continue,
+		};
+		let sender = match resolver.resolve(&ticket).await {
+			Ok(Some(sender)) => sender,
+			// the provider may not have indexed the source tx yet, the placeholder is only
+			// written once the last attempt found nothing either
+			Ok(None) => {
+				let attempts = Mutation::save_sender_resolution_failure(
+					db,
+					ticket.ticket_id.clone(),
+					"sender not found".to_owned(),
+				)
+				.await?;
+				if attempts < SENDER_MAX_ATTEMPTS {
+					continue;
+				}
+				"unavailable sender".to_string()
+			}
+			Err(e) => {
+				info!(
+					"sender resolution error: ticket_id={}, src_chain={}, error={}",
+					ticket.ticket_id, ticket.src_chain, e
+				);
+				Mutation::save_sender_resolution_failure(db, ticket.ticket_id, e.to_string())
+					.await?;
+				continue;
+			}
+		};
+		let ticket_id = ticket.ticket_id.clone();
+		Mutation::update_ticket(db, ticket, None, None, None, Some(Some(sender)), None, None)
+			.await?;
+		Delete::remove_sender_resolution(db, ticket_id).await?;
+	}
+	Ok(())
+}
+
+pub(crate) fn base_url(var: &str, default: &str) -> String {
+	std::env::var(var)
+		.ok()
+		.filter(|url| !url.is_empty())
+		.unwrap_or_else(|| default.to_owned())
+		.trim_end_matches('/')
+		.to_owned()
+}
+
+// explorer apis answer unknown txs with 400 or 404
+pub(crate) async fn get_json(client: &Client, url: String) -> Result<Option<Value>> {
+	let response = client.get(url).send().await?;
+	if matches!(
+		response.status(),
+		StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND
+	) {
+		return Ok(None);
+	}
+	Ok(Some(response.error_for_status()?.json().await?))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+	use crate::routes::evm_verifier::EvmRpc;
+	use crate::stub_server;
+	use serde_json::json;
+	use std::collections::HashMap;
+
+	const BTC_TXID: &str = "8a1c2f6e4d3b5a7c9e0f1a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f";
+	const EVM_TX_HASH: &str = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+
+	async fn stub_explorers() -> String {
+		stub_server::spawn(|request| {
+			let body = match (request.method.as_str(), request.path.as_str()) {
+				("GET", path) if path == format!("/esplora/tx/{}", BTC_TXID) => json!({
+					"vin": [{ "prevout": { "scriptpubkey_address": "bc1qsender" } }],
+				}),
+				("GET", "/doge/txs/6f5e4d") => json!({
+					"inputs": [{ "addresses": ["DSenderAddress"] }],
+				}),
+				("GET", "/icp/transactions/1024") => json!({
+					"block_height": "1024",
+					"from_account_identifier": "f2c9a1d0",
+				}),
+				("POST", "/evm") => json!({
+					"jsonrpc": "2.0",
+					"id": 1,
+					"result": { "hash": EVM_TX_HASH, "from": "0xsender" },
+				}),
+				_ => return (404, "Transaction not found".to_owned()),
+			};
+			(200, body.to_string())
+		})
+		.await
+	}
+
+	fn resolvers(url: &str) -> SenderResolvers {
+		let client = Client::new();
+		let rpcs = HashMap::from([(
+			"Bitlayer".to_owned(),
+			EvmRpc::with_client(client.clone(), format!("{}/evm", url)),
+		)]);
+		SenderResolvers {
+			resolvers: vec![
+				Box::new(EsploraResolver::new(
+					client.clone(),
+					format!("{}/esplora", url),
+				)),
+				Box::new(DogeResolver::new(client.clone(), format!("{}/doge", url))),
+				Box::new(EvmResolver::new(rpcs)),
+				Box::new(IcpResolver::new(client, format!("{}/icp", url))),
+			],
+		}
+	}
+
+	async fn resolve(
+		resolvers: &SenderResolvers,
+		ticket_id: &str,
+		src_chain: &str,
+	) -> Option<String> {
+		let ticket = fixtures::ticket(ticket_id, src_chain, "eICP");
+		resolvers
+			.resolver_for(src_chain)
+			.expect("resolver for src chain")
+			.resolve(&ticket)
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn routes_tickets_by_src_chain() {
+		let url = stub_explorers().await;
+		let resolvers = resolvers(&url);
+
+		assert_eq!(
+			resolve(&resolvers, BTC_TXID, "Bitcoin").await.as_deref(),
+			Some("bc1qsender")
+		);
+		assert_eq!(
+			resolve(&resolvers, &format!("{}:0", BTC_TXID), "Bitcoinbrc20")
+				.await
+				.as_deref(),
+			Some("bc1qsender")
+		);
+		assert_eq!(
+			resolve(&resolvers, "6f5e4d", "Dogecoin").await.as_deref(),
+			Some("DSenderAddress")
+		);
+		assert_eq!(
+			resolve(&resolvers, EVM_TX_HASH, "Bitlayer")
+				.await
+				.as_deref(),
+			Some("0xsender")
+		);
+		assert_eq!(
+			resolve(&resolvers, "1024", "sICP").await.as_deref(),
+			Some("f2c9a1d0")
+		);
+		assert!(resolvers.resolver_for("eSolana").is_none());
+		assert!(!resolvers.chains().contains(&"Bitfinity".to_owned()));
+		assert!(resolvers.resolver_for("Bitfinity").is_none());
+	}
+
+	#[tokio::test]
+	async fn rate_limiter_spaces_requests() {
+		let limiter = RateLimiter::new(Duration::from_millis(50));
+		let start = Instant::now();
+		for _ in 0..3 {
+			limiter.wait().await;
+		}
+		assert!(start.elapsed() >= Duration::from_millis(100));
+	}
+
+	#[tokio::test]
+	async fn unknown_source_txs_have_no_sender() {
+		let url = stub_explorers().await;
+		let resolvers = resolvers(&url);
+
+		assert_eq!(resolve(&resolvers, "deadbeef", "Bitcoin").await, None);
+		assert_eq!(resolve(&resolvers, "deadbeef", "Dogecoin").await, None);
+		assert_eq!(resolve(&resolvers, "2048", "sICP").await, None);
+	}
+}
@@ -1,17 +1,170 @@
+use crate::change_stream::{self, Topic};
 use crate::entity::sea_orm_active_enums::{TicketStatus, TxAction};
 use crate::entity::{
-	bridge_fee_log, chain_meta, deleted_mint_ticket, launchpad, pending_ticket, ticket,
-	token_ledger_id_on_chain, token_meta, token_on_chain, token_volume,
+	api_key, api_key_usage, bridge_fee_log, chain_meta, deleted_mint_ticket, launchpad, outbox,
+	pending_ticket, rune_terms, sender_resolution, ticket, ticket_link, ticket_transition,
+	token_ledger_id_on_chain, token_meta, token_on_chain, token_volume, webhook_delivery,
+	webhook_endpoint,
 };
 use crate::entity::{
+	api_key::Entity as ApiKey, api_key_usage::Entity as ApiKeyUsage,
 	bridge_fee_log::Entity as BridgeFeeLog, chain_meta::Entity as ChainMeta,
 	deleted_mint_ticket::Entity as DeletedMintTicket, launchpad::Entity as Launchpad,
-	pending_ticket::Entity as PendingTicket, ticket::Entity as Ticket,
+	outbox::Entity as Outbox, pending_ticket::Entity as PendingTicket,
+	rune_terms::Entity as RuneTerms, sender_resolution::Entity as SenderResolution,
+	ticket::Entity as Ticket, ticket_link::Entity as TicketLink,
+	ticket_transition::Entity as TicketTransition,
 	token_ledger_id_on_chain::Entity as TokenLedgerIdOnChain, token_meta::Entity as TokenMeta,
 	token_on_chain::Entity as TokenOnChain, token_volume::Entity as TokenVolume,
+	webhook_delivery::Entity as WebhookDelivery, webhook_endpoint::Entity as WebhookEndpoint,
 };
+use crate::events::{TicketEvent, TICKET_EVENTS_CHANNEL};
+use crate::webhook;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, Utc};
 use log::info;
 use sea_orm::{sea_query::OnConflict, *};
+use serde_json::json;
+use std::collections::{HashSet, VecDeque};
+
+// the filters of a ticket list, `chain` matches either end of the ticket and `address`
+// either side
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TicketFilter {
+	pub chain: Option<String>,
+	pub address: Option<String>,
+	pub src_chain: Option<String>,
+	pub dst_chain: Option<String>,
+	pub token: Option<String>,
+	pub status: Option<TicketStatus>,
+	pub from_time: Option<i64>,
+	pub to_time: Option<i64>,
+}
+
+impl TicketFilter {
+	pub fn condition(self) -> Condition {
+		let mut condition = Condition::all();
+		if let Some(chain) = self.chain {
+			condition = condition.add(
+				Condition::any()
+					.add(ticket::Column::SrcChain.eq(chain.clone()))
+					.add(ticket::Column::DstChain.eq(chain)),
+			);
+		}
+		if let Some(address) = self.address {
+			condition = condition.add(
+				Condition::any()
+					.add(address_eq(ticket::Column::Sender, &address))
+					.add(address_eq(ticket::Column::Receiver, &address)),
+			);
+		}
+		if let Some(src_chain) = self.src_chain {
+			condition = condition.add(ticket::Column::SrcChain.eq(src_chain));
+		}
+		if let Some(dst_chain) = self.dst_chain {
+			condition = condition.add(ticket::Column::DstChain.eq(dst_chain));
+		}
+		if let Some(token) = self.token {
+			condition = condition.add(ticket::Column::Token.eq(token));
+		}
+		if let Some(status) = self.status {
+			condition = condition.add(ticket::Column::Status.eq(status));
+		}
+		if let Some(from_time) = self.from_time {
+			condition = condition.add(ticket::Column::TicketTime.gte(from_time));
+		}
+		if let Some(to_time) = self.to_time {
+			condition = condition.add(ticket::Column::TicketTime.lt(to_time));
+		}
+		condition
+	}
+}
+
+// the tickets of an address on either side, `status_counts` and the times cover them all
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressSummary {
+	pub ticket_count: i64,
+	pub first_seen: Option<i64>,
+	pub last_seen: Option<i64>,
+	pub status_counts: Vec<(String, i64)>,
+	pub tokens: Vec<AddressTokenFlow>,
+}
+
+// the amounts of a token the address received and sent, only finalized tickets move funds
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddressTokenFlow {
+	pub token: String,
+	pub inflow: BigDecimal,
+	pub outflow: BigDecimal,
+	pub inflow_human: Option<BigDecimal>,
+	pub outflow_human: Option<BigDecimal>,
+	pub incoming_count: i64,
+	pub outgoing_count: i64,
+	pub first_seen: i64,
+	pub last_seen: i64,
+}
+
+// a ticket holding the searched hash, `matched_by` names the column and `archived` tells a
+// mint ticket of `deleted_mint_ticket`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TicketHashMatch {
+	pub ticket: ticket::Model,
+	pub matched_by: String,
+	pub archived: bool,
+}
+
+// the spellings a pasted hash may be stored under, hex hashes are stored in lower case and
+// either with or without their `0x` prefix depending on the chain
+pub fn hash_variants(hash: &str) -> Vec<String> {
+	let hash = hash.trim();
+	let mut variants = vec![hash.to_owned()];
+	let digits = hash
+		.strip_prefix("0x")
+		.or_else(|| hash.strip_prefix("0X"))
+		.unwrap_or(hash);
+	if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+		let digits = digits.to_ascii_lowercase();
+		variants.push(format!("0x{}", digits));
+		variants.push(digits);
+	}
+	let mut seen = HashSet::new();
+	variants.retain(|variant| seen.insert(variant.clone()));
+	variants
+}
+
+// evm addresses come checksummed or not, hex addresses are compared in lowercase on the
+// `lower(...)` indexes while the others, such as base58 ones, stay case sensitive
+pub fn is_hex_address(address: &str) -> bool {
+	let digits = address
+		.strip_prefix("0x")
+		.or_else(|| address.strip_prefix("0X"))
+		.unwrap_or(address);
+	!digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn address_eq(column: ticket::Column, address: &str) -> sea_query::SimpleExpr {
+	if is_hex_address(address) {
+		sea_query::Expr::expr(sea_query::Func::lower(sea_query::Expr::col(column)))
+			.eq(address.to_ascii_lowercase())
+	} else {
+		column.eq(address)
+	}
+}
+
+// the first seen times of the sub statuses once the ticket enters `sub_status` at `now`, a
+// stage entered again after the ticket left it starts over
+fn enter_sub_status(
+	first_seen: Option<serde_json::Value>,
+	sub_status: &str,
+	now: i64,
+) -> serde_json::Value {
+	let mut first_seen = match first_seen {
+		Some(serde_json::Value::Object(map)) => map,
+		_ => serde_json::Map::new(),
+	};
+	first_seen.insert(sub_status.to_owned(), json!(now));
+	serde_json::Value::Object(first_seen)
+}
 
 pub struct Query;
 
@@ -22,20 +175,36 @@ impl Query {
 	pub async fn get_all_chain(db: &DbConn) -> Result<Vec<chain_meta::Model>, DbErr> {
 		ChainMeta::find().all(db).await
 	}
-	pub async fn get_ticket_by_id(
-		db: &DbConn,
+	pub async fn get_ticket_by_id<C: ConnectionTrait>(
+		db: &C,
 		ticket_id: String,
 	) -> Result<Option<ticket::Model>, DbErr> {
 		Ticket::find_by_id(ticket_id).one(db).await
 	}
-	pub async fn get_deleted_ticket_by_id(
-		db: &DbConn,
+	// locks the row until the transaction of `db` ends
+	pub async fn get_ticket_by_id_for_update<C: ConnectionTrait>(
+		db: &C,
+		ticket_id: String,
+	) -> Result<Option<ticket::Model>, DbErr> {
+		Ticket::find_by_id(ticket_id).lock_exclusive().one(db).await
+	}
+	pub async fn get_deleted_ticket_by_id<C: ConnectionTrait>(
+		db: &C,
 		deleted_ticket_id: String,
 	) -> Result<Option<deleted_mint_ticket::Model>, DbErr> {
 		DeletedMintTicket::find_by_id(deleted_ticket_id)
 			.one(db)
 			.await
 	}
+	pub async fn get_deleted_ticket_by_id_for_update<C: ConnectionTrait>(
+		db: &C,
+		deleted_ticket_id: String,
+	) -> Result<Option<deleted_mint_ticket::Model>, DbErr> {
+		DeletedMintTicket::find_by_id(deleted_ticket_id)
+			.lock_exclusive()
+			.one(db)
+			.await
+	}
 	pub async fn get_token_ledger_id_on_chain_by_id(
 		db: &DbConn,
 		chain_id: String,
@@ -45,6 +214,435 @@ impl Query {
 			.one(db)
 			.await
 	}
+	// every ticket of the transfer the ticket belongs to, from its first hop on
+	pub async fn get_ticket_journey(
+		db: &DbConn,
+		ticket_id: String,
+	) -> Result<Vec<ticket::Model>, DbErr> {
+		let mut first_hop = ticket_id;
+		let mut visited = HashSet::from([first_hop.clone()]);
+		while let Some(link) = TicketLink::find()
+			.filter(ticket_link::Column::ChildId.eq(first_hop.clone()))
+			.order_by_asc(ticket_link::Column::ParentId)
+			.one(db)
+			.await?
+		{
+			if !visited.insert(link.parent_id.clone()) {
+				break;
+			}
+			first_hop = link.parent_id;
+		}
+
+		let mut journey = Vec::new();
+		let mut visited = HashSet::from([first_hop.clone()]);
+		let mut hops = VecDeque::from([first_hop]);
+		while let Some(hop) = hops.pop_front() {
+			// the transfer folded into a mint ticket lives on in `deleted_mint_ticket`
+			match Self::get_ticket_by_id(db, hop.clone()).await? {
+				Some(ticket) => journey.push(ticket),
+				None => {
+					if let Some(deleted_ticket) =
+						Self::get_deleted_ticket_by_id(db, hop.clone()).await?
+					{
+						journey.push(ticket::Model::from_deleted_ticket(deleted_ticket));
+					}
+				}
+			}
+			let links = TicketLink::find()
+				.filter(ticket_link::Column::ParentId.eq(hop))
+				.order_by_asc(ticket_link::Column::ChildId)
+				.all(db)
+				.await?;
+			for link in links {
+				if visited.insert(link.child_id.clone()) {
+					hops.push_back(link.child_id);
+				}
+			}
+		}
+		Ok(journey)
+	}
+
+	// the tickets and archived mint tickets whose id, tx hash or intermediate tx hash is the
+	// hash, live tickets first
+	pub async fn search_ticket_hash(
+		db: &DbConn,
+		hash: &str,
+	) -> Result<Vec<TicketHashMatch>, DbErr> {
+		let variants = hash_variants(hash);
+		let placeholders = (1..=variants.len())
+			.map(|i| format!("${}", i))
+			.collect::<Vec<_>>()
+			.join(", ");
+		let rows = db
+			.query_all(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				format!(
+					r#"
+					SELECT DISTINCT ticket_id, kind, archived
+					FROM ticket_hash
+					WHERE hash IN ({})
+					ORDER BY archived, ticket_id, kind
+					"#,
+					placeholders
+				),
+				variants.into_iter().map(Into::into),
+			))
+			.await?;
+
+		let mut matches = Vec::new();
+		for row in rows {
+			let ticket_id: String = row.try_get("", "ticket_id")?;
+			let archived: bool = row.try_get("", "archived")?;
+			let ticket = if archived {
+				Self::get_deleted_ticket_by_id(db, ticket_id)
+					.await?
+					.map(ticket::Model::from_deleted_ticket)
+			} else {
+				Self::get_ticket_by_id(db, ticket_id).await?
+			};
+			// the ticket may have moved to the archive since the lookup
+			if let Some(ticket) = ticket {
+				matches.push(TicketHashMatch {
+					ticket,
+					matched_by: row.try_get("", "kind")?,
+					archived,
+				});
+			}
+		}
+		Ok(matches)
+	}
+
+	// newest first, `after` is the (ticket_time, ticket_id) of the last ticket of the previous
+	// page
+	pub async fn get_tickets(
+		db: &DbConn,
+		filter: TicketFilter,
+		after: Option<(i64, String)>,
+		limit: u64,
+	) -> Result<Vec<ticket::Model>, DbErr> {
+		let mut condition = filter.condition();
+		if let Some((ticket_time, ticket_id)) = after {
+			condition = condition.add(
+				Condition::any()
+					.add(ticket::Column::TicketTime.lt(ticket_time))
+					.add(
+						Condition::all()
+							.add(ticket::Column::TicketTime.eq(ticket_time))
+							.add(ticket::Column::TicketId.lt(ticket_id)),
+					),
+			);
+		}
+		Ticket::find()
+			.filter(condition)
+			.order_by_desc(ticket::Column::TicketTime)
+			.order_by_desc(ticket::Column::TicketId)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_address_summary(
+		db: &DbConn,
+		address: String,
+	) -> Result<AddressSummary, DbErr> {
+		let (sender, receiver, address) = if is_hex_address(&address) {
+			(
+				"lower(sender)",
+				"lower(receiver)",
+				address.to_ascii_lowercase(),
+			)
+		} else {
+			("sender", "receiver", address)
+		};
+		let statuses = db
+			.query_all(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				format!(
+					r#"
+					SELECT status::text AS status, count(*) AS count,
+						min(ticket_time) AS first_seen, max(ticket_time) AS last_seen
+					FROM ticket
+					WHERE {sender} = $1 OR {receiver} = $1
+					GROUP BY status
+					ORDER BY status
+					"#
+				),
+				[address.clone().into()],
+			))
+			.await?;
+		let mut summary = AddressSummary::default();
+		for row in statuses {
+			let count: i64 = row.try_get("", "count")?;
+			let first_seen: i64 = row.try_get("", "first_seen")?;
+			let last_seen: i64 = row.try_get("", "last_seen")?;
+			summary.ticket_count += count;
+			summary.first_seen = Some(summary.first_seen.map_or(first_seen, |t| t.min(first_seen)));
+			summary.last_seen = Some(summary.last_seen.map_or(last_seen, |t| t.max(last_seen)));
+			summary
+				.status_counts
+				.push((row.try_get("", "status")?, count));
+		}
+
+		let tokens = db
+			.query_all(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				format!(
+					r#"
+					SELECT token,
+						coalesce(sum(amount) FILTER (WHERE {receiver} = $1 AND status = 'Finalized'), 0) AS inflow,
+						coalesce(sum(amount) FILTER (WHERE {sender} = $1 AND status = 'Finalized'), 0) AS outflow,
+						sum(amount_human) FILTER (WHERE {receiver} = $1 AND status = 'Finalized') AS inflow_human,
+						sum(amount_human) FILTER (WHERE {sender} = $1 AND status = 'Finalized') AS outflow_human,
+						count(*) FILTER (WHERE {receiver} = $1 AND status = 'Finalized') AS incoming_count,
+						count(*) FILTER (WHERE {sender} = $1 AND status = 'Finalized') AS outgoing_count,
+						min(ticket_time) AS first_seen, max(ticket_time) AS last_seen
+					FROM ticket
+					WHERE {sender} = $1 OR {receiver} = $1
+					GROUP BY token
+					ORDER BY token
+					"#
+				),
+				[address.into()],
+			))
+			.await?;
+		summary.tokens = tokens
+			.iter()
+			.map(|row| {
+				Ok(AddressTokenFlow {
+					token: row.try_get("", "token")?,
+					inflow: row.try_get("", "inflow")?,
+					outflow: row.try_get("", "outflow")?,
+					inflow_human: row.try_get("", "inflow_human")?,
+					outflow_human: row.try_get("", "outflow_human")?,
+					incoming_count: row.try_get("", "incoming_count")?,
+					outgoing_count: row.try_get("", "outgoing_count")?,
+					first_seen: row.try_get("", "first_seen")?,
+					last_seen: row.try_get("", "last_seen")?,
+				})
+			})
+			.collect::<Result<_, DbErr>>()?;
+		Ok(summary)
+	}
+
+	pub async fn get_chain_by_id(
+		db: &DbConn,
+		chain_id: String,
+	) -> Result<Option<chain_meta::Model>, DbErr> {
+		ChainMeta::find_by_id(chain_id).one(db).await
+	}
+
+	pub async fn get_chains_by_ids(
+		db: &DbConn,
+		chain_ids: Vec<String>,
+	) -> Result<Vec<chain_meta::Model>, DbErr> {
+		ChainMeta::find()
+			.filter(chain_meta::Column::ChainId.is_in(chain_ids))
+			.all(db)
+			.await
+	}
+
+	pub async fn get_chains(
+		db: &DbConn,
+		after: Option<String>,
+		limit: u64,
+	) -> Result<Vec<chain_meta::Model>, DbErr> {
+		let mut select = ChainMeta::find();
+		if let Some(chain_id) = after {
+			select = select.filter(chain_meta::Column::ChainId.gt(chain_id));
+		}
+		select
+			.order_by_asc(chain_meta::Column::ChainId)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_token_by_id(
+		db: &DbConn,
+		token_id: String,
+	) -> Result<Option<token_meta::Model>, DbErr> {
+		TokenMeta::find_by_id(token_id).one(db).await
+	}
+
+	pub async fn get_tokens_by_ids(
+		db: &DbConn,
+		token_ids: Vec<String>,
+	) -> Result<Vec<token_meta::Model>, DbErr> {
+		TokenMeta::find()
+			.filter(token_meta::Column::TokenId.is_in(token_ids))
+			.all(db)
+			.await
+	}
+
+	pub async fn get_tokens(
+		db: &DbConn,
+		after: Option<String>,
+		limit: u64,
+	) -> Result<Vec<token_meta::Model>, DbErr> {
+		let mut select = TokenMeta::find();
+		if let Some(token_id) = after {
+			select = select.filter(token_meta::Column::TokenId.gt(token_id));
+		}
+		select
+			.order_by_asc(token_meta::Column::TokenId)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_token_ledger_ids(
+		db: &DbConn,
+		chain_id: Option<String>,
+		token_id: Option<String>,
+	) -> Result<Vec<token_ledger_id_on_chain::Model>, DbErr> {
+		let mut select = TokenLedgerIdOnChain::find();
+		if let Some(chain_id) = chain_id {
+			select = select.filter(token_ledger_id_on_chain::Column::ChainId.eq(chain_id));
+		}
+		if let Some(token_id) = token_id {
+			select = select.filter(token_ledger_id_on_chain::Column::TokenId.eq(token_id));
+		}
+		select
+			.order_by_asc(token_ledger_id_on_chain::Column::TokenId)
+			.order_by_asc(token_ledger_id_on_chain::Column::ChainId)
+			.all(db)
+			.await
+	}
+
+	// the ledger ids on any of the chains or of any of the tokens
+	pub async fn get_token_ledger_ids_of(
+		db: &DbConn,
+		chain_ids: Vec<String>,
+		token_ids: Vec<String>,
+	) -> Result<Vec<token_ledger_id_on_chain::Model>, DbErr> {
+		TokenLedgerIdOnChain::find()
+			.filter(
+				Condition::any()
+					.add(token_ledger_id_on_chain::Column::ChainId.is_in(chain_ids))
+					.add(token_ledger_id_on_chain::Column::TokenId.is_in(token_ids)),
+			)
+			.order_by_asc(token_ledger_id_on_chain::Column::TokenId)
+			.order_by_asc(token_ledger_id_on_chain::Column::ChainId)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_token_on_chains(
+		db: &DbConn,
+		chain_id: Option<String>,
+		token_id: Option<String>,
+	) -> Result<Vec<token_on_chain::Model>, DbErr> {
+		let mut select = TokenOnChain::find();
+		if let Some(chain_id) = chain_id {
+			select = select.filter(token_on_chain::Column::ChainId.eq(chain_id));
+		}
+		if let Some(token_id) = token_id {
+			select = select.filter(token_on_chain::Column::TokenId.eq(token_id));
+		}
+		select
+			.order_by_asc(token_on_chain::Column::TokenId)
+			.order_by_asc(token_on_chain::Column::ChainId)
+			.all(db)
+			.await
+	}
+
+	// the balances on any of the chains or of any of the tokens
+	pub async fn get_token_on_chains_of(
+		db: &DbConn,
+		chain_ids: Vec<String>,
+		token_ids: Vec<String>,
+	) -> Result<Vec<token_on_chain::Model>, DbErr> {
+		TokenOnChain::find()
+			.filter(
+				Condition::any()
+					.add(token_on_chain::Column::ChainId.is_in(chain_ids))
+					.add(token_on_chain::Column::TokenId.is_in(token_ids)),
+			)
+			.order_by_asc(token_on_chain::Column::TokenId)
+			.order_by_asc(token_on_chain::Column::ChainId)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_token_volume(
+		db: &DbConn,
+		token_id: String,
+	) -> Result<Option<token_volume::Model>, DbErr> {
+		TokenVolume::find_by_id(token_id).one(db).await
+	}
+
+	pub async fn get_token_volumes_by_ids(
+		db: &DbConn,
+		token_ids: Vec<String>,
+	) -> Result<Vec<token_volume::Model>, DbErr> {
+		TokenVolume::find()
+			.filter(token_volume::Column::TokenId.is_in(token_ids))
+			.all(db)
+			.await
+	}
+
+	pub async fn get_token_volumes(
+		db: &DbConn,
+		after: Option<String>,
+		limit: u64,
+	) -> Result<Vec<token_volume::Model>, DbErr> {
+		let mut select = TokenVolume::find();
+		if let Some(token_id) = after {
+			select = select.filter(token_volume::Column::TokenId.gt(token_id));
+		}
+		select
+			.order_by_asc(token_volume::Column::TokenId)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	// newest day first, `after` is the (date, chain_id) of the last log of the previous page and
+	// `from`..=`to` bound the day
+	pub async fn get_bridge_fee_logs(
+		db: &DbConn,
+		chain_id: Option<String>,
+		from: Option<NaiveDate>,
+		to: Option<NaiveDate>,
+		after: Option<(String, String)>,
+		limit: u64,
+	) -> Result<Vec<bridge_fee_log::Model>, DbErr> {
+		let mut condition = Condition::all();
+		if let Some(chain_id) = chain_id {
+			condition = condition.add(bridge_fee_log::Column::ChainId.eq(chain_id));
+		}
+		if let Some(from) = from {
+			condition = condition.add(bridge_fee_log::Column::Date.gte(from.to_string()));
+		}
+		if let Some(to) = to {
+			condition = condition.add(bridge_fee_log::Column::Date.lte(to.to_string()));
+		}
+		if let Some((date, chain_id)) = after {
+			condition = condition.add(
+				Condition::any()
+					.add(bridge_fee_log::Column::Date.lt(date.clone()))
+					.add(
+						Condition::all()
+							.add(bridge_fee_log::Column::Date.eq(date))
+							.add(bridge_fee_log::Column::ChainId.gt(chain_id)),
+					),
+			);
+		}
+		BridgeFeeLog::find()
+			.filter(condition)
+			.order_by_desc(bridge_fee_log::Column::Date)
+			.order_by_asc(bridge_fee_log::Column::ChainId)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	pub async fn get_rune_terms(
+		db: &DbConn,
+		rune_id: String,
+	) -> Result<Option<rune_terms::Model>, DbErr> {
+		RuneTerms::find_by_id(rune_id).one(db).await
+	}
 	pub async fn get_latest_ticket(db: &DbConn) -> Result<Option<ticket::Model>, DbErr> {
 		Ticket::find()
 			.filter(ticket::Column::TicketSeq.is_not_null())
@@ -68,6 +666,24 @@ impl Query {
 			.await
 	}
 
+	// released tickets with a broadcast tx that is not yet deep enough to be finalized
+	pub async fn get_submitted_tickets(
+		db: &DbConn,
+		dest: String,
+	) -> Result<Vec<ticket::Model>, DbErr> {
+		Ticket::find()
+			.filter(
+				Condition::all()
+					.add(ticket::Column::Status.ne(TicketStatus::Finalized))
+					.add(ticket::Column::Status.ne(TicketStatus::Failed))
+					.add(ticket::Column::Status.ne(TicketStatus::Unknown))
+					.add(ticket::Column::DstChain.eq(dest))
+					.add(ticket::Column::TxHash.is_not_null()),
+			)
+			.all(db)
+			.await
+	}
+
 	pub async fn get_unconfirmed_deleted_tickets(
 		db: &DbConn,
 		dest: String,
@@ -102,7 +718,7 @@ impl Query {
 			.filter(
 				Condition::all()
 					.add(ticket::Column::Action.eq(TxAction::Mint))
-					.add(ticket::Column::Amount.eq(0.to_string())),
+					.add(ticket::Column::Amount.eq(BigDecimal::from(0))),
 			)
 			.all(db)
 			.await
@@ -113,7 +729,7 @@ impl Query {
 			.filter(
 				Condition::all()
 					.add(ticket::Column::Action.eq(TxAction::Mint))
-					.add(ticket::Column::Amount.ne(0.to_string()))
+					.add(ticket::Column::Amount.ne(BigDecimal::from(0)))
 					.add(
 						Condition::any()
 							.add(ticket::Column::IntermediateTxHash.is_null())
@@ -124,38 +740,113 @@ impl Query {
 			.await
 	}
 
-	pub async fn get_null_sender_tickets(db: &DbConn) -> Result<Vec<ticket::Model>, DbErr> {
+	// the newest null-sender tickets from `src_chains` that are due for a sender lookup
+	pub async fn get_null_sender_tickets(
+		db: &DbConn,
+		src_chains: Vec<String>,
+		max_attempts: i32,
+		retry_before: i64,
+		limit: u64,
+	) -> Result<Vec<ticket::Model>, DbErr> {
+		let not_due = sea_query::Query::select()
+			.column(sender_resolution::Column::TicketId)
+			.from(SenderResolution)
+			.cond_where(
+				Condition::any()
+					.add(sender_resolution::Column::Attempts.gte(max_attempts))
+					.add(sender_resolution::Column::LastAttemptAt.gt(retry_before)),
+			)
+			.to_owned();
 		Ticket::find()
-			.filter(ticket::Column::Sender.is_null())
-			.order_by_desc(ticket::Column::TicketSeq)
+			.filter(
+				Condition::all()
+					.add(ticket::Column::Sender.is_null())
+					.add(ticket::Column::SrcChain.is_in(src_chains))
+					.add(ticket::Column::TicketId.not_in_subquery(not_due)),
+			)
+			.order_by_desc(ticket::Column::TicketTime)
+			.limit(limit)
 			.all(db)
 			.await
 	}
 
-	pub async fn get_token_tickets(
+	pub async fn get_webhook_endpoints(db: &DbConn) -> Result<Vec<webhook_endpoint::Model>, DbErr> {
+		WebhookEndpoint::find()
+			.order_by_asc(webhook_endpoint::Column::Id)
+			.all(db)
+			.await
+	}
+
+	// the logged events of the tickets in the order they were published
+	pub async fn get_ticket_transitions(
 		db: &DbConn,
-		token: String,
-	) -> Result<Vec<ticket::Model>, DbErr> {
-		Ticket::find()
-			.filter(Condition::all().add(ticket::Column::Token.eq(token)))
+		ticket_ids: Vec<String>,
+	) -> Result<Vec<ticket_transition::Model>, DbErr> {
+		TicketTransition::find()
+			.filter(ticket_transition::Column::TicketId.is_in(ticket_ids))
+			.order_by_asc(ticket_transition::Column::Id)
 			.all(db)
 			.await
 	}
 
-	pub async fn get_not_null_fee_tickets(
+	pub async fn get_api_keys(db: &DbConn) -> Result<Vec<api_key::Model>, DbErr> {
+		ApiKey::find()
+			.order_by_asc(api_key::Column::Id)
+			.all(db)
+			.await
+	}
+
+	// the daily request counts of the key from `since` on, newest first
+	pub async fn get_active_api_key(
 		db: &DbConn,
-		chain: String,
-		start_at: i64,
-		end_at: i64,
-	) -> Result<Vec<ticket::Model>, DbErr> {
-		Ticket::find()
-			.filter(
-				Condition::all()
-					.add(ticket::Column::SrcChain.eq(chain))
-					.add(ticket::Column::BridgeFee.is_not_null())
-					.add(ticket::Column::TicketTime.gte(start_at))
-					.add(ticket::Column::TicketTime.lte(end_at)),
-			)
+		key_hash: &str,
+	) -> Result<Option<api_key::Model>, DbErr> {
+		ApiKey::find()
+			.filter(api_key::Column::KeyHash.eq(key_hash))
+			.filter(api_key::Column::Active.eq(true))
+			.one(db)
+			.await
+	}
+
+	pub async fn get_api_key_usage(
+		db: &DbConn,
+		api_key_id: i64,
+		since: NaiveDate,
+	) -> Result<Vec<api_key_usage::Model>, DbErr> {
+		ApiKeyUsage::find()
+			.filter(api_key_usage::Column::ApiKeyId.eq(api_key_id))
+			.filter(api_key_usage::Column::Date.gte(since))
+			.order_by_desc(api_key_usage::Column::Date)
+			.all(db)
+			.await
+	}
+
+	// the pending deliveries due by `now` to active endpoints, oldest first
+	pub async fn get_due_webhook_deliveries(
+		db: &DbConn,
+		now: i64,
+		limit: u64,
+	) -> Result<Vec<(webhook_delivery::Model, Option<webhook_endpoint::Model>)>, DbErr> {
+		WebhookDelivery::find()
+			.find_also_related(WebhookEndpoint)
+			.filter(webhook_delivery::Column::Status.eq(webhook::DELIVERY_PENDING))
+			.filter(webhook_delivery::Column::NextAttemptAt.lte(now))
+			.filter(webhook_endpoint::Column::Active.eq(true))
+			.order_by_asc(webhook_delivery::Column::Id)
+			.limit(limit)
+			.all(db)
+			.await
+	}
+
+	// the outbox rows the publisher has not sent yet, in the order they were written
+	pub async fn get_unpublished_outbox(
+		db: &DbConn,
+		limit: u64,
+	) -> Result<Vec<outbox::Model>, DbErr> {
+		Outbox::find()
+			.filter(outbox::Column::PublishedAt.is_null())
+			.order_by_asc(outbox::Column::Id)
+			.limit(limit)
 			.all(db)
 			.await
 	}
@@ -164,81 +855,158 @@ impl Query {
 pub struct Delete;
 
 impl Delete {
-	pub async fn remove_ticket_by_id(
-		db: &DbConn,
+	// deletes the rows of `topic` matching `condition` and writes each of them to the outbox as
+	// it was before the delete, in the same statement
+	async fn remove_recorded<C: ConnectionTrait>(
+		db: &C,
+		topic: &Topic,
+		condition: &str,
+		values: Vec<Value>,
+	) -> Result<DeleteResult, DbErr> {
+		let write = format!(
+			"DELETE FROM {} WHERE {} RETURNING *",
+			topic.table, condition
+		);
+		let rows_affected =
+			Mutation::record_changes(db, topic, change_stream::OP_DELETE, &write, values).await?;
+		Ok(DeleteResult { rows_affected })
+	}
+
+	pub async fn remove_ticket_by_id<C: ConnectionTrait>(
+		db: &C,
 		ticket_id: String,
 	) -> Result<DeleteResult, DbErr> {
-		Ticket::delete_by_id(ticket_id).exec(db).await
+		// the fee log and the route volumes of its day no longer count it
+		Mutation::mark_fee_log_dirty(db, &ticket_id).await?;
+		Mutation::mark_route_volume_dirty(db, &ticket_id).await?;
+		Self::remove_recorded(
+			db,
+			&change_stream::TICKET,
+			"ticket_id = $1",
+			vec![ticket_id.into()],
+		)
+		.await
 	}
 
-	pub async fn remove_chains(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		ChainMeta::delete_many()
-			.filter(Condition::all().add(chain_meta::Column::ChainId.is_not_null()))
+	pub async fn remove_published_outbox(
+		db: &DbConn,
+		published_before: i64,
+	) -> Result<DeleteResult, DbErr> {
+		Outbox::delete_many()
+			.filter(outbox::Column::PublishedAt.lt(published_before))
 			.exec(db)
 			.await
 	}
 
+	pub async fn remove_chains(db: &DbConn) -> Result<DeleteResult, DbErr> {
+		Self::remove_recorded(
+			db,
+			&change_stream::CHAIN_META,
+			"chain_id IS NOT NULL",
+			vec![],
+		)
+		.await
+	}
+
 	pub async fn remove_tokens(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		TokenMeta::delete_many()
-			.filter(Condition::all().add(token_meta::Column::TokenId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::TOKEN_META,
+			"token_id IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
 	pub async fn remove_token_on_chains(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		TokenOnChain::delete_many()
-			.filter(Condition::all().add(token_on_chain::Column::ChainId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::TOKEN_ON_CHAIN,
+			"chain_id IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
 	pub async fn remove_token_ledger_id_on_chain(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		TokenLedgerIdOnChain::delete_many()
-			.filter(Condition::all().add(token_ledger_id_on_chain::Column::ChainId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::TOKEN_LEDGER_ID_ON_CHAIN,
+			"chain_id IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
 	pub async fn remove_tickets(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		Ticket::delete_many()
-			.filter(Condition::all().add(ticket::Column::TicketId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(db, &change_stream::TICKET, "ticket_id IS NOT NULL", vec![]).await
 	}
 
 	pub async fn remove_deleted_mint_tickets(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		DeletedMintTicket::delete_many()
-			.filter(Condition::all().add(deleted_mint_ticket::Column::TicketId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::DELETED_MINT_TICKET,
+			"ticket_id IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
 	pub async fn remove_pending_mint_tickets(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		PendingTicket::delete_many()
-			.filter(Condition::all().add(pending_ticket::Column::TicketIndex.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::PENDING_TICKET,
+			"ticket_index IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
 	pub async fn remove_token_volumes(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		TokenVolume::delete_many()
-			.filter(Condition::all().add(token_volume::Column::TokenId.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::TOKEN_VOLUME,
+			"token_id IS NOT NULL",
+			vec![],
+		)
+		.await
 	}
 
+	// the fee log is an aggregate, its consumers get a refresh of the whole table
 	pub async fn remove_bridge_fee_log(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		BridgeFeeLog::delete_many()
+		let txn = db.begin().await?;
+		let result = BridgeFeeLog::delete_many()
 			.filter(Condition::all().add(bridge_fee_log::Column::ChainId.is_not_null()))
-			.exec(db)
-			.await
+			.exec(&txn)
+			.await?;
+		Mutation::record_refresh(&txn, "bridge_fee_log", String::new(), json!({})).await?;
+		txn.commit().await?;
+		Ok(result)
 	}
 
 	pub async fn remove_launch_pad(db: &DbConn) -> Result<DeleteResult, DbErr> {
-		Launchpad::delete_many()
-			.filter(Condition::all().add(launchpad::Column::Launchpad.is_not_null()))
-			.exec(db)
-			.await
+		Self::remove_recorded(
+			db,
+			&change_stream::LAUNCHPAD,
+			"launchpad IS NOT NULL",
+			vec![],
+		)
+		.await
+	}
+
+	pub async fn remove_sender_resolution(
+		db: &DbConn,
+		ticket_id: String,
+	) -> Result<DeleteResult, DbErr> {
+		SenderResolution::delete_by_id(ticket_id).exec(db).await
+	}
+
+	// the queued deliveries of the endpoint go with it
+	pub async fn remove_webhook_endpoint(
+		db: &DbConn,
+		endpoint_id: i64,
+	) -> Result<DeleteResult, DbErr> {
+		WebhookEndpoint::delete_by_id(endpoint_id).exec(db).await
 	}
 }
 
@@ -257,17 +1025,29 @@ impl Mutation {
 		])
 		.do_nothing()
 		.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = TokenLedgerIdOnChain::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 
 		match insert_result {
 			Ok(ret) => {
 				info!("insert token ledger id result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::TOKEN_LEDGER_ID_ON_CHAIN,
+					change_stream::OP_INSERT,
+					vec![
+						token_ledger_id_on_chain.chain_id.clone().into(),
+						token_ledger_id_on_chain.token_id.clone().into(),
+					],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 		Ok(token_ledger_id_on_chain::Model {
 			..token_ledger_id_on_chain
 		})
@@ -284,24 +1064,36 @@ impl Mutation {
 		])
 		.do_nothing()
 		.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = TokenOnChain::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 
 		match insert_result {
 			Ok(ret) => {
 				info!("insert token on chain result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::TOKEN_ON_CHAIN,
+					change_stream::OP_INSERT,
+					vec![
+						token_on_chain.chain_id.clone().into(),
+						token_on_chain.token_id.clone().into(),
+					],
+				)
+				.await?;
 			}
 			Err(_) => {
 				let _model = Self::update_token_on_chain(
-					db,
+					&txn,
 					token_on_chain.clone(),
 					token_on_chain.clone().amount,
 				)
 				.await?;
 			}
 		}
+		txn.commit().await?;
 		Ok(token_on_chain::Model { ..token_on_chain })
 	}
 
@@ -313,16 +1105,25 @@ impl Mutation {
 		let on_conflict = OnConflict::column(chain_meta::Column::ChainId)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = ChainMeta::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(ret) => {
 				info!("insert chain result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::CHAIN_META,
+					change_stream::OP_INSERT,
+					vec![chain_meta.chain_id.clone().into()],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 		Ok(chain_meta::Model { ..chain_meta })
 	}
 
@@ -334,16 +1135,25 @@ impl Mutation {
 		let on_conflict = OnConflict::column(token_meta::Column::TokenId)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = TokenMeta::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(ret) => {
 				info!("insert token result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::TOKEN_META,
+					change_stream::OP_INSERT,
+					vec![token_meta.token_id.clone().into()],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 
 		Ok(token_meta::Model { ..token_meta })
 	}
@@ -353,20 +1163,35 @@ impl Mutation {
 		let on_conflict = OnConflict::column(ticket::Column::TicketId)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = Ticket::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(_ret) => {
 				// info!("insert ticket result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::TICKET,
+					change_stream::OP_INSERT,
+					vec![ticket.ticket_id.clone().into()],
+				)
+				.await?;
+				Self::save_ticket_links(&txn, &ticket).await?;
+				Self::mark_fee_log_dirty(&txn, &ticket.ticket_id).await?;
+				Self::mark_route_volume_dirty(&txn, &ticket.ticket_id).await?;
+				Self::publish_ticket_event(&txn, &TicketEvent::created(&ticket)).await?;
 			}
 			Err(_) => {
-				if let Some(t) = Query::get_ticket_by_id(db, ticket.clone().ticket_id).await? {
-					if t.ticket_seq == None && t.status == TicketStatus::Finalized {
-						let _model = Self::update_ticket(
-							db,
-							ticket.clone(),
+				let existing = Ticket::find_by_id(ticket.ticket_id.clone())
+					.one(&txn)
+					.await?;
+				if let Some(t) = existing {
+					let t = if t.ticket_seq == None && t.status == TicketStatus::Finalized {
+						let model = Self::update_ticket(
+							&txn,
+							t,
 							None,
 							None,
 							None,
@@ -376,32 +1201,121 @@ impl Mutation {
 						)
 						.await?;
 						// info!("update ticket seq result {:?}", model.ticket_seq);
-					}
+						model
+					} else {
+						t
+					};
+					// the tickets it links to may have been synced since it was first saved
+					Self::save_ticket_links(&txn, &t).await?;
 				}
 			}
 		}
+		txn.commit().await?;
 
 		Ok(ticket::Model { ..ticket })
 	}
 
-	pub async fn save_deleted_mint_ticket(
-		db: &DbConn,
+	pub async fn save_ticket_link<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
+		ticket_link: ticket_link::Model,
+	) -> Result<(), DbErr> {
+		let key = vec![
+			ticket_link.parent_id.clone().into(),
+			ticket_link.child_id.clone().into(),
+		];
+		let active_model: ticket_link::ActiveModel = ticket_link.into();
+		let on_conflict =
+			OnConflict::columns([ticket_link::Column::ParentId, ticket_link::Column::ChildId])
+				.do_nothing()
+				.to_owned();
+		let txn = db.begin().await?;
+		match TicketLink::insert(active_model)
+			.on_conflict(on_conflict)
+			.exec(&txn)
+			.await
+		{
+			Ok(_) => {
+				Self::record_change(
+					&txn,
+					&change_stream::TICKET_LINK,
+					change_stream::OP_INSERT,
+					key,
+				)
+				.await?
+			}
+			Err(DbErr::RecordNotInserted) => {}
+			Err(e) => return Err(e),
+		}
+		txn.commit().await
+	}
+
+	// links the ticket to the ticket its release tx created and to the tickets whose
+	// release tx created it
+	pub async fn save_ticket_links<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
+		ticket: &ticket::Model,
+	) -> Result<(), DbErr> {
+		if let Some(link) = ticket.child_link() {
+			let child = Ticket::find_by_id(link.child_id.clone()).one(db).await?;
+			let deleted_child = DeletedMintTicket::find_by_id(link.child_id.clone())
+				.one(db)
+				.await?;
+			if child.is_some() || deleted_child.is_some() {
+				Self::save_ticket_link(db, link).await?;
+			}
+		}
+
+		let mut parents = Ticket::find()
+			.filter(
+				Condition::any()
+					.add(ticket::Column::TxHash.eq(ticket.ticket_id.clone()))
+					.add(ticket::Column::IntermediateTxHash.eq(ticket.ticket_id.clone())),
+			)
+			.all(db)
+			.await?;
+		parents.extend(
+			DeletedMintTicket::find()
+				.filter(deleted_mint_ticket::Column::TxHash.eq(ticket.ticket_id.clone()))
+				.all(db)
+				.await?
+				.into_iter()
+				.map(ticket::Model::from_deleted_ticket),
+		);
+		for link in parents.iter().filter_map(ticket::Model::child_link) {
+			if link.child_id == ticket.ticket_id {
+				Self::save_ticket_link(db, link).await?;
+			}
+		}
+		Ok(())
+	}
+
+	pub async fn save_deleted_mint_ticket<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
 		deleted_ticket: deleted_mint_ticket::Model,
 	) -> Result<deleted_mint_ticket::Model, DbErr> {
 		let active_model: deleted_mint_ticket::ActiveModel = deleted_ticket.clone().into();
 		let on_conflict = OnConflict::column(deleted_mint_ticket::Column::TicketId)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = DeletedMintTicket::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(_ret) => {
 				// info!("insert deleted mint ticket result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::DELETED_MINT_TICKET,
+					change_stream::OP_INSERT,
+					vec![deleted_ticket.ticket_id.clone().into()],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 
 		Ok(deleted_mint_ticket::Model { ..deleted_ticket })
 	}
@@ -414,16 +1328,25 @@ impl Mutation {
 		let on_conflict = OnConflict::column(pending_ticket::Column::TicketIndex)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = PendingTicket::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(ret) => {
 				info!("insert pending ticket index result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::PENDING_TICKET,
+					change_stream::OP_INSERT,
+					vec![pending_ticket.ticket_index.into()],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 
 		Ok(pending_ticket::Model { ..pending_ticket })
 	}
@@ -436,17 +1359,25 @@ impl Mutation {
 		let on_conflict = OnConflict::column(token_volume::Column::TokenId)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = TokenVolume::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(ret) => {
 				info!("insert token volume result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::TOKEN_VOLUME,
+					change_stream::OP_INSERT,
+					vec![token_volume.token_id.clone().into()],
+				)
+				.await?;
 			}
 			Err(_) => {
 				let _model = Self::update_token_volume(
-					db,
+					&txn,
 					token_volume.clone(),
 					token_volume.clone().ticket_count,
 					token_volume.clone().historical_volume,
@@ -454,31 +1385,382 @@ impl Mutation {
 				.await?;
 			}
 		}
+		txn.commit().await?;
 		Ok(token_volume::Model { ..token_volume })
 	}
 
-	pub async fn save_bridge_fee_log(
+	// marks the (src chain, utc day) of a fee paying ticket stale, in the transaction that writes
+	// or removes the ticket. the no-op update locks a pending mark so the refresh that drains it
+	// waits for the ticket to commit
+	pub async fn mark_fee_log_dirty<C: ConnectionTrait>(
+		db: &C,
+		ticket_id: &str,
+	) -> Result<(), DbErr> {
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			r#"
+			INSERT INTO bridge_fee_log_dirty (chain_id, date)
+			SELECT src_chain, (to_timestamp(ticket_time / 1e9) AT TIME ZONE 'UTC')::date
+			FROM ticket
+			WHERE ticket_id = $1 AND bridge_fee IS NOT NULL
+			ON CONFLICT (chain_id, date) DO UPDATE SET chain_id = excluded.chain_id
+			"#,
+			[ticket_id.into()],
+		))
+		.await?;
+		Ok(())
+	}
+
+	// marks the utc day of a ticket stale for the route volumes, in the transaction that writes,
+	// settles or removes the ticket, locking a pending mark the same way as the fee log
+	pub async fn mark_route_volume_dirty<C: ConnectionTrait>(
+		db: &C,
+		ticket_id: &str,
+	) -> Result<(), DbErr> {
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			r#"
+			INSERT INTO route_volume_dirty (date)
+			SELECT (to_timestamp(ticket_time / 1e9) AT TIME ZONE 'UTC')::date
+			FROM ticket
+			WHERE ticket_id = $1
+			ON CONFLICT (date) DO UPDATE SET date = excluded.date
+			"#,
+			[ticket_id.into()],
+		))
+		.await?;
+		Ok(())
+	}
+
+	// drains up to `limit` stale (src chain, utc day) pairs and rebuilds their fee log in one
+	// transaction, `bridge_fee_log` keeps the total of the fees paid in the chain's fee token and
+	// `bridge_fee_log_detail` splits every fee by destination chain and fee token. the days of a
+	// chain without a `chain_meta` row yet stay marked until it is synced. returns the number of
+	// pairs rebuilt
+	pub async fn refresh_bridge_fee_logs(db: &DbConn, limit: u64) -> Result<usize, DbErr> {
+		let txn = db.begin().await?;
+		let days = txn
+			.query_all(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				r#"
+				DELETE FROM bridge_fee_log_dirty
+				WHERE (chain_id, date) IN (
+					SELECT d.chain_id, d.date FROM bridge_fee_log_dirty d
+					JOIN chain_meta c ON c.chain_id = d.chain_id
+					ORDER BY d.date, d.chain_id
+					LIMIT $1
+					FOR UPDATE OF d
+				)
+				RETURNING chain_id, date
+				"#,
+				[limit.into()],
+			))
+			.await?
+			.iter()
+			.map(|row| Ok((row.try_get("", "chain_id")?, row.try_get("", "date")?)))
+			.collect::<Result<Vec<(String, NaiveDate)>, DbErr>>()?;
+		let count = days.len();
+		for (chain_id, day) in days {
+			let start_nanos = day
+				.and_hms_opt(0, 0, 0)
+				.and_then(|start| start.and_utc().timestamp_nanos_opt())
+				.unwrap_or_default();
+			let end_nanos = start_nanos + 24 * 60 * 60 * 1_000_000_000;
+			let date = day.to_string();
+			for sql in [
+				"DELETE FROM bridge_fee_log WHERE chain_id = $1 AND date = $2",
+				"DELETE FROM bridge_fee_log_detail WHERE chain_id = $1 AND date = $2",
+				"DELETE FROM bridge_fee_log_ticket WHERE chain_id = $1 AND date = $2",
+			] {
+				txn.execute(Statement::from_sql_and_values(
+					DbBackend::Postgres,
+					sql,
+					[chain_id.as_str().into(), date.as_str().into()],
+				))
+				.await?;
+			}
+			for sql in [
+				r#"
+				INSERT INTO bridge_fee_log (chain_id, date, fee_token_id, amount, total_ticket)
+				SELECT c.chain_id, $2, coalesce(c.fee_token, ''), sum(t.bridge_fee), count(*)
+				FROM ticket t
+				JOIN chain_meta c ON c.chain_id = t.src_chain
+				WHERE t.src_chain = $1 AND t.bridge_fee IS NOT NULL
+					AND t.ticket_time >= $3 AND t.ticket_time < $4
+					AND coalesce(t.fee_token, c.fee_token, '') = coalesce(c.fee_token, '')
+				GROUP BY c.chain_id, c.fee_token
+				"#,
+				r#"
+				INSERT INTO bridge_fee_log_detail
+					(chain_id, date, dst_chain, fee_token_id, amount, total_ticket)
+				SELECT c.chain_id, $2, t.dst_chain, coalesce(t.fee_token, c.fee_token, ''),
+					sum(t.bridge_fee), count(*)
+				FROM ticket t
+				JOIN chain_meta c ON c.chain_id = t.src_chain
+				WHERE t.src_chain = $1 AND t.bridge_fee IS NOT NULL
+					AND t.ticket_time >= $3 AND t.ticket_time < $4
+				GROUP BY c.chain_id, t.dst_chain, coalesce(t.fee_token, c.fee_token, '')
+				"#,
+				r#"
+				INSERT INTO bridge_fee_log_ticket (ticket_id, chain_id, date)
+				SELECT t.ticket_id, c.chain_id, $2
+				FROM ticket t
+				JOIN chain_meta c ON c.chain_id = t.src_chain
+				WHERE t.src_chain = $1 AND t.bridge_fee IS NOT NULL
+					AND t.ticket_time >= $3 AND t.ticket_time < $4
+				"#,
+			] {
+				txn.execute(Statement::from_sql_and_values(
+					DbBackend::Postgres,
+					sql,
+					[
+						chain_id.as_str().into(),
+						date.as_str().into(),
+						start_nanos.into(),
+						end_nanos.into(),
+					],
+				))
+				.await?;
+			}
+			Self::record_refresh(
+				&txn,
+				"bridge_fee_log",
+				format!("{}:{}", chain_id, date),
+				json!({ "chain_id": chain_id, "date": date }),
+			)
+			.await?;
+		}
+		txn.commit().await?;
+		Ok(count)
+	}
+
+	pub async fn save_rune_terms(
 		db: &DbConn,
-		bridge_fee_log: bridge_fee_log::Model,
-	) -> Result<bridge_fee_log::Model, DbErr> {
-		let active_model: bridge_fee_log::ActiveModel = bridge_fee_log.clone().into();
-		let on_conflict = OnConflict::columns([
-			bridge_fee_log::Column::ChainId,
-			bridge_fee_log::Column::Date,
-		])
-		.do_nothing()
-		.to_owned();
-		let insert_result = BridgeFeeLog::insert(active_model.clone())
+		rune_terms: rune_terms::Model,
+	) -> Result<rune_terms::Model, DbErr> {
+		let active_model: rune_terms::ActiveModel = rune_terms.clone().into();
+		let on_conflict = OnConflict::column(rune_terms::Column::RuneId)
+			.update_column(rune_terms::Column::TermsAmount)
+			.to_owned();
+		let txn = db.begin().await?;
+		RuneTerms::insert(active_model)
 			.on_conflict(on_conflict)
-			.exec(db)
-			.await;
-		match insert_result {
-			Ok(ret) => {
-				info!("insert bridge fee log result : {:?}", ret);
+			.exec(&txn)
+			.await?;
+		Self::record_change(
+			&txn,
+			&change_stream::RUNE_TERMS,
+			change_stream::OP_UPSERT,
+			vec![rune_terms.rune_id.clone().into()],
+		)
+		.await?;
+		txn.commit().await?;
+		Ok(rune_terms)
+	}
+
+	// fills the human readable amounts from the decimals in token_meta, tickets are only
+	// filled once while the small aggregate tables are recomputed whenever they drift, the
+	// scale comes from a `1e-<decimals>` literal as `power()` rounds to 16 decimal places
+	pub async fn normalize_amounts(db: &DbConn) -> Result<(), DbErr> {
+		let txn = db.begin().await?;
+		for (topic, write) in [
+			(
+				&change_stream::TICKET,
+				r#"
+				UPDATE ticket t SET amount_human = t.amount * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = t.token AND t.amount IS NOT NULL AND t.amount_human IS NULL
+				RETURNING t.*
+				"#,
+			),
+			(
+				&change_stream::TOKEN_VOLUME,
+				r#"
+				UPDATE token_volume v
+				SET historical_volume_human = v.historical_volume * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = v.token_id AND v.historical_volume_human
+					IS DISTINCT FROM v.historical_volume * ('1e-' || m.decimals)::numeric
+				RETURNING v.*
+				"#,
+			),
+			(
+				&change_stream::TOKEN_ON_CHAIN,
+				r#"
+				UPDATE token_on_chain c SET amount_human = c.amount * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = c.token_id
+					AND c.amount_human IS DISTINCT FROM c.amount * ('1e-' || m.decimals)::numeric
+				RETURNING c.*
+				"#,
+			),
+		] {
+			Self::record_changes(&txn, topic, change_stream::OP_UPDATE, write, vec![]).await?;
+		}
+
+		// the aggregates keyed by day are refreshed as a whole
+		for (table, sql) in [
+			(
+				"route_volume",
+				r#"
+				UPDATE route_volume v SET volume_human = v.volume * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = v.token
+					AND v.volume_human IS DISTINCT FROM v.volume * ('1e-' || m.decimals)::numeric
+				"#,
+			),
+			(
+				"bridge_fee_log_detail",
+				r#"
+				UPDATE bridge_fee_log_detail l
+				SET amount_human = l.amount * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = l.fee_token_id
+					AND l.amount_human IS DISTINCT FROM l.amount * ('1e-' || m.decimals)::numeric
+				"#,
+			),
+			(
+				"bridge_fee_log",
+				r#"
+				UPDATE bridge_fee_log l SET amount_human = l.amount * ('1e-' || m.decimals)::numeric
+				FROM token_meta m
+				WHERE m.token_id = l.fee_token_id
+					AND l.amount_human IS DISTINCT FROM l.amount * ('1e-' || m.decimals)::numeric
+				"#,
+			),
+		] {
+			if txn.execute_unprepared(sql).await?.rows_affected() > 0 {
+				Self::record_refresh(&txn, table, String::new(), json!({})).await?;
 			}
-			Err(_) => {}
 		}
-		Ok(bridge_fee_log::Model { ..bridge_fee_log })
+		txn.commit().await
+	}
+
+	// recomputes the route volumes and daily stats of the days from `since` on, all days when
+	// `None`
+	pub async fn refresh_route_volumes(db: &DbConn, since: Option<NaiveDate>) -> Result<(), DbErr> {
+		// all days are the days since the unix epoch
+		let since = since.unwrap_or_default();
+		let since_nanos = since
+			.and_hms_opt(0, 0, 0)
+			.and_then(|since| since.and_utc().timestamp_nanos_opt())
+			.unwrap_or_default();
+
+		let txn = db.begin().await?;
+		txn.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			"DELETE FROM route_volume WHERE date >= $1",
+			[since.into()],
+		))
+		.await?;
+		Self::insert_route_volumes(&txn, since_nanos, i64::MAX).await?;
+		Self::record_refresh(
+			&txn,
+			"route_volume",
+			since.to_string(),
+			json!({ "since": since }),
+		)
+		.await?;
+		txn.commit().await
+	}
+
+	// drains up to `limit` stale utc days and rebuilds their route volumes in one transaction,
+	// returns the number of days rebuilt
+	pub async fn refresh_dirty_route_volumes(db: &DbConn, limit: u64) -> Result<usize, DbErr> {
+		let txn = db.begin().await?;
+		let days = txn
+			.query_all(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				r#"
+				DELETE FROM route_volume_dirty
+				WHERE date IN (
+					SELECT date FROM route_volume_dirty
+					ORDER BY date
+					LIMIT $1
+					FOR UPDATE
+				)
+				RETURNING date
+				"#,
+				[limit.into()],
+			))
+			.await?
+			.iter()
+			.map(|row| row.try_get("", "date"))
+			.collect::<Result<Vec<NaiveDate>, DbErr>>()?;
+		let count = days.len();
+		for day in days {
+			let start_nanos = day
+				.and_hms_opt(0, 0, 0)
+				.and_then(|start| start.and_utc().timestamp_nanos_opt())
+				.unwrap_or_default();
+			let end_nanos = start_nanos + 24 * 60 * 60 * 1_000_000_000;
+			txn.execute(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				"DELETE FROM route_volume WHERE date = $1",
+				[day.into()],
+			))
+			.await?;
+			Self::insert_route_volumes(&txn, start_nanos, end_nanos).await?;
+			Self::record_refresh(
+				&txn,
+				"route_volume",
+				day.to_string(),
+				json!({ "date": day }),
+			)
+			.await?;
+		}
+		txn.commit().await?;
+		Ok(count)
+	}
+
+	// the route volumes of the tickets in the ticket time range, the days are assumed empty
+	async fn insert_route_volumes<C: ConnectionTrait>(
+		db: &C,
+		start_nanos: i64,
+		end_nanos: i64,
+	) -> Result<(), DbErr> {
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			r#"
+			INSERT INTO route_volume (src_chain, dst_chain, token, date, ticket_count, volume,
+				fee_total, unique_senders, unique_receivers)
+			SELECT src_chain, dst_chain, token,
+				(to_timestamp(ticket_time / 1e9) AT TIME ZONE 'UTC')::date AS day,
+				count(*), coalesce(sum(amount), 0), coalesce(sum(bridge_fee), 0),
+				count(DISTINCT sender), count(DISTINCT receiver)
+			FROM ticket
+			WHERE ticket_time >= $1 AND ticket_time < $2
+			GROUP BY src_chain, dst_chain, token, day
+			"#,
+			[start_nanos.into(), end_nanos.into()],
+		))
+		.await?;
+		Ok(())
+	}
+
+	// the totals of every token, tokens without tickets get a zero volume, only the totals
+	// that changed are written
+	pub async fn refresh_token_volumes(db: &DbConn) -> Result<(), DbErr> {
+		Self::record_changes(
+			db,
+			&change_stream::TOKEN_VOLUME,
+			change_stream::OP_UPSERT,
+			r#"
+			INSERT INTO token_volume (token_id, ticket_count, historical_volume)
+			SELECT m.token_id, coalesce(sum(v.ticket_count), 0)::text, coalesce(sum(v.volume), 0)
+			FROM token_meta m
+			LEFT JOIN route_volume v ON v.token = m.token_id
+			GROUP BY m.token_id
+			ON CONFLICT (token_id) DO UPDATE
+			SET ticket_count = excluded.ticket_count, historical_volume = excluded.historical_volume
+			WHERE (token_volume.ticket_count, token_volume.historical_volume)
+				IS DISTINCT FROM (excluded.ticket_count, excluded.historical_volume)
+			RETURNING *
+			"#,
+			vec![],
+		)
+		.await?;
+		Ok(())
 	}
 
 	pub async fn save_launch_pad(
@@ -489,30 +1771,48 @@ impl Mutation {
 		let on_conflict = OnConflict::column(launchpad::Column::Launchpad)
 			.do_nothing()
 			.to_owned();
+		let txn = db.begin().await?;
 		let insert_result = Launchpad::insert(active_model.clone())
 			.on_conflict(on_conflict)
-			.exec(db)
+			.exec(&txn)
 			.await;
 		match insert_result {
 			Ok(ret) => {
 				info!("insert launchpad result : {:?}", ret);
+				Self::record_change(
+					&txn,
+					&change_stream::LAUNCHPAD,
+					change_stream::OP_INSERT,
+					vec![launchpad.launchpad.clone().into()],
+				)
+				.await?;
 			}
 			Err(_) => {}
 		}
+		txn.commit().await?;
 		Ok(launchpad::Model { ..launchpad })
 	}
 
-	pub async fn update_ticket(
-		db: &DbConn,
+	pub async fn update_ticket<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
 		ticket: ticket::Model,
 		status: Option<TicketStatus>,
 		tx_hash: Option<Option<String>>,
-		amount: Option<String>,
+		amount: Option<BigDecimal>,
 		sender: Option<Option<String>>,
 		intermediate_tx_hash: Option<Option<String>>,
 		seq: Option<Option<i64>>,
 	) -> Result<ticket::Model, DbErr> {
-		let mut active_model: ticket::ActiveModel = ticket.into();
+		let relinked =
+			matches!(tx_hash, Some(Some(_))) || matches!(intermediate_tx_hash, Some(Some(_)));
+		// the route volumes sum the amounts and count the senders
+		let recounted = amount.is_some() || sender.is_some();
+		let txn = db.begin().await?;
+		// the passed ticket may be stale, the event is the change to the row as it is
+		let before = Query::get_ticket_by_id_for_update(&txn, ticket.ticket_id.clone())
+			.await?
+			.ok_or_else(|| DbErr::RecordNotFound(ticket.ticket_id))?;
+		let mut active_model: ticket::ActiveModel = before.clone().into();
 		if let Some(_status) = status {
 			active_model.status = Set(_status);
 		}
@@ -520,7 +1820,8 @@ impl Mutation {
 			active_model.tx_hash = Set(_tx_hash);
 		}
 		if let Some(_amount) = amount {
-			active_model.amount = Set(_amount);
+			active_model.amount = Set(Some(_amount));
+			active_model.amount_human = Set(None);
 		}
 		if let Some(_sender) = sender {
 			active_model.sender = Set(_sender);
@@ -531,7 +1832,18 @@ impl Mutation {
 		if let Some(_seq) = seq {
 			active_model.ticket_seq = Set(_seq);
 		}
-		let ticket = active_model.update(db).await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_ticket_update(&txn, &ticket).await?;
+		if relinked {
+			Self::save_ticket_links(&txn, &ticket).await?;
+		}
+		if recounted {
+			Self::mark_route_volume_dirty(&txn, &ticket.ticket_id).await?;
+		}
+		if let Some(event) = TicketEvent::changed(&before, &ticket) {
+			Self::publish_ticket_event(&txn, &event).await?;
+		}
+		txn.commit().await?;
 		Ok(ticket)
 	}
 
@@ -540,46 +1852,688 @@ impl Mutation {
 		ticket: ticket::Model,
 		tx_hash: Option<String>,
 	) -> Result<ticket::Model, DbErr> {
-		let mut active_model: ticket::ActiveModel = ticket.into();
+		let relinked = tx_hash.is_some();
+		let txn = db.begin().await?;
+		let before = Query::get_ticket_by_id_for_update(&txn, ticket.ticket_id.clone())
+			.await?
+			.ok_or_else(|| DbErr::RecordNotFound(ticket.ticket_id))?;
+		let mut active_model: ticket::ActiveModel = before.clone().into();
 		active_model.tx_hash = Set(tx_hash);
-		let ticket = active_model.update(db).await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_ticket_update(&txn, &ticket).await?;
+		if relinked {
+			Self::save_ticket_links(&txn, &ticket).await?;
+		}
+		if let Some(event) = TicketEvent::changed(&before, &ticket) {
+			Self::publish_ticket_event(&txn, &event).await?;
+		}
+		txn.commit().await?;
+		Ok(ticket)
+	}
+
+	// writes the row of `topic` with the given key values to the outbox, as it is in the
+	// transaction of the change. the bookkeeping of the sync itself, the cursors, the webhooks
+	// and the sender lookups, is not part of the change stream
+	pub async fn record_change<C: ConnectionTrait>(
+		db: &C,
+		topic: &Topic,
+		op: &str,
+		key: Vec<Value>,
+	) -> Result<(), DbErr> {
+		let condition = topic
+			.keys
+			.iter()
+			.enumerate()
+			.map(|(i, column)| format!("t.{} = ${}", column, i + 4))
+			.collect::<Vec<_>>()
+			.join(" AND ");
+		let sql = format!(
+			r#"
+			INSERT INTO outbox (topic, key, op, payload, created_at)
+			SELECT $1, concat_ws(':', {}), $2, row_to_json(t), $3
+			FROM {} t
+			WHERE {}
+			"#,
+			Self::outbox_key(topic, "t"),
+			topic.table,
+			condition
+		);
+		let mut values: Vec<Value> = vec![
+			topic.table.into(),
+			op.into(),
+			Utc::now().timestamp_nanos_opt().unwrap_or_default().into(),
+		];
+		values.extend(key);
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			sql,
+			values,
+		))
+		.await?;
+		Ok(())
+	}
+
+	pub async fn record_ticket_update<C: ConnectionTrait>(
+		db: &C,
+		ticket: &ticket::Model,
+	) -> Result<(), DbErr> {
+		Self::record_change(
+			db,
+			&change_stream::TICKET,
+			change_stream::OP_UPDATE,
+			vec![ticket.ticket_id.clone().into()],
+		)
+		.await
+	}
+
+	// runs `write`, an insert or update of the rows of `topic` returning them, and writes the
+	// returned rows to the outbox in the same statement, returns the number of rows written
+	pub async fn record_changes<C: ConnectionTrait>(
+		db: &C,
+		topic: &Topic,
+		op: &str,
+		write: &str,
+		values: Vec<Value>,
+	) -> Result<u64, DbErr> {
+		let sql = format!(
+			r#"
+			WITH changed AS ({})
+			INSERT INTO outbox (topic, key, op, payload, created_at)
+			SELECT '{}', concat_ws(':', {}), '{}', row_to_json(changed), {}
+			FROM changed
+			"#,
+			write,
+			topic.table,
+			Self::outbox_key(topic, "changed"),
+			op,
+			Utc::now().timestamp_nanos_opt().unwrap_or_default()
+		);
+		let result = db
+			.execute(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				sql,
+				values,
+			))
+			.await?;
+		Ok(result.rows_affected())
+	}
+
+	fn outbox_key(topic: &Topic, alias: &str) -> String {
+		topic
+			.keys
+			.iter()
+			.map(|column| format!("{}.{}", alias, column))
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+
+	// an aggregate recomputed as a whole, `key` names the recomputed range
+	pub async fn record_refresh<C: ConnectionTrait>(
+		db: &C,
+		table: &str,
+		key: String,
+		range: serde_json::Value,
+	) -> Result<(), DbErr> {
+		outbox::ActiveModel {
+			topic: Set(table.to_owned()),
+			key: Set(key),
+			op: Set(change_stream::OP_REFRESH.to_owned()),
+			payload: Set(range),
+			created_at: Set(Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+			..Default::default()
+		}
+		.insert(db)
+		.await?;
+		Ok(())
+	}
+
+	pub async fn mark_outbox_published(
+		db: &DbConn,
+		ids: Vec<i64>,
+		published_at: i64,
+	) -> Result<(), DbErr> {
+		if ids.is_empty() {
+			return Ok(());
+		}
+		Outbox::update_many()
+			.col_expr(
+				outbox::Column::PublishedAt,
+				sea_query::Expr::value(published_at),
+			)
+			.filter(outbox::Column::Id.is_in(ids))
+			.exec(db)
+			.await?;
+		Ok(())
+	}
+
+	// notifies the listeners, queues the webhooks and logs the event for the ticket timeline, all
+	// of which land when the surrounding transaction, if any, commits
+	pub async fn publish_ticket_event<C: ConnectionTrait>(
+		db: &C,
+		event: &TicketEvent,
+	) -> Result<(), DbErr> {
+		Self::save_ticket_transition(db, event).await?;
+		let payload = serde_json::to_string(event).map_err(|e| DbErr::Custom(e.to_string()))?;
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			"SELECT pg_notify($1, $2)",
+			[TICKET_EVENTS_CHANNEL.into(), payload.into()],
+		))
+		.await?;
+		if let Some(event_type) = webhook::webhook_event(event) {
+			Self::enqueue_webhook_deliveries(db, event_type, event).await?;
+		}
+		Ok(())
+	}
+
+	pub async fn save_ticket_transition<C: ConnectionTrait>(
+		db: &C,
+		event: &TicketEvent,
+	) -> Result<(), DbErr> {
+		ticket_transition::ActiveModel {
+			ticket_id: Set(event.ticket_id.clone()),
+			kind: Set(event.kind.as_str().to_owned()),
+			status: Set(event.status.clone()),
+			previous_status: Set(event.previous_status.clone()),
+			tx_hash: Set(event.tx_hash.clone()),
+			intermediate_tx_hash: Set(event.intermediate_tx_hash.clone()),
+			// the event time is in millis, the log in nanoseconds like the ticket times
+			occurred_at: Set(event.occurred_at.saturating_mul(1_000_000)),
+			..Default::default()
+		}
+		.insert(db)
+		.await?;
+		Ok(())
+	}
+
+	// one pending delivery per active endpoint whose filters match the ticket, a hex address
+	// matches in any case as in `is_hex_address`
+	pub async fn enqueue_webhook_deliveries<C: ConnectionTrait>(
+		db: &C,
+		event_type: &str,
+		event: &TicketEvent,
+	) -> Result<(), DbErr> {
+		let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+		db.execute(Statement::from_sql_and_values(
+			DbBackend::Postgres,
+			r#"
+			INSERT INTO webhook_delivery
+				(endpoint_id, event, ticket_id, payload, status, attempts, next_attempt_at, created_at)
+			SELECT id, $1, $2, $3, $4, 0, $5, $5
+			FROM webhook_endpoint
+			WHERE active
+				AND (chain IS NULL OR chain = $6 OR chain = $7)
+				AND (token IS NULL OR token = $8)
+				AND (address IS NULL OR address = $9 OR address = $10
+					OR (address ~ '^(0[xX])?[0-9a-fA-F]+$'
+						AND lower(address) IN (lower($9), lower($10))))
+			"#,
+			[
+				event_type.into(),
+				event.ticket_id.clone().into(),
+				webhook::payload(event_type, event).into(),
+				webhook::DELIVERY_PENDING.into(),
+				now.into(),
+				event.src_chain.clone().into(),
+				event.dst_chain.clone().into(),
+				event.token.clone().into(),
+				event.sender.clone().into(),
+				event.receiver.clone().into(),
+			],
+		))
+		.await?;
+		Ok(())
+	}
+
+	pub async fn save_webhook_endpoint(
+		db: &DbConn,
+		url: String,
+		secret: String,
+		chain: Option<String>,
+		token: Option<String>,
+		address: Option<String>,
+	) -> Result<webhook_endpoint::Model, DbErr> {
+		webhook_endpoint::ActiveModel {
+			url: Set(url),
+			secret: Set(secret),
+			chain: Set(chain),
+			token: Set(token),
+			address: Set(address),
+			active: Set(true),
+			created_at: Set(Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+			..Default::default()
+		}
+		.insert(db)
+		.await
+	}
+
+	pub async fn mark_webhook_delivered(
+		db: &DbConn,
+		delivery_id: i64,
+		delivered_at: i64,
+	) -> Result<(), DbErr> {
+		WebhookDelivery::update_many()
+			.col_expr(
+				webhook_delivery::Column::Status,
+				sea_query::Expr::value(webhook::DELIVERY_DELIVERED),
+			)
+			.col_expr(
+				webhook_delivery::Column::Attempts,
+				sea_query::Expr::col(webhook_delivery::Column::Attempts).add(1),
+			)
+			.col_expr(
+				webhook_delivery::Column::DeliveredAt,
+				sea_query::Expr::value(delivered_at),
+			)
+			.filter(webhook_delivery::Column::Id.eq(delivery_id))
+			.exec(db)
+			.await?;
+		Ok(())
+	}
+
+	// without a next attempt the delivery is dead-lettered
+	pub async fn save_webhook_delivery_failure(
+		db: &DbConn,
+		delivery_id: i64,
+		attempts: i32,
+		error: String,
+		next_attempt_at: Option<i64>,
+	) -> Result<(), DbErr> {
+		let status = match next_attempt_at {
+			Some(_) => webhook::DELIVERY_PENDING,
+			None => webhook::DELIVERY_DEAD,
+		};
+		let mut update = WebhookDelivery::update_many()
+			.col_expr(
+				webhook_delivery::Column::Status,
+				sea_query::Expr::value(status),
+			)
+			.col_expr(
+				webhook_delivery::Column::Attempts,
+				sea_query::Expr::value(attempts),
+			)
+			.col_expr(
+				webhook_delivery::Column::LastError,
+				sea_query::Expr::value(error),
+			);
+		if let Some(next_attempt_at) = next_attempt_at {
+			update = update.col_expr(
+				webhook_delivery::Column::NextAttemptAt,
+				sea_query::Expr::value(next_attempt_at),
+			);
+		}
+		update
+			.filter(webhook_delivery::Column::Id.eq(delivery_id))
+			.exec(db)
+			.await?;
+		Ok(())
+	}
+
+	// queues a delivery again from its first attempt, e.g. a dead-lettered one
+	pub async fn retry_webhook_delivery(db: &DbConn, delivery_id: i64) -> Result<u64, DbErr> {
+		let result = WebhookDelivery::update_many()
+			.col_expr(
+				webhook_delivery::Column::Status,
+				sea_query::Expr::value(webhook::DELIVERY_PENDING),
+			)
+			.col_expr(
+				webhook_delivery::Column::Attempts,
+				sea_query::Expr::value(0),
+			)
+			.col_expr(
+				webhook_delivery::Column::NextAttemptAt,
+				sea_query::Expr::value(Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+			)
+			.filter(webhook_delivery::Column::Id.eq(delivery_id))
+			.exec(db)
+			.await?;
+		Ok(result.rows_affected)
+	}
+
+	pub async fn save_api_key(
+		db: &DbConn,
+		name: String,
+		prefix: String,
+		key_hash: String,
+		rate_limit: i32,
+		daily_quota: Option<i64>,
+	) -> Result<api_key::Model, DbErr> {
+		api_key::ActiveModel {
+			name: Set(name),
+			prefix: Set(prefix),
+			key_hash: Set(key_hash),
+			rate_limit: Set(rate_limit),
+			daily_quota: Set(daily_quota),
+			active: Set(true),
+			created_at: Set(Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+			..Default::default()
+		}
+		.insert(db)
+		.await
+	}
+
+	// unset limits are left as they are, `Some(None)` lifts the daily quota
+	pub async fn update_api_key_limits(
+		db: &DbConn,
+		api_key_id: i64,
+		rate_limit: Option<i32>,
+		daily_quota: Option<Option<i64>>,
+	) -> Result<u64, DbErr> {
+		if rate_limit.is_none() && daily_quota.is_none() {
+			return Ok(0);
+		}
+		let mut update = ApiKey::update_many();
+		if let Some(rate_limit) = rate_limit {
+			update = update.col_expr(
+				api_key::Column::RateLimit,
+				sea_query::Expr::value(rate_limit),
+			);
+		}
+		if let Some(daily_quota) = daily_quota {
+			update = update.col_expr(
+				api_key::Column::DailyQuota,
+				sea_query::Expr::value(daily_quota),
+			);
+		}
+		let result = update
+			.filter(api_key::Column::Id.eq(api_key_id))
+			.exec(db)
+			.await?;
+		Ok(result.rows_affected)
+	}
+
+	// a revoked key is kept with its usage but no longer authenticates
+	pub async fn revoke_api_key(db: &DbConn, api_key_id: i64) -> Result<u64, DbErr> {
+		let result = ApiKey::update_many()
+			.col_expr(api_key::Column::Active, sea_query::Expr::value(false))
+			.filter(api_key::Column::Id.eq(api_key_id))
+			.exec(db)
+			.await?;
+		Ok(result.rows_affected)
+	}
+
+	// counts a request made with the key on `date` unless the key used up its daily quota, in
+	// one statement as every keyed request goes through it. returns the requests of the day
+	// with this one, None when the quota is used up and the request was not counted
+	pub async fn count_api_key_request(
+		db: &DbConn,
+		api_key_id: i64,
+		daily_quota: Option<i64>,
+		date: NaiveDate,
+	) -> Result<Option<i64>, DbErr> {
+		let row = db
+			.query_one(Statement::from_sql_and_values(
+				DbBackend::Postgres,
+				r#"
+				INSERT INTO api_key_usage (api_key_id, date, request_count)
+				SELECT $1, $2, 1 WHERE $3::bigint IS NULL OR $3 > 0
+				ON CONFLICT (api_key_id, date)
+				DO UPDATE SET request_count = api_key_usage.request_count + 1
+				WHERE $3::bigint IS NULL OR api_key_usage.request_count < $3
+				RETURNING request_count
+				"#,
+				[api_key_id.into(), date.into(), daily_quota.into()],
+			))
+			.await?;
+		row.map(|row| row.try_get("", "request_count")).transpose()
+	}
+
+	// counts a failed sender lookup of the ticket, returns the attempts made so far
+	pub async fn save_sender_resolution_failure(
+		db: &DbConn,
+		ticket_id: String,
+		error: String,
+	) -> Result<i32, DbErr> {
+		let active_model = sender_resolution::ActiveModel {
+			ticket_id: Set(ticket_id),
+			attempts: Set(1),
+			last_error: Set(Some(error)),
+			last_attempt_at: Set(Utc::now().timestamp_nanos_opt().unwrap_or_default()),
+		};
+		let on_conflict = OnConflict::column(sender_resolution::Column::TicketId)
+			.value(
+				sender_resolution::Column::Attempts,
+				sea_query::Expr::col((SenderResolution, sender_resolution::Column::Attempts))
+					.add(1),
+			)
+			.update_columns([
+				sender_resolution::Column::LastError,
+				sender_resolution::Column::LastAttemptAt,
+			])
+			.to_owned();
+		let resolution = SenderResolution::insert(active_model)
+			.on_conflict(on_conflict)
+			.exec_with_returning(db)
+			.await?;
+		Ok(resolution.attempts)
+	}
+
+	pub async fn update_ticket_confirmations(
+		db: &DbConn,
+		ticket: ticket::Model,
+		confirmations: i32,
+		block_height: Option<i64>,
+	) -> Result<ticket::Model, DbErr> {
+		let mut active_model: ticket::ActiveModel = ticket.into();
+		active_model.confirmations = Set(Some(confirmations));
+		active_model.block_height = Set(block_height);
+		let txn = db.begin().await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_ticket_update(&txn, &ticket).await?;
+		txn.commit().await?;
+		Ok(ticket)
+	}
+
+	// records the in-flight sub status and the first time it was seen
+	pub async fn update_ticket_sub_status(
+		db: &DbConn,
+		ticket: ticket::Model,
+		sub_status: &str,
+	) -> Result<ticket::Model, DbErr> {
+		if ticket.sub_status.as_deref() == Some(sub_status) {
+			return Ok(ticket);
+		}
+		let first_seen = enter_sub_status(
+			ticket.sub_status_first_seen.clone(),
+			sub_status,
+			Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+		);
+
+		let mut active_model: ticket::ActiveModel = ticket.into();
+		active_model.sub_status = Set(Some(sub_status.to_owned()));
+		active_model.sub_status_first_seen = Set(Some(first_seen));
+		let txn = db.begin().await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_ticket_update(&txn, &ticket).await?;
+		txn.commit().await?;
 		Ok(ticket)
 	}
 
-	pub async fn update_token_volume(
+	// the stages seen stay in `sub_status_first_seen`
+	pub async fn clear_ticket_sub_status(
 		db: &DbConn,
+		ticket: ticket::Model,
+	) -> Result<ticket::Model, DbErr> {
+		if ticket.sub_status.is_none() {
+			return Ok(ticket);
+		}
+		let mut active_model: ticket::ActiveModel = ticket.into();
+		active_model.sub_status = Set(None);
+		let txn = db.begin().await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_ticket_update(&txn, &ticket).await?;
+		txn.commit().await?;
+		Ok(ticket)
+	}
+
+	pub async fn update_deleted_ticket_sub_status(
+		db: &DbConn,
+		ticket: deleted_mint_ticket::Model,
+		sub_status: &str,
+	) -> Result<deleted_mint_ticket::Model, DbErr> {
+		if ticket.sub_status.as_deref() == Some(sub_status) {
+			return Ok(ticket);
+		}
+		let first_seen = enter_sub_status(
+			ticket.sub_status_first_seen.clone(),
+			sub_status,
+			Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+		);
+
+		let mut active_model: deleted_mint_ticket::ActiveModel = ticket.into();
+		active_model.sub_status = Set(Some(sub_status.to_owned()));
+		active_model.sub_status_first_seen = Set(Some(first_seen));
+		let txn = db.begin().await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_change(
+			&txn,
+			&change_stream::DELETED_MINT_TICKET,
+			change_stream::OP_UPDATE,
+			vec![ticket.ticket_id.clone().into()],
+		)
+		.await?;
+		txn.commit().await?;
+		Ok(ticket)
+	}
+
+	pub async fn clear_deleted_ticket_sub_status(
+		db: &DbConn,
+		ticket: deleted_mint_ticket::Model,
+	) -> Result<deleted_mint_ticket::Model, DbErr> {
+		if ticket.sub_status.is_none() {
+			return Ok(ticket);
+		}
+		let mut active_model: deleted_mint_ticket::ActiveModel = ticket.into();
+		active_model.sub_status = Set(None);
+		let txn = db.begin().await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_change(
+			&txn,
+			&change_stream::DELETED_MINT_TICKET,
+			change_stream::OP_UPDATE,
+			vec![ticket.ticket_id.clone().into()],
+		)
+		.await?;
+		txn.commit().await?;
+		Ok(ticket)
+	}
+
+	pub async fn update_token_volume<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
 		token_volume: token_volume::Model,
 		len: String,
-		volume: String,
+		volume: BigDecimal,
 	) -> Result<token_volume::Model, DbErr> {
 		let mut active_model: token_volume::ActiveModel = token_volume.into();
 		active_model.ticket_count = Set(len);
 		active_model.historical_volume = Set(volume);
-		let token_volume = active_model.update(db).await?;
+		let txn = db.begin().await?;
+		let token_volume = active_model.update(&txn).await?;
+		Self::record_change(
+			&txn,
+			&change_stream::TOKEN_VOLUME,
+			change_stream::OP_UPDATE,
+			vec![token_volume.token_id.clone().into()],
+		)
+		.await?;
+		txn.commit().await?;
 		Ok(token_volume)
 	}
 
-	pub async fn update_token_on_chain(
-		db: &DbConn,
+	pub async fn update_token_on_chain<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
 		token_on_chain: token_on_chain::Model,
-		amount: String,
+		amount: BigDecimal,
 	) -> Result<token_on_chain::Model, DbErr> {
 		let mut active_model: token_on_chain::ActiveModel = token_on_chain.into();
 		active_model.amount = Set(amount);
-		let token_on_chain = active_model.update(db).await?;
+		let txn = db.begin().await?;
+		let token_on_chain = active_model.update(&txn).await?;
+		Self::record_change(
+			&txn,
+			&change_stream::TOKEN_ON_CHAIN,
+			change_stream::OP_UPDATE,
+			vec![
+				token_on_chain.chain_id.clone().into(),
+				token_on_chain.token_id.clone().into(),
+			],
+		)
+		.await?;
+		txn.commit().await?;
 		Ok(token_on_chain)
 	}
 
-	pub async fn update_deleted_ticket_statu_and_tx_hash(
-		db: &DbConn,
+	pub async fn update_deleted_ticket_statu_and_tx_hash<C: ConnectionTrait + TransactionTrait>(
+		db: &C,
 		ticket: deleted_mint_ticket::Model,
 		tx_hash: Option<String>,
 		status: TicketStatus,
 	) -> Result<deleted_mint_ticket::Model, DbErr> {
-		let mut active_model: deleted_mint_ticket::ActiveModel = ticket.into();
+		let txn = db.begin().await?;
+		let before = Query::get_deleted_ticket_by_id_for_update(&txn, ticket.ticket_id.clone())
+			.await?
+			.ok_or_else(|| DbErr::RecordNotFound(ticket.ticket_id))?;
+		let mut active_model: deleted_mint_ticket::ActiveModel = before.clone().into();
 		active_model.tx_hash = Set(tx_hash);
 		active_model.status = Set(status);
-		let ticket = active_model.update(db).await?;
+		let ticket = active_model.update(&txn).await?;
+		Self::record_change(
+			&txn,
+			&change_stream::DELETED_MINT_TICKET,
+			change_stream::OP_UPDATE,
+			vec![ticket.ticket_id.clone().into()],
+		)
+		.await?;
+		// the archived transfer is still the ticket its subscribers follow
+		if let Some(event) = TicketEvent::archived_changed(&before, &ticket) {
+			Self::publish_ticket_event(&txn, &event).await?;
+		}
+		txn.commit().await?;
 		Ok(ticket)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_hex_addresses_ignore_the_case() {
+		assert!(is_hex_address("0xAbCd01"));
+		assert!(is_hex_address("abcd01"));
+		assert!(!is_hex_address("0x"));
+		assert!(!is_hex_address(
+			"bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"
+		));
+		assert!(!is_hex_address(
+			"7EcDhSYGxXyscszYEp35KHN8vvw3svAuLKTzXwCFLtV"
+		));
+	}
+
+	#[test]
+	fn a_stage_entered_again_starts_over() {
+		let first_seen = enter_sub_status(None, "NotFound", 1);
+		let first_seen = enter_sub_status(Some(first_seen), "AwaitingConfirmations", 2);
+		assert_eq!(
+			enter_sub_status(Some(first_seen), "NotFound", 3),
+			json!({ "NotFound": 3, "AwaitingConfirmations": 2 })
+		);
+	}
+
+	#[test]
+	fn hex_hashes_are_searched_in_every_spelling() {
+		assert_eq!(
+			hash_variants(" 0xABcd "),
+			vec!["0xABcd".to_owned(), "0xabcd".to_owned(), "abcd".to_owned()]
+		);
+		assert_eq!(
+			hash_variants("abcd"),
+			vec!["abcd".to_owned(), "0xabcd".to_owned()]
+		);
+		// ticket ids and icp ledger blocks are looked up as they are
+		assert_eq!(
+			hash_variants("ryjl3-tyaaa-aaaaa-aaaba-cai_42"),
+			vec!["ryjl3-tyaaa-aaaaa-aaaba-cai_42".to_owned()]
+		);
+	}
+}
@@ -0,0 +1,106 @@
+//! A minimal local HTTP server standing in for the external APIs in tests.
+
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Clone, Debug)]
+pub struct StubRequest {
+	pub method: String,
+	pub path: String,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+impl StubRequest {
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers
+			.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	pub fn json(&self) -> serde_json::Value {
+		serde_json::from_slice(&self.body).unwrap_or_default()
+	}
+}
+
+// serves every request with `handler`, which returns the status code and the json body
+pub async fn spawn<F>(handler: F) -> String
+where
+	F: Fn(StubRequest) -> (u16, String) + Send + Sync + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0")
+		.await
+		.expect("bind stub server");
+	let address = listener.local_addr().expect("stub server address");
+	let handler = Arc::new(handler);
+
+	tokio::spawn(async move {
+		while let Ok((stream, _)) = listener.accept().await {
+			let handler = handler.clone();
+			tokio::spawn(async move {
+				let _ = serve(stream, handler.as_ref()).await;
+			});
+		}
+	});
+
+	format!("http://{}", address)
+}
+
+async fn serve<F>(mut stream: TcpStream, handler: &F) -> std::io::Result<()>
+where
+	F: Fn(StubRequest) -> (u16, String),
+{
+	let mut buffer = Vec::new();
+	let mut chunk = [0u8; 4096];
+	let header_end = loop {
+		let read = stream.read(&mut chunk).await?;
+		if read == 0 {
+			return Ok(());
+		}
+		buffer.extend_from_slice(&chunk[..read]);
+		if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+			break position + 4;
+		}
+	};
+
+	let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+	let mut lines = head.split("\r\n");
+	let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+	let method = request_line.next().unwrap_or_default().to_owned();
+	let path = request_line.next().unwrap_or_default().to_owned();
+	let headers: Vec<(String, String)> = lines
+		.filter_map(|line| line.split_once(':'))
+		.map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+		.collect();
+	let content_length = headers
+		.iter()
+		.find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+		.and_then(|(_, value)| value.parse::<usize>().ok())
+		.unwrap_or(0);
+
+	let mut body = buffer[header_end..].to_vec();
+	while body.len() < content_length {
+		let read = stream.read(&mut chunk).await?;
+		if read == 0 {
+			break;
+		}
+		body.extend_from_slice(&chunk[..read]);
+	}
+
+	let (status, response) = handler(StubRequest {
+		method,
+		path,
+		headers,
+		body,
+	});
+	let reply = format!(
+		"HTTP/1.1 {} STUB\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+		status,
+		response.len(),
+		response
+	);
+	stream.write_all(reply.as_bytes()).await?;
+	stream.shutdown().await
+}
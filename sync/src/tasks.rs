@@ -1,14 +1,17 @@
-use crate::customs::UPDATE_DELETED_MINT_TICKET_SYNC_INTERVAL;
+use crate::change_stream::{self, OutboxSink, OUTBOX_PUBLISH_INTERVAL};
+use crate::customs::{BTC_CONFIRMATION_SYNC_INTERVAL, UPDATE_DELETED_MINT_TICKET_SYNC_INTERVAL};
 use crate::hub::{
-	CHAIN_SYNC_INTERVAL, FEE_LOG_SYNC_INTERVAL, TICKET_SYNC_INTERVAL, TOKEN_ON_CHAIN_SYNC_INTERVAL,
-	TOKEN_SYNC_INTERVAL, TOKEN_VOLUME_SYNC_INTERVAL,
+	AMOUNT_NORMALIZE_INTERVAL, CHAIN_SYNC_INTERVAL, FEE_LOG_SYNC_INTERVAL, TICKET_SYNC_INTERVAL,
+	TOKEN_ON_CHAIN_SYNC_INTERVAL, TOKEN_SYNC_INTERVAL, TOKEN_VOLUME_SYNC_INTERVAL,
 };
 use crate::routes::TOKEN_LEDGER_ID_ON_CHAIN_SYNC_INTERVAL;
+use crate::webhook::WEBHOOK_DELIVERY_INTERVAL;
 use crate::Delete;
 use crate::{
 	customs::{bitcoin, doge, sicp},
 	evm, hub,
 	routes::{cosmwasm, icp, solana},
+	sender, webhook,
 };
 use futures::Future;
 use log::error;
@@ -48,7 +51,8 @@ where
 	})
 }
 
-pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
+pub async fn execute_sync_tasks(db_conn: Arc<DbConn>, outbox_sink: OutboxSink) {
+	// the wiped rows reach the change stream as deletes before the sync loads them again
 	let remove_database = async {
 		let _ = Delete::remove_chains(&db_conn).await;
 		let _ = Delete::remove_tokens(&db_conn).await;
@@ -111,6 +115,13 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 		|db_conn| async move { sicp::sync_all_icrc_token_canister_id_from_sicp(&db_conn).await },
 	);
 
+	let sync_all_token_ledger_id_from_brc20 = spawn_named_sync_task(
+		"sync_all_brc20_token_ledger_id_on_chain",
+		db_conn.clone(),
+		TOKEN_LEDGER_ID_ON_CHAIN_SYNC_INTERVAL,
+		|db_conn| async move { bitcoin::sync_all_brc20_token_ledger_id_on_chain(&db_conn).await },
+	);
+
 	let sync_all_token_ledger_id_from_cosmwasm = spawn_named_sync_task(
 		"sync_all_cosmwasm_token_ledger_id_on_chain",
 		db_conn.clone(),
@@ -160,6 +171,13 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 		|db_conn| async move { bitcoin::sync_all_ticket_status_from_bitcoin(&db_conn).await },
 	);
 
+	let track_bitcoin_confirmations = spawn_named_sync_task(
+		"track_bitcoin_confirmations",
+		db_conn.clone(),
+		BTC_CONFIRMATION_SYNC_INTERVAL,
+		|db_conn| async move { bitcoin::track_bitcoin_confirmations(&db_conn).await },
+	);
+
 	let sync_ticket_status_from_sicp = spawn_named_sync_task(
 		"sync_ticket_status_from_sicp",
 		db_conn.clone(),
@@ -195,11 +213,11 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 	// 	|db_conn| async move { ton::sync_all_tickets_status_from_ton_route(&db_conn).await },
 	// );
 
-	let update_sender_tickets_from_hub = spawn_named_sync_task(
-		"update_sender",
+	let backfill_senders = spawn_named_sync_task(
+		"backfill_senders",
 		db_conn.clone(),
 		TICKET_SYNC_INTERVAL,
-		|db_conn| async move { hub::update_sender(&db_conn).await },
+		|db_conn| async move { sender::backfill_senders(&db_conn).await },
 	);
 
 	let update_mint_tickets_from_btc = spawn_named_sync_task(
@@ -230,6 +248,31 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 		|db_conn| async move { hub::sync_bridge_fee_log(&db_conn).await },
 	);
 
+	let normalize_amounts_from_hub = spawn_named_sync_task(
+		"normalize_amounts",
+		db_conn.clone(),
+		AMOUNT_NORMALIZE_INTERVAL,
+		|db_conn| async move { hub::normalize_amounts(&db_conn).await },
+	);
+
+	let deliver_webhooks = spawn_named_sync_task(
+		"deliver_webhooks",
+		db_conn.clone(),
+		WEBHOOK_DELIVERY_INTERVAL,
+		|db_conn| async move { webhook::deliver_webhooks(&db_conn).await },
+	);
+
+	let outbox_sink = Arc::new(outbox_sink);
+	let publish_outbox = spawn_named_sync_task(
+		"publish_outbox",
+		db_conn.clone(),
+		OUTBOX_PUBLISH_INTERVAL,
+		move |db_conn| {
+			let outbox_sink = outbox_sink.clone();
+			async move { change_stream::publish_outbox(&db_conn, &outbox_sink).await }
+		},
+	);
+
 	let _ = tokio::join!(
 		remove_database,
 		// sync_ticket_status_from_sui,
@@ -239,6 +282,7 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 		sync_all_token_ledger_id_on_chain_from_icp,
 		sync_all_token_ledger_id_from_evm,
 		sync_all_token_canister_id_from_sicp,
+		sync_all_token_ledger_id_from_brc20,
 		sync_all_token_ledger_id_from_cosmwasm,
 		// sync_all_token_ledger_id_from_ton,
 		sync_tokens_on_chains_from_hub,
@@ -246,15 +290,19 @@ pub async fn execute_sync_tasks(db_conn: Arc<DbConn>) {
 		sync_ticket_status_from_solana_route,
 		// sync_ticket_status_from_solana_custom,
 		sync_ticket_status_from_bitcoin,
+		track_bitcoin_confirmations,
 		sync_ticket_status_from_sicp,
 		sync_ticket_status_from_eicp,
 		sync_all_tickets_status_from_evm,
 		sync_all_tickets_status_from_cosmwasm,
 		// sync_all_tickets_status_from_ton,
-		update_sender_tickets_from_hub,
+		backfill_senders,
 		update_mint_tickets_from_btc,
 		update_deleted_mint_tickets_from_btc,
 		update_total_volumes_from_hub,
 		update_sync_bridge_fee_log_hub,
+		normalize_amounts_from_hub,
+		deliver_webhooks,
+		publish_outbox,
 	);
 }
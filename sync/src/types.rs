@@ -1,9 +1,10 @@
 use crate::entity;
+use crate::hub::memo::DecodedMemo;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use candid::CandidType;
 use entity::{
-	bridge_fee_log, chain_meta, deleted_mint_ticket, launchpad, pending_ticket,
-	sea_orm_active_enums, ticket, token_ledger_id_on_chain, token_meta, token_on_chain,
-	token_volume,
+	chain_meta, deleted_mint_ticket, launchpad, pending_ticket, rune_terms, sea_orm_active_enums,
+	ticket, ticket_link, token_ledger_id_on_chain, token_meta, token_on_chain, token_volume,
 };
 use ic_cdk::api::call::RejectionCode;
 use serde::{Deserialize, Serialize};
@@ -125,6 +126,15 @@ impl From<token_meta::Model> for TokenMeta {
 	}
 }
 
+impl rune_terms::Model {
+	pub fn new(rune_id: String, terms_amount: i64) -> Self {
+		Self {
+			rune_id,
+			terms_amount,
+		}
+	}
+}
+
 impl launchpad::Model {
 	pub fn new(launchpad: String, cainister_id: String) -> Self {
 		Self {
@@ -157,16 +167,23 @@ impl From<OmnityTokenOnChain> for token_on_chain::Model {
 		token_on_chain::Model {
 			chain_id: token_on_chain.chain_id,
 			token_id: token_on_chain.token_id,
-			amount: token_on_chain.amount.to_string(),
+			amount: BigDecimal::from(token_on_chain.amount),
+			amount_human: None,
 		}
 	}
 }
 impl From<token_on_chain::Model> for OmnityTokenOnChain {
 	fn from(model: token_on_chain::Model) -> Self {
+		// numeric(78, 0) holds a u256, the amounts beyond u128 saturate
+		let amount = match model.amount.to_u128() {
+			Some(amount) => amount,
+			None if model.amount > BigDecimal::from(0) => u128::MAX,
+			None => 0,
+		};
 		OmnityTokenOnChain {
 			chain_id: model.chain_id,
 			token_id: model.token_id,
-			amount: model.amount.parse::<u128>().unwrap(),
+			amount,
 		}
 	}
 }
@@ -270,11 +287,12 @@ impl core::fmt::Display for Token {
 }
 
 impl token_volume::Model {
-	pub fn new(token_id: String, len: usize, volume: u128) -> Self {
+	pub fn new(token_id: String, len: usize, volume: BigDecimal) -> Self {
 		Self {
 			token_id: token_id,
 			ticket_count: len.to_string(),
-			historical_volume: volume.to_string(),
+			historical_volume: volume,
+			historical_volume_human: None,
 		}
 	}
 }
@@ -368,14 +386,52 @@ pub struct OmnityTicket {
 	pub memo: Option<Vec<u8>>,
 }
 
+// raw amounts are unsigned integers in the smallest unit of the token
+pub fn parse_amount(raw: &str) -> Option<BigDecimal> {
+	let raw = raw.trim().trim_matches('"');
+	if raw.is_empty() || !raw.bytes().all(|byte| byte.is_ascii_digit()) {
+		return None;
+	}
+	raw.parse().ok()
+}
+
+// the amounts of a ticket, values that do not parse are kept in `parse_error` instead of
+// being counted as zero
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TicketAmounts {
+	pub amount: Option<BigDecimal>,
+	pub bridge_fee: Option<BigDecimal>,
+	pub parse_error: Option<String>,
+}
+
+impl TicketAmounts {
+	pub fn parse(amount: &str, bridge_fee: Option<&str>) -> Self {
+		let mut errors = Vec::new();
+		let mut parse = |name: &str, raw: &str| {
+			let parsed = parse_amount(raw);
+			if parsed.is_none() {
+				errors.push(format!("invalid {}: {}", name, raw));
+			}
+			parsed
+		};
+		let amount = parse("amount", amount);
+		let bridge_fee = bridge_fee.and_then(|bridge_fee| parse("bridge_fee", bridge_fee));
+		Self {
+			amount,
+			bridge_fee,
+			parse_error: (!errors.is_empty()).then(|| errors.join("; ")),
+		}
+	}
+}
+
 impl ticket::Model {
 	pub fn from_omnity_ticket(
 		seq: u64,
 		omnity_ticket: OmnityTicket,
-		updated_memo: Option<String>,
-		bridge_fee: Option<String>,
+		memo: DecodedMemo,
 		date: String,
 	) -> Self {
+		let amounts = TicketAmounts::parse(&omnity_ticket.amount, memo.bridge_fee.as_deref());
 		Self {
 			ticket_id: omnity_ticket.ticket_id.to_owned(),
 			ticket_seq: Some(seq as i64),
@@ -385,24 +441,34 @@ impl ticket::Model {
 			dst_chain: omnity_ticket.dst_chain.to_owned(),
 			action: omnity_ticket.action.into(),
 			token: omnity_ticket.token.to_owned(),
-			amount: omnity_ticket.amount.to_owned(),
+			amount: amounts.amount,
 			sender: omnity_ticket.sender.to_owned(),
 			receiver: omnity_ticket.receiver.to_owned(),
-			memo: updated_memo,
+			memo: memo.memo,
 			status: TicketStatus::WaitingForConfirmByDest.into(),
 			tx_hash: None,
 			intermediate_tx_hash: None,
-			bridge_fee,
+			bridge_fee: amounts.bridge_fee,
 			date,
+			sub_status: None,
+			sub_status_first_seen: None,
+			confirmations: None,
+			block_height: None,
+			memo_hex: memo.memo_hex,
+			memo_json: memo.memo_json,
+			referral_code: memo.referral_code,
+			fee_token: memo.fee_token,
+			parse_error: amounts.parse_error,
+			amount_human: None,
 		}
 	}
 
 	pub fn from_omnity_pending_ticket(
 		pending_ticket: OmnityTicket,
-		updated_memo: Option<String>,
-		bridge_fee: Option<String>,
+		memo: DecodedMemo,
 		date: String,
 	) -> Self {
+		let amounts = TicketAmounts::parse(&pending_ticket.amount, memo.bridge_fee.as_deref());
 		Self {
 			ticket_id: pending_ticket.ticket_id.to_owned(),
 			ticket_seq: None,
@@ -412,19 +478,30 @@ impl ticket::Model {
 			dst_chain: pending_ticket.dst_chain.to_owned(),
 			action: pending_ticket.action.into(),
 			token: pending_ticket.token.to_owned(),
-			amount: pending_ticket.amount.to_owned(),
+			amount: amounts.amount,
 			sender: pending_ticket.sender.to_owned(),
 			receiver: pending_ticket.receiver.to_owned(),
-			memo: updated_memo,
+			memo: memo.memo,
 			status: TicketStatus::Pending.into(),
 			tx_hash: None,
 			intermediate_tx_hash: None,
-			bridge_fee,
+			bridge_fee: amounts.bridge_fee,
 			date,
+			sub_status: None,
+			sub_status_first_seen: None,
+			confirmations: None,
+			block_height: None,
+			memo_hex: memo.memo_hex,
+			memo_json: memo.memo_json,
+			referral_code: memo.referral_code,
+			fee_token: memo.fee_token,
+			parse_error: amounts.parse_error,
+			amount_human: None,
 		}
 	}
 
 	pub fn from_deleted_ticket(deleted_ticket: deleted_mint_ticket::Model) -> Self {
+		let amounts = TicketAmounts::parse(&deleted_ticket.amount, None);
 		Self {
 			ticket_id: deleted_ticket.ticket_id,
 			ticket_seq: deleted_ticket.ticket_seq,
@@ -434,7 +511,7 @@ impl ticket::Model {
 			dst_chain: deleted_ticket.dst_chain,
 			action: deleted_ticket.action,
 			token: deleted_ticket.token,
-			amount: deleted_ticket.amount,
+			amount: amounts.amount,
 			sender: deleted_ticket.sender,
 			receiver: deleted_ticket.receiver,
 			memo: deleted_ticket.memo,
@@ -443,6 +520,42 @@ impl ticket::Model {
 			intermediate_tx_hash: None,
 			bridge_fee: None,
 			date: deleted_ticket.date,
+			sub_status: deleted_ticket.sub_status,
+			sub_status_first_seen: deleted_ticket.sub_status_first_seen,
+			confirmations: None,
+			block_height: None,
+			memo_hex: None,
+			memo_json: None,
+			referral_code: None,
+			fee_token: None,
+			parse_error: amounts.parse_error,
+			amount_human: None,
+		}
+	}
+
+	// the edge to the ticket created by the release tx of this ticket, a mint ticket
+	// links to the transfer in its hashes and hands its release over to that transfer
+	pub fn child_link(&self) -> Option<ticket_link::Model> {
+		let (child_id, kind) = match self.action {
+			sea_orm_active_enums::TxAction::Mint => (
+				self.intermediate_tx_hash
+					.as_ref()
+					.or(self.tx_hash.as_ref())?,
+				ticket_link::TicketLinkKind::Mint,
+			),
+			_ => (self.tx_hash.as_ref()?, ticket_link::TicketLinkKind::Release),
+		};
+		(child_id != &self.ticket_id)
+			.then(|| ticket_link::Model::new(self.ticket_id.clone(), child_id.clone(), kind))
+	}
+}
+
+impl ticket_link::Model {
+	pub fn new(parent_id: String, child_id: String, kind: ticket_link::TicketLinkKind) -> Self {
+		Self {
+			parent_id,
+			child_id,
+			kind,
 		}
 	}
 }
@@ -451,7 +564,7 @@ impl core::fmt::Display for ticket::Model {
 	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
 		write!(
             f,
-            "\nticket id:{} \nticket seq:{:?} \nticket type:{:?} \ncreated time:{} \nsrc chain:{} \ndst_chain:{} \naction:{:?} \ntoken:{} \namount:{} \nsender:{:?} \nrecevier:{} \nmemo:{:?} \nstatus:{:?} \ntx hash:{:?} \nintermediate tx hash:{:?}",
+            "\nticket id:{} \nticket seq:{:?} \nticket type:{:?} \ncreated time:{} \nsrc chain:{} \ndst_chain:{} \naction:{:?} \ntoken:{} \namount:{:?} \nsender:{:?} \nrecevier:{} \nmemo:{:?} \nstatus:{:?} \ntx hash:{:?} \nintermediate tx hash:{:?}",
             self.ticket_id,
             self.ticket_seq,
             self.ticket_type,
@@ -495,13 +608,18 @@ impl From<ticket::Model> for deleted_mint_ticket::Model {
 			dst_chain: ticket.dst_chain,
 			action: ticket.action.into(),
 			token: ticket.token,
-			amount: ticket.amount,
+			amount: ticket
+				.amount
+				.map(|amount| amount.with_scale(0).to_string())
+				.unwrap_or_default(),
 			sender: ticket.sender,
 			receiver: ticket.receiver,
 			memo: ticket.memo,
 			status: ticket.status.into(),
 			tx_hash: ticket.tx_hash,
 			date: ticket.date,
+			sub_status: ticket.sub_status,
+			sub_status_first_seen: ticket.sub_status_first_seen,
 		}
 	}
 }
@@ -614,26 +732,6 @@ impl From<sea_orm_active_enums::TxAction> for TxAction {
 	}
 }
 
-impl bridge_fee_log::Model {
-	pub fn new(
-		chain_id: String,
-		date: String,
-		fee_token_id: String,
-		amount: String,
-		total_ticket: i32,
-		seqs: String,
-	) -> Self {
-		Self {
-			chain_id,
-			date,
-			fee_token_id,
-			amount,
-			total_ticket,
-			seqs,
-		}
-	}
-}
-
 #[derive(CandidType, Deserialize, Debug, Error)]
 pub enum Error {
 	#[error("The chain(`{0}`) already exists")]
@@ -734,3 +832,49 @@ impl Reason {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_raw_amounts() {
+		assert_eq!(parse_amount("1000"), Some(BigDecimal::from(1000)));
+		assert_eq!(parse_amount(" \"20000\" "), Some(BigDecimal::from(20000)));
+		assert_eq!(
+			parse_amount("340282366920938463463374607431768211456"),
+			"340282366920938463463374607431768211456".parse().ok()
+		);
+		assert_eq!(parse_amount(""), None);
+		assert_eq!(parse_amount("-1"), None);
+		assert_eq!(parse_amount("1.5"), None);
+		assert_eq!(parse_amount("1e18"), None);
+	}
+
+	#[test]
+	fn flags_ticket_amounts_that_do_not_parse() {
+		assert_eq!(
+			TicketAmounts::parse("1000", Some("\"10\"")),
+			TicketAmounts {
+				amount: Some(BigDecimal::from(1000)),
+				bridge_fee: Some(BigDecimal::from(10)),
+				parse_error: None,
+			}
+		);
+		assert_eq!(
+			TicketAmounts::parse("abc", Some("{}")),
+			TicketAmounts {
+				amount: None,
+				bridge_fee: None,
+				parse_error: Some("invalid amount: abc; invalid bridge_fee: {}".to_owned()),
+			}
+		);
+		assert_eq!(
+			TicketAmounts::parse("7", None),
+			TicketAmounts {
+				amount: Some(BigDecimal::from(7)),
+				..Default::default()
+			}
+		);
+	}
+}
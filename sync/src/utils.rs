@@ -83,6 +83,11 @@ pub async fn create_omnity_canister(canister: &str) -> Result<Principal, Box<dyn
 	Ok(Principal::from_text(canister_id)?)
 }
 
+// optional customs and routes are only polled when their canister id is set
+pub fn is_canister_configured(canister: &str) -> bool {
+	std::env::var(canister).map_or(false, |id| !id.is_empty())
+}
+
 pub(crate) fn is_decommissioned_canister(canister_id: &Principal) -> bool {
 	DECOMMISSIONED_CANISTER_IDS.contains(&canister_id.to_text().as_str())
 }
@@ -0,0 +1,229 @@
+//! Notifies the registered partner endpoints of ticket lifecycle events.
+//!
+//! Every event is queued per matching endpoint in the same write as the ticket change, the
+//! delivery task then posts the queued rows with a signed body and retries the failed ones
+//! with an exponential backoff until they are dead-lettered.
+
+use crate::entity::{webhook_delivery, webhook_endpoint};
+use crate::events::{TicketEvent, TicketEventKind};
+use crate::service::{Mutation, Query};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::info;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use ring::hmac;
+use sea_orm::DbConn;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::time::Duration;
+
+pub const WEBHOOK_DELIVERY_INTERVAL: u64 = 10;
+pub const WEBHOOK_BATCH_SIZE: u64 = 100;
+// about a day of retries with the backoff below
+pub const WEBHOOK_MAX_ATTEMPTS: i32 = 12;
+const WEBHOOK_RETRY_BASE: i64 = 30;
+const WEBHOOK_RETRY_MAX: i64 = 6 * 3600;
+
+pub const DELIVERY_PENDING: &str = "pending";
+pub const DELIVERY_DELIVERED: &str = "delivered";
+pub const DELIVERY_DEAD: &str = "dead";
+
+pub const SIGNATURE_HEADER: &str = "X-Omnity-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Omnity-Timestamp";
+pub const EVENT_HEADER: &str = "X-Omnity-Event";
+pub const DELIVERY_HEADER: &str = "X-Omnity-Delivery";
+
+static HTTP_CLIENT: Lazy<reqwest::Result<Client>> =
+	Lazy::new(|| Client::builder().timeout(Duration::from_secs(10)).build());
+
+// the ticket events partners are notified of, other updates stay internal
+pub fn webhook_event(event: &TicketEvent) -> Option<&'static str> {
+	match (&event.kind, event.status.as_str()) {
+		(TicketEventKind::Created, _) => Some("ticket.created"),
+		(TicketEventKind::StatusChanged, "Finalized") => Some("ticket.finalized"),
+		(TicketEventKind::StatusChanged, "Failed") => Some("ticket.failed"),
+		_ => None,
+	}
+}
+
+pub fn payload(event_type: &str, event: &TicketEvent) -> Value {
+	json!({ "type": event_type, "data": event })
+}
+
+// hex hmac-sha256 of `{timestamp}.{body}`, receivers recompute it with their secret
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+	let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+	let tag = hmac::sign(&key, format!("{}.{}", timestamp, body).as_bytes());
+	format!("sha256={}", hex::encode(tag.as_ref()))
+}
+
+// seconds to wait after the given number of failed attempts
+pub fn retry_delay(attempts: i32) -> i64 {
+	let exponent = attempts.clamp(1, 30) as u32 - 1;
+	WEBHOOK_RETRY_BASE
+		.saturating_mul(2i64.saturating_pow(exponent))
+		.min(WEBHOOK_RETRY_MAX)
+}
+
+pub fn new_secret() -> String {
+	hex::encode(rand::random::<[u8; 32]>())
+}
+
+pub async fn send(
+	client: &Client,
+	endpoint: &webhook_endpoint::Model,
+	delivery: &webhook_delivery::Model,
+) -> Result<()> {
+	let body = delivery.payload.to_string();
+	let timestamp = Utc::now().timestamp();
+	let response = client
+		.post(&endpoint.url)
+		.header(reqwest::header::CONTENT_TYPE, "application/json")
+		.header(EVENT_HEADER, &delivery.event)
+		.header(DELIVERY_HEADER, delivery.id.to_string())
+		.header(TIMESTAMP_HEADER, timestamp.to_string())
+		.header(SIGNATURE_HEADER, sign(&endpoint.secret, timestamp, &body))
+		.body(body)
+		.send()
+		.await?;
+	if !response.status().is_success() {
+		return Err(anyhow!("endpoint answered {}", response.status()));
+	}
+	Ok(())
+}
+
+// posts the due deliveries, a failed one is retried later or dead-lettered past
+// `WEBHOOK_MAX_ATTEMPTS`
+pub async fn deliver_webhooks(db: &DbConn) -> Result<(), Box<dyn Error>> {
+	let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+	let client = HTTP_CLIENT
+		.as_ref()
+		.map_err(|e| format!("webhook http client: {}", e))?;
+	let deliveries = Query::get_due_webhook_deliveries(db, now, WEBHOOK_BATCH_SIZE).await?;
+	for (delivery, endpoint) in deliveries {
+		let result = match &endpoint {
+			Some(endpoint) => send(client, endpoint, &delivery).await,
+			None => Err(anyhow!("endpoint {} is gone", delivery.endpoint_id)),
+		};
+		let now = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+		match result {
+			Ok(()) => Mutation::mark_webhook_delivered(db, delivery.id, now).await?,
+			Err(e) => {
+				let attempts = delivery.attempts + 1;
+				let next_attempt_at = (attempts < WEBHOOK_MAX_ATTEMPTS)
+					.then(|| now + retry_delay(attempts) * 1_000_000_000);
+				info!(
+					"webhook delivery failed: id={}, endpoint={}, attempts={}, error={}",
+					delivery.id, delivery.endpoint_id, attempts, e
+				);
+				Mutation::save_webhook_delivery_failure(
+					db,
+					delivery.id,
+					attempts,
+					e.to_string(),
+					next_attempt_at,
+				)
+				.await?;
+			}
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entity::fixtures;
+	use crate::entity::sea_orm_active_enums::TicketStatus;
+	use crate::stub_server;
+	use std::sync::{Arc, Mutex};
+
+	fn endpoint(url: String) -> webhook_endpoint::Model {
+		webhook_endpoint::Model {
+			id: 1,
+			url,
+			secret: "partner-secret".to_owned(),
+			chain: None,
+			token: None,
+			address: None,
+			active: true,
+			created_at: 0,
+		}
+	}
+
+	#[test]
+	fn only_lifecycle_events_are_sent() {
+		let mut ticket = fixtures::ticket("t1", "Bitcoin", "eICP");
+		assert_eq!(
+			webhook_event(&TicketEvent::created(&ticket)),
+			Some("ticket.created")
+		);
+
+		let before = ticket.clone();
+		ticket.tx_hash = Some("0xabc".to_owned());
+		let event = TicketEvent::changed(&before, &ticket).unwrap();
+		assert_eq!(webhook_event(&event), None);
+
+		ticket.status = TicketStatus::Failed;
+		let event = TicketEvent::changed(&before, &ticket).unwrap();
+		assert_eq!(webhook_event(&event), Some("ticket.failed"));
+	}
+
+	#[test]
+	fn retries_back_off_up_to_a_ceiling() {
+		assert_eq!(retry_delay(1), 30);
+		assert_eq!(retry_delay(2), 60);
+		assert_eq!(retry_delay(5), 480);
+		assert_eq!(retry_delay(WEBHOOK_MAX_ATTEMPTS), WEBHOOK_RETRY_MAX);
+	}
+
+	#[tokio::test]
+	async fn posts_signed_payloads() {
+		let received = Arc::new(Mutex::new(Vec::new()));
+		let url = {
+			let received = received.clone();
+			stub_server::spawn(move |request| {
+				received.lock().unwrap().push(request.clone());
+				match request.path.as_str() {
+					"/hook" => (200, "{}".to_owned()),
+					_ => (500, "{}".to_owned()),
+				}
+			})
+			.await
+		};
+		let ticket = fixtures::ticket("t1", "Bitcoin", "eICP");
+		let delivery = webhook_delivery::Model {
+			id: 7,
+			endpoint_id: 1,
+			event: "ticket.created".to_owned(),
+			ticket_id: "t1".to_owned(),
+			payload: payload("ticket.created", &TicketEvent::created(&ticket)),
+			status: DELIVERY_PENDING.to_owned(),
+			attempts: 0,
+			next_attempt_at: 0,
+			last_error: None,
+			delivered_at: None,
+			created_at: 0,
+		};
+		let client = Client::new();
+
+		send(&client, &endpoint(format!("{}/hook", url)), &delivery)
+			.await
+			.unwrap();
+		assert!(send(&client, &endpoint(format!("{}/down", url)), &delivery)
+			.await
+			.is_err());
+
+		let request = received.lock().unwrap()[0].clone();
+		let timestamp: i64 = request.header(TIMESTAMP_HEADER).unwrap().parse().unwrap();
+		let body = String::from_utf8(request.body.clone()).unwrap();
+		assert_eq!(
+			request.header(SIGNATURE_HEADER),
+			Some(sign("partner-secret", timestamp, &body).as_str())
+		);
+		assert_eq!(request.header(DELIVERY_HEADER), Some("7"));
+		assert_eq!(request.header(EVENT_HEADER), Some("ticket.created"));
+		assert_eq!(request.json()["data"]["ticket_id"], "t1");
+	}
+}